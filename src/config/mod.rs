@@ -1,3 +1,13 @@
+mod loader;
+mod migrate;
+mod routing;
+mod secret;
 mod settings;
+mod validate;
 
+pub use loader::*;
+pub use migrate::*;
+pub use routing::*;
+pub use secret::*;
 pub use settings::*;
+pub use validate::*;
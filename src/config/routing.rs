@@ -0,0 +1,316 @@
+use super::Config;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Where the upload content came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputSource {
+    Stdin,
+    Clipboard,
+    File,
+    Command,
+    /// Received by `pst serve`
+    Serve,
+}
+
+/// A `[[routing]]` entry mapping matching content to a provider group.
+///
+/// Every condition that is set must match; a rule without conditions matches everything.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct RoutingRule {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub glob: Option<GlobMatcher>,
+    #[serde(default)]
+    pub mime: Option<String>,
+    #[serde(default)]
+    pub min_size_mb: Option<u64>,
+    #[serde(default)]
+    pub max_size_mb: Option<u64>,
+    #[serde(default)]
+    pub source: Option<InputSource>,
+    #[serde(default)]
+    pub filename_regex: Option<RegexMatcher>,
+    pub group: String,
+    #[serde(default)]
+    pub expires: Option<String>,
+    #[serde(default)]
+    pub strip_exif: Option<bool>,
+}
+
+/// A `glob` condition, compiled when the config is loaded so a bad pattern is reported
+/// up front.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct GlobMatcher(glob::Pattern);
+
+impl GlobMatcher {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        glob::Pattern::new(pattern)
+            .map(Self)
+            .map_err(|e| format!("Invalid routing glob '{}': {}", pattern, e))
+    }
+
+    /// Matches the whole filename, ignoring case.
+    pub fn is_match(&self, filename: &str) -> bool {
+        let options = glob::MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        self.0.matches_with(filename, options)
+    }
+}
+
+impl TryFrom<String> for GlobMatcher {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Self::new(&pattern)
+    }
+}
+
+impl From<GlobMatcher> for String {
+    fn from(matcher: GlobMatcher) -> Self {
+        matcher.0.as_str().to_string()
+    }
+}
+
+/// A `filename_regex` condition, compiled when the config is loaded.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct RegexMatcher(Regex);
+
+impl RegexMatcher {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        Regex::new(pattern)
+            .map(Self)
+            .map_err(|e| format!("Invalid routing regex '{}': {}", pattern, e))
+    }
+
+    pub fn is_match(&self, filename: &str) -> bool {
+        self.0.is_match(filename)
+    }
+}
+
+impl TryFrom<String> for RegexMatcher {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Self::new(&pattern)
+    }
+}
+
+impl From<RegexMatcher> for String {
+    fn from(matcher: RegexMatcher) -> Self {
+        matcher.0.as_str().to_string()
+    }
+}
+
+/// The properties of an upload that routing rules are matched against.
+#[derive(Debug, Clone)]
+pub struct RoutingContext<'a> {
+    pub filename: Option<&'a str>,
+    pub mime: Option<&'a str>,
+    pub size: u64,
+    pub source: InputSource,
+}
+
+impl RoutingRule {
+    pub fn matches(&self, context: &RoutingContext) -> bool {
+        if !self.extensions.is_empty() {
+            let extension = context
+                .filename
+                .and_then(|name| std::path::Path::new(name).extension())
+                .and_then(|ext| ext.to_str());
+
+            let matched = extension.is_some_and(|ext| {
+                self.extensions
+                    .iter()
+                    .any(|allowed| allowed.trim_start_matches('.').eq_ignore_ascii_case(ext))
+            });
+            if !matched {
+                return false;
+            }
+        }
+
+        if let Some(glob) = &self.glob {
+            if !context.filename.is_some_and(|name| glob.is_match(name)) {
+                return false;
+            }
+        }
+
+        if let Some(pattern) = &self.mime {
+            if !context.mime.is_some_and(|mime| mime_matches(pattern, mime)) {
+                return false;
+            }
+        }
+
+        if let Some(min) = self.min_size_mb {
+            if context.size < min * 1024 * 1024 {
+                return false;
+            }
+        }
+
+        if let Some(max) = self.max_size_mb {
+            if context.size > max * 1024 * 1024 {
+                return false;
+            }
+        }
+
+        if let Some(source) = self.source {
+            if source != context.source {
+                return false;
+            }
+        }
+
+        if let Some(regex) = &self.filename_regex {
+            if !context.filename.is_some_and(|name| regex.is_match(name)) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl Config {
+    /// Returns the first routing rule matching the upload, if any.
+    pub fn find_route(&self, context: &RoutingContext) -> Option<&RoutingRule> {
+        self.routing.iter().find(|rule| rule.matches(context))
+    }
+}
+
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => mime
+            .split('/')
+            .next()
+            .is_some_and(|top| top.eq_ignore_ascii_case(prefix)),
+        None => pattern.eq_ignore_ascii_case(mime),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(filename: &str, size: u64, source: InputSource) -> RoutingContext<'_> {
+        RoutingContext {
+            filename: Some(filename),
+            mime: None,
+            size,
+            source,
+        }
+    }
+
+    fn rule(group: &str) -> RoutingRule {
+        RoutingRule {
+            group: group.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rule_without_conditions_matches_everything() {
+        let rule = rule("files");
+        assert!(rule.matches(&context("anything.bin", 0, InputSource::Stdin)));
+    }
+
+    #[test]
+    fn test_glob_and_min_size() {
+        let rule = RoutingRule {
+            glob: Some(GlobMatcher::new("*.log").unwrap()),
+            min_size_mb: Some(5),
+            ..rule("internal")
+        };
+
+        let big = 6 * 1024 * 1024;
+        assert!(rule.matches(&context("app.log", big, InputSource::File)));
+        assert!(rule.matches(&context("APP.LOG", big, InputSource::File)));
+        assert!(!rule.matches(&context("app.log", 1024, InputSource::File)));
+        assert!(!rule.matches(&context("app.txt", big, InputSource::File)));
+    }
+
+    #[test]
+    fn test_extensions_ignore_leading_dot_and_case() {
+        let rule = RoutingRule {
+            extensions: vec![".PNG".to_string(), "jpg".to_string()],
+            ..rule("images")
+        };
+
+        assert!(rule.matches(&context("shot.png", 1, InputSource::File)));
+        assert!(rule.matches(&context("shot.JPG", 1, InputSource::File)));
+        assert!(!rule.matches(&context("shot.gif", 1, InputSource::File)));
+    }
+
+    #[test]
+    fn test_mime_wildcard() {
+        let rule = RoutingRule {
+            mime: Some("image/*".to_string()),
+            ..rule("images")
+        };
+
+        let mut ctx = context("x", 1, InputSource::Clipboard);
+        ctx.mime = Some("image/png");
+        assert!(rule.matches(&ctx));
+        ctx.mime = Some("text/plain");
+        assert!(!rule.matches(&ctx));
+        ctx.mime = None;
+        assert!(!rule.matches(&ctx));
+    }
+
+    #[test]
+    fn test_source_and_filename_regex() {
+        let rule = RoutingRule {
+            source: Some(InputSource::Clipboard),
+            filename_regex: Some(RegexMatcher::new(r"^Screenshot").unwrap()),
+            ..rule("screenshots")
+        };
+
+        assert!(rule.matches(&context("Screenshot 1.png", 1, InputSource::Clipboard)));
+        assert!(!rule.matches(&context("Screenshot 1.png", 1, InputSource::File)));
+        assert!(!rule.matches(&context("photo.png", 1, InputSource::Clipboard)));
+    }
+
+    #[test]
+    fn test_invalid_patterns_are_rejected_when_loaded() {
+        let error =
+            toml::from_str::<RoutingRule>("filename_regex = \"(\"\ngroup = \"files\"").unwrap_err();
+        assert!(error.to_string().contains("Invalid routing regex '('"));
+
+        let error = toml::from_str::<RoutingRule>("glob = \"[a\"\ngroup = \"files\"").unwrap_err();
+        assert!(error.to_string().contains("Invalid routing glob '[a'"));
+
+        let rule: RoutingRule =
+            toml::from_str("glob = \"*.log\"\nfilename_regex = \"^app\"\ngroup = \"logs\"")
+                .unwrap();
+        assert!(rule.matches(&context("app.log", 1, InputSource::File)));
+        let value = toml::Value::try_from(&rule).unwrap();
+        assert_eq!(value["glob"].as_str(), Some("*.log"));
+        assert_eq!(value["filename_regex"].as_str(), Some("^app"));
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let config = Config {
+            routing: vec![
+                RoutingRule {
+                    extensions: vec!["log".to_string()],
+                    ..rule("logs")
+                },
+                rule("catch_all"),
+            ],
+            ..Default::default()
+        };
+
+        let route = config.find_route(&context("build.log", 1, InputSource::Stdin));
+        assert_eq!(route.map(|r| r.group.as_str()), Some("logs"));
+
+        let route = config.find_route(&context("notes.md", 1, InputSource::Stdin));
+        assert_eq!(route.map(|r| r.group.as_str()), Some("catch_all"));
+    }
+}
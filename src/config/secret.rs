@@ -0,0 +1,301 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::process::Command;
+use std::sync::OnceLock;
+use thiserror::Error;
+
+/// Keyring service that `pst config set-secret` stores entries under.
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "pst";
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("environment variable {0} is not set")]
+    MissingEnv(String),
+
+    #[error("command `{command}` failed: {message}")]
+    Command { command: String, message: String },
+
+    #[error("cannot read {path}: {source}")]
+    File {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[error("keyring entry '{name}': {message}")]
+    Keyring { name: String, message: String },
+}
+
+/// A password or key in the config, either inline or looked up when it is needed.
+///
+/// ```toml
+/// password = "hunter2"
+/// password = { env = "PST_FTP_PASSWORD" }
+/// password = { command = "pass show pst/ftp" }
+/// password = { file = "~/.secrets/ftp" }
+/// password = { keyring = "ftp" }
+/// ```
+#[derive(Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Secret {
+    Plain(String),
+    Env { env: String },
+    Command { command: String },
+    File { file: String },
+    Keyring { keyring: String },
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Secret::Plain(_) => f.write_str("Plain(<redacted>)"),
+            Secret::Env { env } => write!(f, "Env({})", env),
+            Secret::Command { command } => write!(f, "Command({})", command),
+            Secret::File { file } => write!(f, "File({})", file),
+            Secret::Keyring { keyring } => write!(f, "Keyring({})", keyring),
+        }
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Secret::Plain(value.to_string())
+    }
+}
+
+impl Default for Secret {
+    fn default() -> Self {
+        Secret::Plain(String::new())
+    }
+}
+
+impl Secret {
+    /// Hides an inline value for display. Lookups are shown as they are, since they
+    /// only say where the secret lives.
+    pub fn masked(&self) -> Secret {
+        match self {
+            Secret::Plain(value) if !value.is_empty() => Secret::Plain("********".to_string()),
+            other => other.clone(),
+        }
+    }
+
+    /// Looks up the value. Commands and keyring lookups may prompt the user to unlock.
+    pub fn resolve(&self) -> Result<String, SecretError> {
+        match self {
+            Secret::Plain(value) => Ok(value.clone()),
+            Secret::Env { env } => {
+                std::env::var(env).map_err(|_| SecretError::MissingEnv(env.clone()))
+            }
+            Secret::Command { command } => run_command(command),
+            Secret::File { file } => {
+                let path = shellexpand::tilde(file).into_owned();
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|source| SecretError::File { path, source })?;
+                Ok(trim_newline(contents))
+            }
+            Secret::Keyring { keyring } => keyring_get(keyring),
+        }
+    }
+}
+
+/// A secret that is resolved on first use and then cached, so each one is only
+/// unlocked for the providers an upload actually tries.
+pub struct LazySecret {
+    secret: Secret,
+    value: OnceLock<String>,
+}
+
+impl LazySecret {
+    pub fn new(secret: Secret) -> Self {
+        Self {
+            secret,
+            value: OnceLock::new(),
+        }
+    }
+
+    pub fn get(&self) -> Result<&str, SecretError> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+        let value = self.secret.resolve()?;
+        Ok(self.value.get_or_init(|| value))
+    }
+}
+
+fn trim_newline(mut value: String) -> String {
+    while value.ends_with('\n') || value.ends_with('\r') {
+        value.pop();
+    }
+    value
+}
+
+fn run_command(command: &str) -> Result<String, SecretError> {
+    let error = |message: String| SecretError::Command {
+        command: command.to_string(),
+        message,
+    };
+
+    #[cfg(windows)]
+    let output = Command::new("cmd").args(["/C", command]).output();
+    #[cfg(not(windows))]
+    let output = Command::new("sh").args(["-c", command]).output();
+
+    let output = output.map_err(|e| error(e.to_string()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(error(match stderr.trim() {
+            "" => output.status.to_string(),
+            stderr => stderr.to_string(),
+        }));
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| error("output is not valid UTF-8".to_string()))?;
+    Ok(trim_newline(stdout))
+}
+
+/// Runs a keyring operation on its own thread, since the Secret Service backend
+/// starts a runtime of its own and can't be called from inside tokio.
+#[cfg(feature = "keyring")]
+fn with_keyring<T: Send + 'static>(
+    name: &str,
+    operation: impl FnOnce(keyring::Entry) -> keyring::Result<T> + Send + 'static,
+) -> Result<T, SecretError> {
+    let entry_name = name.to_string();
+    let result = std::thread::spawn(move || {
+        keyring::Entry::new(KEYRING_SERVICE, &entry_name).and_then(operation)
+    })
+    .join()
+    .unwrap_or_else(|_| {
+        Err(keyring::Error::PlatformFailure(
+            "keyring thread panicked".into(),
+        ))
+    });
+
+    result.map_err(|e| SecretError::Keyring {
+        name: name.to_string(),
+        message: e.to_string(),
+    })
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_get(name: &str) -> Result<String, SecretError> {
+    with_keyring(name, |entry| entry.get_password())
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_get(name: &str) -> Result<String, SecretError> {
+    Err(no_keyring(name))
+}
+
+/// Stores a secret in the OS keyring for use as `{ keyring = "<name>" }`.
+#[cfg(feature = "keyring")]
+pub fn keyring_set(name: &str, value: &str) -> Result<(), SecretError> {
+    let value = value.to_string();
+    with_keyring(name, move |entry| entry.set_password(&value))
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn keyring_set(name: &str, _value: &str) -> Result<(), SecretError> {
+    Err(no_keyring(name))
+}
+
+#[cfg(not(feature = "keyring"))]
+fn no_keyring(name: &str) -> SecretError {
+    SecretError::Keyring {
+        name: name.to_string(),
+        message: "pst was built without keyring support, rebuild with --features keyring"
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Serialize)]
+    struct Provider {
+        password: Secret,
+    }
+
+    fn parse(value: &str) -> Secret {
+        toml::from_str::<Provider>(&format!("password = {}", value))
+            .unwrap()
+            .password
+    }
+
+    #[test]
+    fn test_secret_forms() {
+        assert_eq!(parse(r#""hunter2""#), Secret::from("hunter2"));
+        assert_eq!(
+            parse(r#"{ env = "PST_TEST" }"#),
+            Secret::Env {
+                env: "PST_TEST".to_string()
+            }
+        );
+        assert_eq!(
+            parse(r#"{ command = "pass show pst/bunny" }"#),
+            Secret::Command {
+                command: "pass show pst/bunny".to_string()
+            }
+        );
+        assert_eq!(
+            parse(r#"{ keyring = "bunny" }"#),
+            Secret::Keyring {
+                keyring: "bunny".to_string()
+            }
+        );
+        assert!(toml::from_str::<Provider>(r#"password = { vault = "x" }"#).is_err());
+
+        // Inline secrets keep their plain form when the config is written back
+        let written = toml::to_string(&Provider {
+            password: Secret::from("hunter2"),
+        })
+        .unwrap();
+        assert_eq!(written.trim(), r#"password = "hunter2""#);
+        assert_eq!(
+            format!("{:?}", Secret::from("hunter2")),
+            "Plain(<redacted>)"
+        );
+    }
+
+    #[test]
+    fn test_resolve_env_and_file() {
+        std::env::set_var("PST_TEST_SECRET_ENV", "from-env");
+        let env = Secret::Env {
+            env: "PST_TEST_SECRET_ENV".to_string(),
+        };
+        assert_eq!(env.resolve().unwrap(), "from-env");
+
+        let missing = Secret::Env {
+            env: "PST_TEST_SECRET_MISSING".to_string(),
+        };
+        assert!(matches!(missing.resolve(), Err(SecretError::MissingEnv(_))));
+
+        let path = std::env::temp_dir().join(format!("pst-secret-{}", std::process::id()));
+        std::fs::write(&path, "from-file\n").unwrap();
+        let file = Secret::File {
+            file: path.to_string_lossy().into_owned(),
+        };
+        assert_eq!(file.resolve().unwrap(), "from-file");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_command_once() {
+        let path = std::env::temp_dir().join(format!("pst-secret-count-{}", std::process::id()));
+        let secret = LazySecret::new(Secret::Command {
+            command: format!("echo x >> {}; echo from-command", path.display()),
+        });
+        assert_eq!(secret.get().unwrap(), "from-command");
+        assert_eq!(secret.get().unwrap(), "from-command");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "x\n");
+        std::fs::remove_file(&path).unwrap();
+
+        let failing = Secret::Command {
+            command: "echo locked >&2; exit 1".to_string(),
+        };
+        let error = failing.resolve().unwrap_err().to_string();
+        assert!(error.contains("locked"), "{}", error);
+    }
+}
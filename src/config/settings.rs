@@ -1,3 +1,7 @@
+use super::{RoutingRule, Secret, CONFIG_VERSION};
+use crate::providers::TransferProtocol;
+use crate::render::PageTheme;
+use crate::transform::ConvertFormat;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -7,11 +11,9 @@ use thiserror::Error;
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("Config file not found: {0}")]
-    #[allow(dead_code)]
     NotFound(PathBuf),
 
     #[error("Failed to parse config: {0}")]
-    #[allow(dead_code)]
     ParseError(String),
 
     #[error("Invalid config value: {0}")]
@@ -29,10 +31,24 @@ pub enum ConfigError {
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct Config {
+    /// Layout of the file, see `CONFIG_VERSION`. Older files are migrated when loaded.
+    #[serde(default)]
+    pub version: u32,
     pub general: GeneralConfig,
     pub providers: HashMap<String, ProviderConfig>,
     #[serde(default)]
     pub provider_groups: HashMap<String, ProviderGroupConfig>,
+    #[serde(default)]
+    pub secret_scan: SecretScanConfig,
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    #[serde(default)]
+    pub render: RenderConfig,
+    /// Named output templates for `--format`, e.g. `slack = "<{url}|{name}>"`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub templates: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routing: Vec<RoutingRule>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -52,7 +68,7 @@ pub struct GeneralConfig {
 pub enum ProviderConfig {
     #[serde(rename = "http")]
     Http(HttpProviderConfig),
-    #[serde(rename = "ftp_sftp")]
+    #[serde(rename = "ftp", alias = "ftp_sftp")]
     FtpSftp(FTPSFTPProviderConfig),
     #[serde(rename = "bunny")]
     Bunny(BunnyProviderConfig),
@@ -70,6 +86,27 @@ impl ProviderConfig {
         }
     }
 
+    /// Trusted providers are private storage that may receive content with secrets.
+    pub fn is_trusted(&self) -> bool {
+        match self {
+            ProviderConfig::Http(config) => config.trusted,
+            ProviderConfig::FtpSftp(config) => config.trusted,
+            ProviderConfig::Bunny(config) => config.trusted,
+            ProviderConfig::S3(config) => config.trusted,
+        }
+    }
+
+    /// How HTML uploads are made safe. Public paste services serve uploads from their own
+    /// domains, so HTML sent to them is left alone.
+    pub fn html_policy(&self) -> HtmlPolicy {
+        match self {
+            ProviderConfig::Http(_) => HtmlPolicy::Allow,
+            ProviderConfig::FtpSftp(config) => config.html,
+            ProviderConfig::Bunny(config) => config.html,
+            ProviderConfig::S3(config) => config.html,
+        }
+    }
+
     #[allow(dead_code)]
     pub fn get_max_file_size(&self) -> u64 {
         match self {
@@ -81,6 +118,21 @@ impl ProviderConfig {
     }
 }
 
+/// What to do with HTML uploads, which could run scripts on the provider's domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HtmlPolicy {
+    /// Remove scripts, event handlers and embedded documents
+    #[default]
+    Sanitize,
+    /// Serve the page as a download; providers that can't set headers sanitize instead
+    Attachment,
+    /// Keep the page as is but add a Content Security Policy that blocks scripts
+    Csp,
+    /// Upload the page unchanged
+    Allow,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct HttpProviderConfig {
     #[serde(default = "default_enabled")]
@@ -93,6 +145,8 @@ pub struct HttpProviderConfig {
     pub userhash: Option<String>,
     #[serde(default = "default_expiration")]
     pub default_expiration: String,
+    #[serde(default)]
+    pub trusted: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -103,9 +157,9 @@ pub struct FTPSFTPProviderConfig {
     #[serde(default = "default_port")]
     pub port: u16,
     pub username: String,
-    pub password: Option<String>,
+    pub password: Option<Secret>,
     pub ssh_private_key: Option<String>,
-    pub ssh_key_passphrase: Option<String>,
+    pub ssh_key_passphrase: Option<Secret>,
     pub directory: String,
     pub public_url: String,
     #[serde(default = "default_directory_mode")]
@@ -115,13 +169,13 @@ pub struct FTPSFTPProviderConfig {
     #[serde(default = "default_ascii_mode")]
     pub ascii_mode_for_pastes: bool,
     #[serde(default)]
-    pub enable_ftp: bool,
-    #[serde(default)]
-    pub enable_ftps: bool,
-    #[serde(default)]
-    pub enable_sftp: bool,
+    pub protocol: TransferProtocol,
     #[serde(default = "default_expiration")]
     pub default_expiration: String,
+    #[serde(default)]
+    pub trusted: bool,
+    #[serde(default)]
+    pub html: HtmlPolicy,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
@@ -129,12 +183,16 @@ pub struct BunnyProviderConfig {
     #[serde(default = "default_enabled")]
     pub enabled: bool,
     pub storage_zone: String,
-    pub access_key: String,
+    pub access_key: Secret,
     #[serde(default)]
     pub region: Option<String>,
     pub public_url: String,
     #[serde(default = "default_max_file_size")]
     pub max_file_size_mb: u64,
+    #[serde(default)]
+    pub trusted: bool,
+    #[serde(default)]
+    pub html: HtmlPolicy,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -146,7 +204,7 @@ pub struct S3ProviderConfig {
     #[serde(default)]
     pub endpoint: Option<String>,
     pub access_key_id: String,
-    pub secret_access_key: String,
+    pub secret_access_key: Secret,
     pub public_url: String,
     #[serde(default = "default_s3_max_file_size")]
     pub max_file_size_mb: u64,
@@ -154,6 +212,10 @@ pub struct S3ProviderConfig {
     pub multipart_threshold_mb: u64,
     #[serde(default = "default_multipart_chunk_size")]
     pub multipart_chunk_size_mb: u64,
+    #[serde(default)]
+    pub trusted: bool,
+    #[serde(default)]
+    pub html: HtmlPolicy,
 }
 
 impl Default for S3ProviderConfig {
@@ -164,15 +226,46 @@ impl Default for S3ProviderConfig {
             region: "us-east-1".to_string(),
             endpoint: None,
             access_key_id: String::new(),
-            secret_access_key: String::new(),
+            secret_access_key: Secret::from(""),
             public_url: String::new(),
             max_file_size_mb: 5000,
             multipart_threshold_mb: 100,
             multipart_chunk_size_mb: 10,
+            trusted: false,
+            html: HtmlPolicy::default(),
         }
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SecretScanConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_entropy_threshold")]
+    pub entropy_threshold: f64,
+    #[serde(default = "default_entropy_min_length")]
+    pub entropy_min_length: usize,
+    #[serde(default)]
+    pub patterns: Vec<SecretPattern>,
+}
+
+impl Default for SecretScanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            entropy_threshold: default_entropy_threshold(),
+            entropy_min_length: default_entropy_min_length(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SecretPattern {
+    pub name: String,
+    pub regex: String,
+}
+
 fn default_disabled() -> bool {
     false
 }
@@ -221,38 +314,105 @@ fn default_strip_exif() -> bool {
     true
 }
 
+fn default_replacement() -> String {
+    "[REDACTED]".to_string()
+}
+
+fn default_entropy_threshold() -> f64 {
+    4.5
+}
+
+fn default_entropy_min_length() -> usize {
+    20
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct ProviderGroupConfig {
     pub providers: Vec<String>,
+    /// Redaction rules applied to text uploaded through this group
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub redact: Vec<String>,
+    /// Render pastes in this group as HTML pages; overrides `[render] enabled`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub render: Option<bool>,
+    /// Image transforms for uploads routed to this group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<ImageTransformConfig>,
 }
 
-impl Config {
-    pub fn load() -> Result<Self, ConfigError> {
-        let config_path = get_config_path()?;
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct RedactionConfig {
+    #[serde(default)]
+    pub rules: HashMap<String, RedactionRule>,
+}
 
-        if !config_path.exists() {
-            let default_config = Config::default_with_ftp();
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RedactionRule {
+    pub regex: String,
+    #[serde(default = "default_replacement")]
+    pub replacement: String,
+}
 
-            if let Some(parent) = config_path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
+/// `[render]` section: HTML pages generated next to pastes on storage providers.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct RenderConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub theme: PageTheme,
+}
 
-            let config_content =
-                toml::to_string_pretty(&default_config).map_err(ConfigError::TomlSerializeError)?;
-            std::fs::write(&config_path, config_content)?;
+/// Image resizing and re-encoding applied before upload.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImageTransformConfig {
+    /// Shrink images wider than this, keeping the aspect ratio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_width: Option<u32>,
+    /// Shrink images taller than this, keeping the aspect ratio
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_height: Option<u32>,
+    /// Convert to this format; defaults to the original format
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<ConvertFormat>,
+    /// Quality for JPEG, WebP and AVIF output (1-100)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+    /// Recompress PNG output losslessly
+    #[serde(default)]
+    pub optimize_png: bool,
+    /// Rotate images upright using their EXIF orientation
+    #[serde(default = "default_enabled")]
+    pub auto_orient: bool,
+}
 
-            return Ok(default_config);
+impl Default for ImageTransformConfig {
+    fn default() -> Self {
+        Self {
+            max_width: None,
+            max_height: None,
+            format: None,
+            quality: None,
+            optimize_png: false,
+            auto_orient: default_enabled(),
         }
+    }
+}
 
-        let config_content = std::fs::read_to_string(&config_path)?;
-        let config: Config =
-            toml::from_str(&config_content).map_err(ConfigError::TomlParseError)?;
-
-        Ok(config)
+impl ImageTransformConfig {
+    /// Whether any transform is configured.
+    pub fn is_enabled(&self) -> bool {
+        self.max_width.is_some()
+            || self.max_height.is_some()
+            || self.format.is_some()
+            || self.quality.is_some()
+            || self.optimize_png
     }
+}
 
+impl Config {
     pub fn default_with_ftp() -> Self {
         Self {
+            version: CONFIG_VERSION,
             general: GeneralConfig {
                 default_provider: "all".to_string(),
                 timeout_seconds: 30,
@@ -272,7 +432,7 @@ impl Config {
                         host: "ftp.example.com".to_string(),
                         port: 22,
                         username: "username".to_string(),
-                        password: Some("password".to_string()),
+                        password: Some(Secret::from("password")),
                         ssh_private_key: Some("~/.ssh/id_rsa".to_string()),
                         ssh_key_passphrase: None,
                         directory: "/public_html/uploads".to_string(),
@@ -280,10 +440,10 @@ impl Config {
                         directory_mode: "create_if_missing".to_string(),
                         max_file_size_mb: 1000,
                         ascii_mode_for_pastes: true,
-                        enable_ftp: false,
-                        enable_ftps: false,
-                        enable_sftp: true,
+                        protocol: TransferProtocol::Sftp,
                         default_expiration: "1h".to_string(),
+                        trusted: false,
+                        html: HtmlPolicy::default(),
                     }),
                 );
 
@@ -296,6 +456,7 @@ impl Config {
                         ascii_mode_for_pastes: true,
                         userhash: None,
                         default_expiration: "1h".to_string(),
+                        trusted: false,
                     }),
                 );
 
@@ -307,6 +468,7 @@ impl Config {
                         ascii_mode_for_pastes: true,
                         userhash: None,
                         default_expiration: "1h".to_string(),
+                        trusted: false,
                     }),
                 );
 
@@ -318,6 +480,7 @@ impl Config {
                         ascii_mode_for_pastes: true,
                         userhash: None,
                         default_expiration: "1h".to_string(),
+                        trusted: false,
                     }),
                 );
 
@@ -329,6 +492,7 @@ impl Config {
                         ascii_mode_for_pastes: true,
                         userhash: None,
                         default_expiration: "1h".to_string(),
+                        trusted: false,
                     }),
                 );
 
@@ -338,10 +502,12 @@ impl Config {
                     ProviderConfig::Bunny(BunnyProviderConfig {
                         enabled: false,
                         storage_zone: "your-storage-zone".to_string(),
-                        access_key: "your-access-key".to_string(),
+                        access_key: Secret::from("your-access-key"),
                         region: None,
                         public_url: "https://cdn.example.com/files".to_string(),
                         max_file_size_mb: 500,
+                        trusted: false,
+                        html: HtmlPolicy::default(),
                     }),
                 );
 
@@ -354,11 +520,13 @@ impl Config {
                         region: "us-east-1".to_string(),
                         endpoint: None,
                         access_key_id: "AKIA...".to_string(),
-                        secret_access_key: "...".to_string(),
+                        secret_access_key: Secret::from("..."),
                         public_url: "https://my-bucket.s3.amazonaws.com".to_string(),
                         max_file_size_mb: 5000,
                         multipart_threshold_mb: 100,
                         multipart_chunk_size_mb: 10,
+                        trusted: false,
+                        html: HtmlPolicy::default(),
                     }),
                 );
 
@@ -378,6 +546,9 @@ impl Config {
                             "x0at".to_string(),
                             "uguu".to_string(),
                         ],
+                        redact: Vec::new(),
+                        render: None,
+                        transform: None,
                     },
                 );
                 map.insert(
@@ -390,6 +561,9 @@ impl Config {
                             "paste_rs".to_string(),
                             "x0at".to_string(),
                         ],
+                        redact: Vec::new(),
+                        render: None,
+                        transform: None,
                     },
                 );
                 map.insert(
@@ -403,18 +577,149 @@ impl Config {
                             "x0at".to_string(),
                             "uguu".to_string(),
                         ],
+                        redact: Vec::new(),
+                        render: None,
+                        transform: None,
                     },
                 );
                 map
             },
+            secret_scan: SecretScanConfig::default(),
+            redaction: RedactionConfig::default(),
+            render: RenderConfig::default(),
+            templates: HashMap::new(),
+            routing: Vec::new(),
         }
     }
 
-    #[allow(dead_code)]
+    /// Starting config for `pst config init` with only the named providers enabled.
+    ///
+    /// Enabled storage providers read their secrets from `PST_<PROVIDER>_<FIELD>`
+    /// environment variables instead of keeping the example placeholders.
+    pub fn init_with(enabled: &[String]) -> Result<Config, ConfigError> {
+        let mut config = Config::default_with_ftp();
+        if let Some(unknown) = enabled
+            .iter()
+            .find(|name| !config.providers.contains_key(*name))
+        {
+            let mut names: Vec<&String> = config.providers.keys().collect();
+            names.sort();
+            return Err(ConfigError::InvalidValue(format!(
+                "Unknown provider '{}'. Available: {}",
+                unknown,
+                names
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+
+        for (name, provider) in config.providers.iter_mut() {
+            let enable = enabled.contains(name);
+            let env = |field: &str| Secret::Env {
+                env: format!("PST_{}_{}", name.to_uppercase(), field.to_uppercase()),
+            };
+            match provider {
+                ProviderConfig::Http(http) => http.enabled = enable,
+                ProviderConfig::FtpSftp(ftp) => {
+                    ftp.enabled = enable;
+                    if enable {
+                        ftp.password = Some(env("password"));
+                    }
+                }
+                ProviderConfig::Bunny(bunny) => {
+                    bunny.enabled = enable;
+                    if enable {
+                        bunny.access_key = env("access_key");
+                    }
+                }
+                ProviderConfig::S3(s3) => {
+                    s3.enabled = enable;
+                    if enable {
+                        s3.secret_access_key = env("secret_access_key");
+                    }
+                }
+            }
+        }
+        Ok(config)
+    }
+
+    /// Formats the config as TOML.
+    ///
+    /// Goes through `toml::Value`, which writes plain values before tables, since secrets
+    /// like `{ env = "..." }` are tables that can sit between plain values in a struct.
+    pub fn to_toml(&self) -> Result<String, ConfigError> {
+        let value = toml::Value::try_from(self)?;
+        Ok(toml::to_string_pretty(&value)?)
+    }
+
+    /// A copy for display, with inline passwords and keys hidden.
+    pub fn masked(&self) -> Config {
+        let mut config = self.clone();
+        for provider in config.providers.values_mut() {
+            match provider {
+                ProviderConfig::Http(http) => {
+                    if let Some(userhash) = &mut http.userhash {
+                        *userhash = "********".to_string();
+                    }
+                }
+                ProviderConfig::FtpSftp(ftp) => {
+                    ftp.password = ftp.password.as_ref().map(Secret::masked);
+                    ftp.ssh_key_passphrase = ftp.ssh_key_passphrase.as_ref().map(Secret::masked);
+                }
+                ProviderConfig::Bunny(bunny) => bunny.access_key = bunny.access_key.masked(),
+                ProviderConfig::S3(s3) => s3.secret_access_key = s3.secret_access_key.masked(),
+            }
+        }
+        config
+    }
+
     pub fn get_provider_config(&self, name: &str) -> Option<&ProviderConfig> {
         self.providers.get(name)
     }
 
+    /// Resolves the redaction rules for a group plus any extra rule names, in order.
+    /// Whether pastes uploaded through the group get a rendered HTML page by default.
+    pub fn should_render(&self, group: &str) -> bool {
+        self.provider_groups
+            .get(group)
+            .and_then(|g| g.render)
+            .unwrap_or(self.render.enabled)
+    }
+
+    /// Image transforms for a group; empty when the group has none.
+    pub fn image_transform(&self, group: &str) -> ImageTransformConfig {
+        self.provider_groups
+            .get(group)
+            .and_then(|g| g.transform.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get_redaction_rules(
+        &self,
+        group: &str,
+        extra: &[String],
+    ) -> Result<Vec<(String, &RedactionRule)>, ConfigError> {
+        let group_rules = self
+            .provider_groups
+            .get(group)
+            .map(|g| g.redact.as_slice())
+            .unwrap_or_default();
+
+        let mut rules: Vec<(String, &RedactionRule)> = Vec::new();
+        for name in group_rules.iter().chain(extra) {
+            if rules.iter().any(|(existing, _)| existing == name) {
+                continue;
+            }
+            let rule = self.redaction.rules.get(name).ok_or_else(|| {
+                ConfigError::InvalidValue(format!("Unknown redaction rule: {}", name))
+            })?;
+            rules.push((name.clone(), rule));
+        }
+        Ok(rules)
+    }
+
     pub fn get_provider_group(&self, name: &str) -> Option<&Vec<String>> {
         self.provider_groups.get(name).map(|g| &g.providers)
     }
@@ -440,7 +745,7 @@ impl Config {
     }
 }
 
-fn get_config_path() -> Result<PathBuf, ConfigError> {
+pub(super) fn get_config_path() -> Result<PathBuf, ConfigError> {
     let project_dirs = ProjectDirs::from("", "", "pst").ok_or_else(|| {
         ConfigError::InvalidValue("Could not determine home directory".to_string())
     })?;
@@ -0,0 +1,299 @@
+use super::{
+    merge, migrate, project_config_errors, read_toml, select_profile, Config, ProviderConfig,
+    Secret,
+};
+use crate::template::OutputTemplate;
+use regex::Regex;
+use std::path::Path;
+use toml::Value;
+
+/// Problems found in a config file. Errors stop pst from working as configured,
+/// warnings are worth fixing.
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn extend(&mut self, other: ValidationReport) {
+        self.errors.extend(other.errors);
+        self.warnings.extend(other.warnings);
+    }
+}
+
+/// Checks a complete config file: TOML syntax, value types, every profile, and the
+/// settings that refer to each other.
+pub fn validate_file(path: &Path) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            report.errors.push(format!("{}: {}", path.display(), e));
+            return report;
+        }
+    };
+
+    // Parsing the text directly keeps line and column numbers in the errors
+    let mut value: Value = match toml::from_str(&content) {
+        Ok(value) => value,
+        Err(e) => {
+            report.errors.push(format!("{}: {}", path.display(), e));
+            return report;
+        }
+    };
+    if let Err(e) = toml::from_str::<Config>(&content) {
+        report.errors.push(format!("{}: {}", path.display(), e));
+        return report;
+    }
+
+    match migrate(&mut value) {
+        Ok(changes) if !changes.is_empty() => report.warnings.push(format!(
+            "{}: uses an older config layout, run `pst config migrate` to update it",
+            path.display()
+        )),
+        Ok(_) => {}
+        Err(e) => {
+            report.errors.push(format!("{}: {}", path.display(), e));
+            return report;
+        }
+    }
+
+    let profiles = match &mut value {
+        Value::Table(table) => table.remove("profiles"),
+        _ => None,
+    };
+    match value.clone().try_into::<Config>() {
+        Ok(config) => report.extend(config.check()),
+        Err(e) => report.errors.push(format!("{}: {}", path.display(), e)),
+    }
+
+    let names: Vec<String> = match &profiles {
+        Some(Value::Table(profiles)) => profiles.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+    for name in names {
+        let mut merged = value.clone();
+        let checked = select_profile(profiles.clone(), &name).and_then(|profile| {
+            merge(&mut merged, profile);
+            merged
+                .try_into::<Config>()
+                .map_err(|e| super::ConfigError::ParseError(e.to_string()))
+        });
+        match checked {
+            Ok(config) => {
+                // Only report what the profile itself breaks
+                let profile_report = config.check();
+                let new = |messages: Vec<String>, base: &[String]| -> Vec<String> {
+                    messages
+                        .into_iter()
+                        .filter(|message| !base.contains(message))
+                        .map(|message| format!("profile '{}': {}", name, message))
+                        .collect()
+                };
+                let errors = new(profile_report.errors, &report.errors);
+                let warnings = new(profile_report.warnings, &report.warnings);
+                report.errors.extend(errors);
+                report.warnings.extend(warnings);
+            }
+            Err(e) => report.errors.push(format!("profile '{}': {}", name, e)),
+        }
+    }
+
+    report
+}
+
+/// Checks a project `.pst.toml`, which only holds the settings it overrides.
+pub fn validate_project_file(path: &Path) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    match read_toml(path) {
+        Ok(value) => report.errors.extend(
+            project_config_errors(&value)
+                .into_iter()
+                .map(|error| format!("{}: {}", path.display(), error)),
+        ),
+        Err(e) => report.errors.push(e.to_string()),
+    }
+    report
+}
+
+impl Config {
+    /// Checks settings that refer to each other, such as groups naming providers.
+    pub fn check(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let errors = &mut report.errors;
+
+        let mut groups: Vec<_> = self.provider_groups.iter().collect();
+        groups.sort_by_key(|(name, _)| name.as_str());
+        for (name, group) in groups {
+            for provider in &group.providers {
+                if !self.providers.contains_key(provider) {
+                    errors.push(format!(
+                        "provider_groups.{}: unknown provider '{}'",
+                        name, provider
+                    ));
+                }
+            }
+            for rule in &group.redact {
+                if !self.redaction.rules.contains_key(rule) {
+                    errors.push(format!(
+                        "provider_groups.{}: unknown redaction rule '{}'",
+                        name, rule
+                    ));
+                }
+            }
+        }
+
+        let mut rules: Vec<_> = self.redaction.rules.iter().collect();
+        rules.sort_by_key(|(name, _)| name.as_str());
+        for (name, rule) in rules {
+            if let Err(e) = Regex::new(&rule.regex) {
+                errors.push(format!("redaction.rules.{}: invalid regex: {}", name, e));
+            }
+        }
+
+        for pattern in &self.secret_scan.patterns {
+            if let Err(e) = Regex::new(&pattern.regex) {
+                errors.push(format!(
+                    "secret_scan.patterns '{}': invalid regex: {}",
+                    pattern.name, e
+                ));
+            }
+        }
+
+        for (index, rule) in self.routing.iter().enumerate() {
+            let label = rule
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("#{}", index + 1));
+            if !self.provider_groups.contains_key(&rule.group) {
+                errors.push(format!(
+                    "routing rule {}: unknown group '{}'",
+                    label, rule.group
+                ));
+            }
+        }
+
+        let mut templates: Vec<_> = self.templates.iter().collect();
+        templates.sort_by_key(|(name, _)| name.as_str());
+        for (name, template) in templates {
+            if let Err(e) = OutputTemplate::parse(template) {
+                errors.push(format!("templates.{}: {}", name, e));
+            }
+        }
+
+        let mut providers: Vec<_> = self.providers.iter().collect();
+        providers.sort_by_key(|(name, _)| name.as_str());
+        for (name, provider) in providers {
+            if provider.is_enabled() && has_inline_secret(provider) {
+                report.warnings.push(format!(
+                    "providers.{}: secret is stored in plain text, consider {{ env = \"...\" }} or {{ keyring = \"...\" }}",
+                    name
+                ));
+            }
+        }
+
+        report
+    }
+}
+
+fn has_inline_secret(provider: &ProviderConfig) -> bool {
+    let inline = |secret: &Secret| matches!(secret, Secret::Plain(value) if !value.is_empty());
+    match provider {
+        ProviderConfig::Http(_) => false,
+        ProviderConfig::FtpSftp(ftp) => {
+            ftp.password.as_ref().is_some_and(inline)
+                || ftp.ssh_key_passphrase.as_ref().is_some_and(inline)
+        }
+        ProviderConfig::Bunny(bunny) => inline(&bunny.access_key),
+        ProviderConfig::S3(s3) => inline(&s3.secret_access_key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(name: &str, content: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("pst-validate-{}-{}", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    const VALID: &str = r#"
+version = 2
+
+[general]
+default_provider = "all"
+timeout_seconds = 30
+max_retries = 3
+retry_delay_ms = 1000
+
+[providers.0x0st]
+type = "http"
+
+[provider_groups.files]
+providers = ["0x0st"]
+"#;
+
+    #[test]
+    fn test_syntax_and_type_errors_have_positions() {
+        let path = write("syntax", "[general\ntimeout_seconds = 30\n");
+        let report = validate_file(&path);
+        assert!(report.errors[0].contains("line 1"), "{:?}", report.errors);
+        std::fs::remove_file(path).unwrap();
+
+        let path = write(
+            "types",
+            &VALID.replace("timeout_seconds = 30", "timeout_seconds = \"30\""),
+        );
+        let report = validate_file(&path);
+        assert!(report.errors[0].contains("line 6"), "{:?}", report.errors);
+        std::fs::remove_file(path).unwrap();
+
+        let path = write(
+            "routing",
+            &format!(
+                "{}\n[[routing]]\nfilename_regex = \"(\"\ngroup = \"files\"\n",
+                VALID
+            ),
+        );
+        let report = validate_file(&path);
+        assert!(
+            report.errors[0].contains("Invalid routing regex '('"),
+            "{:?}",
+            report.errors
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_references_are_checked_in_profiles_too() {
+        let path = write(
+            "references",
+            &format!(
+                "{}\n[[routing]]\nglob = \"*.log\"\ngroup = \"logs\"\n\n[profiles.work.provider_groups.files]\nproviders = [\"s3\"]\n",
+                VALID
+            ),
+        );
+        let report = validate_file(&path);
+        assert_eq!(
+            report.errors,
+            vec![
+                "routing rule #1: unknown group 'logs'",
+                "profile 'work': provider_groups.files: unknown provider 's3'",
+            ]
+        );
+        std::fs::remove_file(path).unwrap();
+
+        let path = write("valid", VALID);
+        let report = validate_file(&path);
+        assert!(report.is_ok() && report.warnings.is_empty(), "{:?}", report);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+use crate::config::{
+    config_path, config_version, find_project_config, migrate as migrate_value, read_toml,
+    validate_file, validate_project_file, Config, LoadOptions, ProviderConfig, ValidationReport,
+    CONFIG_VERSION,
+};
+use anyhow::{bail, Context, Result};
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Creates the config file, asking which providers to enable when run in a terminal.
+pub fn init(options: &LoadOptions, enable: &[String], force: bool) -> Result<()> {
+    let path = config_path(options)?;
+    if path.exists() && !force {
+        bail!(
+            "{} already exists. Use `pst config edit` to change it, or --force to start over",
+            path.display()
+        );
+    }
+
+    let interactive = enable.is_empty() && std::io::stdin().is_terminal();
+    let enabled = if !enable.is_empty() {
+        enable.to_vec()
+    } else if interactive {
+        choose_providers()?
+    } else {
+        let defaults = Config::default_with_ftp();
+        let mut names: Vec<String> = defaults
+            .providers
+            .iter()
+            .filter(|(_, provider)| provider.is_enabled())
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    };
+
+    let mut config = Config::init_with(&enabled)?;
+    if interactive {
+        for (name, provider) in config.providers.iter_mut() {
+            if provider.is_enabled() {
+                ask_provider_settings(name, provider)?;
+            }
+        }
+    }
+
+    write_config(&path, &config)?;
+    println!("Wrote {}", path.display());
+
+    let mut storage: Vec<&String> = config
+        .providers
+        .iter()
+        .filter(|(_, provider)| {
+            provider.is_enabled() && !matches!(provider, ProviderConfig::Http(_))
+        })
+        .map(|(name, _)| name)
+        .collect();
+    storage.sort();
+    for name in storage {
+        println!(
+            "  {} reads its secret from the environment, see [providers.{}]. Run `pst config set-secret {}` to use the keyring instead.",
+            name, name, name
+        );
+    }
+    Ok(())
+}
+
+/// Prints the effective config after profiles and overrides, with secrets hidden.
+pub fn show(options: &LoadOptions) -> Result<()> {
+    let config = Config::load(options).context("Failed to load config")?;
+    let path = config_path(options)?;
+
+    if path.exists() {
+        println!("# Config: {}", path.display());
+    } else {
+        println!(
+            "# Config: built-in defaults, run `pst config init` to create {}",
+            path.display()
+        );
+    }
+    if let Some(project) = options.project_dir.as_deref().and_then(find_project_config) {
+        println!("# Project config: {}", project.display());
+    }
+    if let Some(profile) = options
+        .profile
+        .clone()
+        .or_else(|| std::env::var("PST_PROFILE").ok())
+    {
+        println!("# Profile: {}", profile);
+    }
+    println!();
+    print!("{}", config.masked().to_toml()?);
+    Ok(())
+}
+
+/// Checks the config file, the project config and the selected profile.
+pub fn validate(options: &LoadOptions) -> Result<()> {
+    let path = config_path(options)?;
+    let mut report = if path.exists() {
+        validate_file(&path)
+    } else if options.path.is_some() {
+        bail!("Config file not found: {}", path.display());
+    } else {
+        println!(
+            "No config file at {}, using built-in defaults",
+            path.display()
+        );
+        ValidationReport::default()
+    };
+
+    let project = options.project_dir.as_deref().and_then(find_project_config);
+    if let Some(project) = &project {
+        report.extend(validate_project_file(project));
+    }
+
+    // Problems that only appear once everything is layered together
+    if report.is_ok() {
+        match Config::load(options) {
+            Ok(config) => {
+                let effective = config.check();
+                for error in effective.errors {
+                    if !report.errors.contains(&error) {
+                        report.errors.push(error);
+                    }
+                }
+            }
+            Err(e) => report.errors.push(e.to_string()),
+        }
+    }
+
+    print_report(&report);
+    if !report.is_ok() {
+        bail!("{} problem(s) found", report.errors.len());
+    }
+    match project {
+        Some(project) => println!("{} and {} are valid", path.display(), project.display()),
+        None if path.exists() => println!("{} is valid", path.display()),
+        None => {}
+    }
+    Ok(())
+}
+
+/// Opens the config file in `$VISUAL` or `$EDITOR`, saving it only once it is valid.
+pub fn edit(options: &LoadOptions) -> Result<()> {
+    let path = config_path(options)?;
+    if !path.exists() {
+        if options.path.is_some() {
+            bail!("Config file not found: {}", path.display());
+        }
+        write_config(&path, &Config::default_with_ftp())?;
+    }
+
+    // Edit a copy so a broken config never replaces a working one
+    let draft = path.with_extension("edit.toml");
+    std::fs::copy(&path, &draft).with_context(|| format!("Failed to copy {}", path.display()))?;
+
+    loop {
+        if let Err(e) = run_editor(&draft) {
+            let _ = std::fs::remove_file(&draft);
+            return Err(e);
+        }
+
+        let report = validate_file(&draft);
+        print_report(&report);
+        if report.is_ok() {
+            std::fs::rename(&draft, &path)
+                .with_context(|| format!("Failed to save {}", path.display()))?;
+            println!("Saved {}", path.display());
+            return Ok(());
+        }
+
+        if !std::io::stdin().is_terminal() || !confirm("Edit again?", true)? {
+            let _ = std::fs::remove_file(&draft);
+            bail!("Changes discarded, {} was not modified", path.display());
+        }
+    }
+}
+
+/// Upgrades a config file to the current layout, keeping a backup of the original.
+pub fn migrate(options: &LoadOptions, file: Option<&Path>, dry_run: bool) -> Result<()> {
+    let path = match file {
+        Some(file) => file.to_path_buf(),
+        None => config_path(options)?,
+    };
+    if !path.exists() {
+        bail!("Config file not found: {}", path.display());
+    }
+
+    let mut value = read_toml(&path)?;
+    let version = config_version(&value)?;
+    let changes = migrate_value(&mut value)?;
+    if version == CONFIG_VERSION {
+        println!(
+            "{} is already at version {}",
+            path.display(),
+            CONFIG_VERSION
+        );
+        return Ok(());
+    }
+
+    println!("  version: {} -> {}", version, CONFIG_VERSION);
+    for change in &changes {
+        println!("  {}", change);
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    let mut backup = path.clone().into_os_string();
+    backup.push(".bak");
+    let backup = PathBuf::from(backup);
+    std::fs::copy(&path, &backup)
+        .with_context(|| format!("Failed to back up {}", path.display()))?;
+    std::fs::write(&path, toml::to_string_pretty(&value)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    println!(
+        "Migrated {} to version {}. The original, with its comments, is saved as {}",
+        path.display(),
+        CONFIG_VERSION,
+        backup.display()
+    );
+    Ok(())
+}
+
+/// Stores a secret in the keyring and prints the config value that refers to it.
+pub fn set_secret(name: &str) -> Result<()> {
+    // Prompt without echo, or read a line when the secret is piped in
+    let value = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("Secret for '{}': ", name))
+    } else {
+        let mut line = String::new();
+        std::io::stdin()
+            .read_line(&mut line)
+            .map(|_| line.trim_end_matches(['\r', '\n']).to_string())
+    }
+    .context("Failed to read the secret")?;
+    if value.is_empty() {
+        bail!("No secret entered, the keyring was not changed");
+    }
+    crate::config::keyring_set(name, &value)?;
+    eprintln!(
+        "Stored '{}' in the keyring. Use it in config.toml as:",
+        name
+    );
+    println!("{{ keyring = \"{}\" }}", name);
+    Ok(())
+}
+
+fn write_config(path: &Path, config: &Config) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, config.to_toml()?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn print_report(report: &ValidationReport) {
+    for warning in &report.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    for error in &report.errors {
+        eprintln!("error: {}", error);
+    }
+}
+
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+
+    // Run through the shell so editors with arguments, like `code --wait`, work
+    #[cfg(windows)]
+    let status = std::process::Command::new("cmd")
+        .args(["/C", &format!("{} \"{}\"", editor, path.display())])
+        .status();
+    #[cfg(not(windows))]
+    let status = std::process::Command::new("sh")
+        .args(["-c", &format!("{} \"$1\"", editor), "sh"])
+        .arg(path)
+        .status();
+
+    let status = status.with_context(|| format!("Failed to run editor '{}'", editor))?;
+    if !status.success() {
+        bail!("Editor '{}' exited with {}", editor, status);
+    }
+    Ok(())
+}
+
+fn choose_providers() -> Result<Vec<String>> {
+    let defaults = Config::default_with_ftp();
+    let mut names: Vec<(&String, &ProviderConfig)> = defaults.providers.iter().collect();
+    // Public services first, then the storage providers that need settings
+    names.sort_by_key(|(name, provider)| (!matches!(provider, ProviderConfig::Http(_)), *name));
+
+    let mut enabled = Vec::new();
+    for (name, provider) in names {
+        if confirm(&format!("Enable {}?", name), provider.is_enabled())? {
+            enabled.push(name.clone());
+        }
+    }
+    Ok(enabled)
+}
+
+fn ask_provider_settings(name: &str, provider: &mut ProviderConfig) -> Result<()> {
+    match provider {
+        ProviderConfig::Http(_) => {}
+        ProviderConfig::FtpSftp(ftp) => {
+            println!("[providers.{}]", name);
+            ftp.host = ask("Host", &ftp.host)?;
+            ftp.port = ask("Port", &ftp.port.to_string())?
+                .parse()
+                .context("Port must be a number")?;
+            ftp.username = ask("Username", &ftp.username)?;
+            ftp.directory = ask("Directory", &ftp.directory)?;
+            ftp.public_url = ask("Public URL", &ftp.public_url)?;
+        }
+        ProviderConfig::Bunny(bunny) => {
+            println!("[providers.{}]", name);
+            bunny.storage_zone = ask("Storage zone", &bunny.storage_zone)?;
+            let region = ask("Region (empty for Frankfurt)", "")?;
+            bunny.region = Some(region).filter(|region| !region.is_empty());
+            bunny.public_url = ask("Public URL", &bunny.public_url)?;
+        }
+        ProviderConfig::S3(s3) => {
+            println!("[providers.{}]", name);
+            s3.bucket = ask("Bucket", &s3.bucket)?;
+            s3.region = ask("Region", &s3.region)?;
+            let endpoint = ask("Endpoint (empty for AWS)", "")?;
+            s3.endpoint = Some(endpoint).filter(|endpoint| !endpoint.is_empty());
+            s3.access_key_id = ask("Access key ID", &s3.access_key_id)?;
+            s3.public_url = ask("Public URL", &s3.public_url)?;
+        }
+    }
+    Ok(())
+}
+
+fn read_answer(question: &str) -> Result<String> {
+    print!("{} ", question);
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+fn ask(question: &str, default: &str) -> Result<String> {
+    let answer = match default {
+        "" => read_answer(&format!("{}:", question))?,
+        default => read_answer(&format!("{} [{}]:", question, default))?,
+    };
+    Ok(if answer.is_empty() {
+        default.to_string()
+    } else {
+        answer
+    })
+}
+
+fn confirm(question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+    let answer = read_answer(&format!("{} {}", question, hint))?;
+    Ok(match answer.to_ascii_lowercase().as_str() {
+        "" => default,
+        answer => answer.starts_with('y'),
+    })
+}
@@ -0,0 +1,363 @@
+/// Broad classification of sniffed content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    Image,
+    Text,
+    Document,
+    Archive,
+    Audio,
+    Video,
+}
+
+/// Text encodings that can be recognised from a byte order mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// Result of sniffing the leading bytes of some content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sniffed {
+    pub kind: ContentKind,
+    pub mime: &'static str,
+    pub extension: &'static str,
+    pub encoding: Option<TextEncoding>,
+}
+
+impl Sniffed {
+    const fn new(kind: ContentKind, mime: &'static str, extension: &'static str) -> Self {
+        Self {
+            kind,
+            mime,
+            extension,
+            encoding: None,
+        }
+    }
+
+    const fn text(encoding: TextEncoding) -> Self {
+        Self {
+            kind: ContentKind::Text,
+            mime: "text/plain",
+            extension: "txt",
+            encoding: Some(encoding),
+        }
+    }
+}
+
+/// Identifies content from its magic bytes.
+pub fn sniff(data: &[u8]) -> Option<Sniffed> {
+    use ContentKind::*;
+
+    // Text with a byte order mark
+    if data.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Some(Sniffed::text(TextEncoding::Utf8));
+    }
+    if data.starts_with(&[0xFF, 0xFE]) && !data.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) {
+        return Some(Sniffed::text(TextEncoding::Utf16Le));
+    }
+    if data.starts_with(&[0xFE, 0xFF]) {
+        return Some(Sniffed::text(TextEncoding::Utf16Be));
+    }
+
+    // Images
+    if data.starts_with(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return Some(Sniffed::new(Image, "image/png", "png"));
+    }
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(Sniffed::new(Image, "image/jpeg", "jpg"));
+    }
+    if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        return Some(Sniffed::new(Image, "image/gif", "gif"));
+    }
+    if data.starts_with(&[0x49, 0x49, 0x2A, 0x00]) || data.starts_with(&[0x4D, 0x4D, 0x00, 0x2A]) {
+        return Some(Sniffed::new(Image, "image/tiff", "tiff"));
+    }
+    if data.len() >= 14 && data.starts_with(b"BM") && data[6..10] == [0, 0, 0, 0] {
+        return Some(Sniffed::new(Image, "image/bmp", "bmp"));
+    }
+    if data.len() >= 6 && data.starts_with(&[0x00, 0x00, 0x01, 0x00]) && data[4] != 0 {
+        return Some(Sniffed::new(Image, "image/x-icon", "ico"));
+    }
+
+    // RIFF containers
+    if data.len() >= 12 && data.starts_with(b"RIFF") {
+        match &data[8..12] {
+            b"WEBP" => return Some(Sniffed::new(Image, "image/webp", "webp")),
+            b"WAVE" => return Some(Sniffed::new(Audio, "audio/wav", "wav")),
+            b"AVI " => return Some(Sniffed::new(Video, "video/x-msvideo", "avi")),
+            _ => {}
+        }
+    }
+
+    // ISO base media files (MP4, MOV, HEIC, AVIF)
+    if data.len() >= 12 && &data[4..8] == b"ftyp" {
+        return Some(match &data[8..12] {
+            b"heic" | b"heix" | b"heim" | b"heis" | b"mif1" | b"msf1" => {
+                Sniffed::new(Image, "image/heic", "heic")
+            }
+            b"avif" | b"avis" => Sniffed::new(Image, "image/avif", "avif"),
+            b"qt  " => Sniffed::new(Video, "video/quicktime", "mov"),
+            b"M4A " | b"M4B " => Sniffed::new(Audio, "audio/mp4", "m4a"),
+            _ => Sniffed::new(Video, "video/mp4", "mp4"),
+        });
+    }
+
+    // Other media
+    if data.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        let header = &data[..data.len().min(64)];
+        if header.windows(4).any(|w| w == b"webm") {
+            return Some(Sniffed::new(Video, "video/webm", "webm"));
+        }
+        return Some(Sniffed::new(Video, "video/x-matroska", "mkv"));
+    }
+    if data.starts_with(b"ID3")
+        || (data.len() >= 2 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0 && data[1] & 0x06 != 0)
+    {
+        return Some(Sniffed::new(Audio, "audio/mpeg", "mp3"));
+    }
+    if data.starts_with(b"OggS") {
+        return Some(Sniffed::new(Audio, "audio/ogg", "ogg"));
+    }
+    if data.starts_with(b"fLaC") {
+        return Some(Sniffed::new(Audio, "audio/flac", "flac"));
+    }
+
+    // Documents
+    if data.starts_with(b"%PDF-") {
+        return Some(Sniffed::new(Document, "application/pdf", "pdf"));
+    }
+
+    // Archives
+    if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+        return Some(Sniffed::new(Archive, "application/zip", "zip"));
+    }
+    if data.starts_with(&[0x1F, 0x8B]) {
+        return Some(Sniffed::new(Archive, "application/gzip", "gz"));
+    }
+    if data.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
+        return Some(Sniffed::new(Archive, "application/x-7z-compressed", "7z"));
+    }
+    if data.starts_with(b"Rar!\x1A\x07") {
+        return Some(Sniffed::new(Archive, "application/vnd.rar", "rar"));
+    }
+    if data.starts_with(&[0xFD, 0x37, 0x7A, 0x58, 0x5A, 0x00]) {
+        return Some(Sniffed::new(Archive, "application/x-xz", "xz"));
+    }
+    if data.starts_with(b"BZh") {
+        return Some(Sniffed::new(Archive, "application/x-bzip2", "bz2"));
+    }
+    if data.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+        return Some(Sniffed::new(Archive, "application/zstd", "zst"));
+    }
+    if data.len() >= 262 && &data[257..262] == b"ustar" {
+        return Some(Sniffed::new(Archive, "application/x-tar", "tar"));
+    }
+
+    None
+}
+
+/// Decodes BOM-prefixed text into a UTF-8 string.
+pub fn decode_text(data: &[u8], encoding: TextEncoding) -> Option<String> {
+    match encoding {
+        TextEncoding::Utf8 => std::str::from_utf8(data.get(3..)?)
+            .ok()
+            .map(|s| s.to_string()),
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+            let body = data.get(2..)?;
+            if body.len() % 2 != 0 {
+                return None;
+            }
+            let units: Vec<u16> = body
+                .chunks_exact(2)
+                .map(|pair| match encoding {
+                    TextEncoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            String::from_utf16(&units).ok()
+        }
+    }
+}
+
+/// Extension to MIME overrides shared by all providers.
+///
+/// Source code and other plain text formats are served as `text/plain` so browsers
+/// display them instead of downloading.
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("txt", "text/plain"),
+    ("log", "text/plain"),
+    ("md", "text/plain"),
+    ("rs", "text/plain"),
+    ("py", "text/plain"),
+    ("toml", "text/plain"),
+    ("yaml", "text/plain"),
+    ("yml", "text/plain"),
+    ("ini", "text/plain"),
+    ("conf", "text/plain"),
+    ("sh", "text/plain"),
+    ("bat", "text/plain"),
+    ("c", "text/plain"),
+    ("h", "text/plain"),
+    ("cpp", "text/plain"),
+    ("go", "text/plain"),
+    ("java", "text/plain"),
+    ("ts", "text/plain"),
+    ("csv", "text/csv"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "application/javascript"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("bmp", "image/bmp"),
+    ("ico", "image/x-icon"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("heic", "image/heic"),
+    ("avif", "image/avif"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("7z", "application/x-7z-compressed"),
+    ("rar", "application/vnd.rar"),
+    ("xz", "application/x-xz"),
+    ("bz2", "application/x-bzip2"),
+    ("zst", "application/zstd"),
+    ("mp4", "video/mp4"),
+    ("mov", "video/quicktime"),
+    ("webm", "video/webm"),
+    ("mkv", "video/x-matroska"),
+    ("avi", "video/x-msvideo"),
+    ("mp3", "audio/mpeg"),
+    ("m4a", "audio/mp4"),
+    ("ogg", "audio/ogg"),
+    ("flac", "audio/flac"),
+    ("wav", "audio/wav"),
+];
+
+pub const DEFAULT_MIME: &str = "application/octet-stream";
+
+/// Returns the MIME type for a file extension (without the leading dot).
+pub fn mime_for_extension(extension: &str) -> &'static str {
+    let extension = extension.to_lowercase();
+    MIME_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| *mime)
+        .or_else(|| mime_guess::from_ext(&extension).first_raw())
+        .unwrap_or(DEFAULT_MIME)
+}
+
+/// Returns the MIME type for a filename, based on its extension.
+pub fn mime_for_filename(filename: &str) -> &'static str {
+    std::path::Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(mime_for_extension)
+        .unwrap_or(DEFAULT_MIME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_images() {
+        let png = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00];
+        assert_eq!(sniff(&png).map(|s| s.extension), Some("png"));
+        assert_eq!(
+            sniff(&[0xFF, 0xD8, 0xFF, 0xE0]).map(|s| s.mime),
+            Some("image/jpeg")
+        );
+        assert_eq!(
+            sniff(b"GIF89a....").map(|s| s.kind),
+            Some(ContentKind::Image)
+        );
+        assert_eq!(
+            sniff(b"RIFF\x00\x00\x00\x00WEBPVP8 ").map(|s| s.extension),
+            Some("webp")
+        );
+        assert_eq!(
+            sniff(b"\x00\x00\x00\x18ftypheic\x00\x00\x00\x00").map(|s| s.mime),
+            Some("image/heic")
+        );
+    }
+
+    #[test]
+    fn test_sniff_media_and_archives() {
+        assert_eq!(
+            sniff(b"\x00\x00\x00\x18ftypisom\x00\x00\x02\x00").map(|s| s.kind),
+            Some(ContentKind::Video)
+        );
+        assert_eq!(
+            sniff(b"\x00\x00\x00\x14ftypqt  ").map(|s| s.extension),
+            Some("mov")
+        );
+        assert_eq!(
+            sniff(b"%PDF-1.7").map(|s| s.kind),
+            Some(ContentKind::Document)
+        );
+        assert_eq!(sniff(b"PK\x03\x04rest").map(|s| s.extension), Some("zip"));
+        assert_eq!(sniff(&[0x1F, 0x8B, 0x08]).map(|s| s.extension), Some("gz"));
+
+        let mut tar = vec![0u8; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff(&tar).map(|s| s.extension), Some("tar"));
+    }
+
+    #[test]
+    fn test_sniff_plain_text_is_unknown() {
+        assert_eq!(sniff(b"hello world"), None);
+        assert_eq!(sniff(b"BMW is a car brand"), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn test_sniff_and_decode_utf16() {
+        let mut data = vec![0xFF, 0xFE];
+        for unit in "héllo".encode_utf16() {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+
+        let sniffed = sniff(&data).unwrap();
+        assert_eq!(sniffed.kind, ContentKind::Text);
+        assert_eq!(sniffed.encoding, Some(TextEncoding::Utf16Le));
+        assert_eq!(
+            decode_text(&data, TextEncoding::Utf16Le).as_deref(),
+            Some("héllo")
+        );
+    }
+
+    #[test]
+    fn test_decode_utf8_bom() {
+        let data = b"\xEF\xBB\xBFplain";
+        assert_eq!(
+            sniff(data).and_then(|s| s.encoding),
+            Some(TextEncoding::Utf8)
+        );
+        assert_eq!(
+            decode_text(data, TextEncoding::Utf8).as_deref(),
+            Some("plain")
+        );
+    }
+
+    #[test]
+    fn test_mime_registry() {
+        assert_eq!(mime_for_filename("notes.md"), "text/plain");
+        assert_eq!(mime_for_filename("main.RS"), "text/plain");
+        assert_eq!(mime_for_filename("photo.JPEG"), "image/jpeg");
+        assert_eq!(mime_for_filename("*.pdf"), "application/pdf");
+        assert_eq!(mime_for_filename("no_extension"), DEFAULT_MIME);
+        assert_eq!(mime_for_extension("woff2"), "font/woff2");
+        assert_eq!(mime_for_extension("unknownext"), DEFAULT_MIME);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::models::{ProgressCallback, UploadProgress};
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Something that happened during an upload, for programs driving pst as a subprocess.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Started {
+        filename: Option<String>,
+        bytes: u64,
+        group: String,
+    },
+    ProviderAttempt {
+        provider: String,
+        attempt: u32,
+    },
+    Progress {
+        provider: String,
+        bytes: u64,
+        total: u64,
+    },
+    Retry {
+        provider: String,
+        attempt: u32,
+        delay_ms: u64,
+        error: String,
+    },
+    ProviderFailed {
+        provider: String,
+        error: String,
+    },
+    Succeeded {
+        provider: String,
+        url: Option<String>,
+    },
+    Finished {
+        success: bool,
+        url: Option<String>,
+        error: Option<String>,
+    },
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    /// Milliseconds since the Unix epoch
+    timestamp: u64,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Writes events as newline-delimited JSON, one object per line.
+pub struct EventSink {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl EventSink {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn stderr() -> Self {
+        Self::new(Box::new(std::io::stderr()))
+    }
+
+    /// Writes to a file descriptor inherited from the parent process.
+    #[cfg(unix)]
+    pub fn from_fd(fd: i32) -> Result<Self> {
+        use std::os::unix::io::FromRawFd;
+
+        if fd < 0 {
+            anyhow::bail!("Invalid file descriptor: {}", fd);
+        }
+        // The descriptor is owned by pst from here on and closed when it exits
+        let file = unsafe { std::fs::File::from_raw_fd(fd) };
+        Ok(Self::new(Box::new(file)))
+    }
+
+    #[cfg(not(unix))]
+    pub fn from_fd(_fd: i32) -> Result<Self> {
+        anyhow::bail!("--events-fd is only supported on Unix")
+    }
+
+    /// Writes one event. Events are best effort: a closed pipe never fails the upload.
+    pub fn emit(&self, event: Event) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let Ok(mut line) = serde_json::to_string(&Line {
+            timestamp,
+            event: &event,
+        }) else {
+            return;
+        };
+        line.push('\n');
+
+        let mut writer = self.writer.lock().unwrap();
+        let _ = writer.write_all(line.as_bytes());
+        let _ = writer.flush();
+    }
+}
+
+/// Emits progress events, passing updates on to another callback such as a progress bar.
+///
+/// Events are only written when the whole percentage changes, so large uploads don't
+/// flood the stream.
+pub struct EventProgressCallback {
+    sink: Arc<EventSink>,
+    inner: Option<Arc<dyn ProgressCallback>>,
+    last_percent: AtomicU64,
+}
+
+impl EventProgressCallback {
+    pub fn new(sink: Arc<EventSink>, inner: Option<Arc<dyn ProgressCallback>>) -> Self {
+        Self {
+            sink,
+            inner,
+            last_percent: AtomicU64::new(u64::MAX),
+        }
+    }
+}
+
+impl ProgressCallback for EventProgressCallback {
+    fn call(&self, progress: &UploadProgress) {
+        let percent = progress.percentage as u64;
+        if self.last_percent.swap(percent, Ordering::SeqCst) != percent {
+            self.sink.emit(Event::Progress {
+                provider: progress.provider.clone(),
+                bytes: progress.bytes_uploaded,
+                total: progress.total_bytes,
+            });
+        }
+        if let Some(inner) = &self.inner {
+            inner.call(progress);
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(inner) = &self.inner {
+            inner.finish();
+        }
+    }
+
+    fn reset(&self) {
+        self.last_percent.store(u64::MAX, Ordering::SeqCst);
+        if let Some(inner) = &self.inner {
+            inner.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProgressTracker;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(data)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn lines(buffer: &Buffer) -> Vec<serde_json::Value> {
+        let data = buffer.0.lock().unwrap();
+        std::str::from_utf8(&data)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_events_are_one_json_object_per_line() {
+        let buffer = Buffer::default();
+        let sink = EventSink::new(Box::new(buffer.clone()));
+        sink.emit(Event::Retry {
+            provider: "uguu".to_string(),
+            attempt: 1,
+            delay_ms: 1000,
+            error: "Connection failed: timeout".to_string(),
+        });
+        sink.emit(Event::Finished {
+            success: true,
+            url: Some("https://example.com/a.txt".to_string()),
+            error: None,
+        });
+
+        let events = lines(&buffer);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["event"], "retry");
+        assert_eq!(events[0]["delay_ms"], 1000);
+        assert!(events[0]["timestamp"].as_u64().unwrap() > 0);
+        assert_eq!(events[1]["event"], "finished");
+        assert_eq!(events[1]["url"], "https://example.com/a.txt");
+    }
+
+    #[test]
+    fn test_progress_events_are_throttled_to_whole_percentages() {
+        let buffer = Buffer::default();
+        let sink = Arc::new(EventSink::new(Box::new(buffer.clone())));
+        let callback = Arc::new(EventProgressCallback::new(sink, None));
+        let tracker = ProgressTracker::new(1000, callback, "upload".to_string());
+
+        tracker.reset("0x0st", 1000);
+        for _ in 0..10 {
+            tracker.add_progress(1);
+        }
+        tracker.add_progress(990);
+
+        let events = lines(&buffer);
+        let bytes: Vec<u64> = events
+            .iter()
+            .map(|e| e["bytes"].as_u64().unwrap())
+            .collect();
+        assert_eq!(bytes, vec![0, 10, 1000]);
+        assert!(events.iter().all(|e| e["provider"] == "0x0st"));
+    }
+}
@@ -0,0 +1,195 @@
+use super::{categorize, MetadataCategory};
+use exif_reader::{Exif, In, Tag, Value};
+use std::io::Cursor;
+
+/// A single piece of private metadata found in an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataField {
+    pub category: MetadataCategory,
+    pub name: String,
+    pub value: String,
+}
+
+/// Lists the EXIF fields of an image that identify where, when or with what it was taken.
+///
+/// GPS tags are summarized as a single position when the file has one, and an embedded
+/// thumbnail is listed once instead of tag by tag. Formats without EXIF give an empty list.
+pub fn describe_exif(data: &[u8]) -> Vec<MetadataField> {
+    let exif = match exif_reader::Reader::new().read_from_container(&mut Cursor::new(data)) {
+        Ok(exif) => exif,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut fields = Vec::new();
+    let position = gps_position(&exif);
+    if let Some((latitude, longitude)) = position {
+        fields.push(MetadataField {
+            category: MetadataCategory::Location,
+            name: "GPS position".to_string(),
+            value: format!("{:.6}, {:.6}", latitude, longitude),
+        });
+    }
+
+    let mut has_thumbnail = false;
+    for field in exif.fields() {
+        let category = match categorize(field) {
+            Some(MetadataCategory::OtherExif) | None => continue,
+            Some(MetadataCategory::Thumbnail) => {
+                has_thumbnail = true;
+                continue;
+            }
+            Some(category) => category,
+        };
+        if category == MetadataCategory::Location && position.is_some() {
+            continue;
+        }
+        let name = match field.tag {
+            super::XP_AUTHOR => "XPAuthor".to_string(),
+            tag => tag.to_string(),
+        };
+        fields.push(MetadataField {
+            category,
+            name,
+            value: display_value(&exif, field.tag, &field.value),
+        });
+    }
+
+    if has_thumbnail {
+        let size = exif
+            .get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)
+            .and_then(|f| f.value.get_uint(0));
+        fields.push(MetadataField {
+            category: MetadataCategory::Thumbnail,
+            name: "Thumbnail".to_string(),
+            value: match size {
+                Some(size) => format!("JPEG, {} bytes", size),
+                None => "present".to_string(),
+            },
+        });
+    }
+    fields
+}
+
+fn display_value(exif: &Exif, tag: Tag, value: &Value) -> String {
+    match (tag, value) {
+        // Windows XP tags are UTF-16 text stored as bytes
+        (super::XP_AUTHOR, Value::Byte(bytes)) => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .take_while(|&unit| unit != 0)
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => format!(
+            "{}{}",
+            value.display_as(tag).to_string().trim_matches('"'),
+            unit_suffix(exif, tag)
+        ),
+    }
+}
+
+fn unit_suffix(exif: &Exif, tag: Tag) -> String {
+    match tag {
+        Tag::GPSAltitude => match exif
+            .get_field(Tag::GPSAltitudeRef, In::PRIMARY)
+            .and_then(|f| f.value.get_uint(0))
+        {
+            Some(1) => " m below sea level".to_string(),
+            _ => " m".to_string(),
+        },
+        _ => String::new(),
+    }
+}
+
+/// Reads the GPS latitude and longitude as signed decimal degrees.
+fn gps_position(exif: &Exif) -> Option<(f64, f64)> {
+    let coordinate = |tag: Tag, reference: Tag, negative: u8| {
+        let degrees = match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Rational(parts) if parts.len() == 3 => {
+                parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0
+            }
+            _ => return None,
+        };
+        let is_negative = match &exif.get_field(reference, In::PRIMARY)?.value {
+            Value::Ascii(text) => text.first().and_then(|t| t.first()) == Some(&negative),
+            _ => false,
+        };
+        Some(if is_negative { -degrees } else { degrees })
+    };
+
+    let latitude = coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S')?;
+    let longitude = coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W')?;
+    Some((latitude, longitude))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif_reader::{Field, Rational};
+
+    fn tiff_with_exif(fields: &[Field]) -> Vec<u8> {
+        let mut writer = exif_reader::experimental::Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        tiff.into_inner()
+    }
+
+    #[test]
+    fn test_describe_exif_lists_private_fields() {
+        let field = |tag, value| Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        };
+        let ascii = |text: &str| Value::Ascii(vec![text.as_bytes().to_vec()]);
+        let dms = |d: u32, m: u32, s: u32| {
+            Value::Rational(vec![
+                Rational::from((d, 1)),
+                Rational::from((m, 1)),
+                Rational::from((s, 1)),
+            ])
+        };
+        let tiff = tiff_with_exif(&[
+            field(Tag::Make, ascii("Phone")),
+            field(Tag::Artist, ascii("Jane Doe")),
+            field(Tag::BodySerialNumber, ascii("SN123")),
+            field(
+                Tag::ExposureTime,
+                Value::Rational(vec![Rational::from((1, 60))]),
+            ),
+            field(Tag::GPSLatitudeRef, ascii("N")),
+            field(Tag::GPSLatitude, dms(51, 30, 0)),
+            field(Tag::GPSLongitudeRef, ascii("W")),
+            field(Tag::GPSLongitude, dms(0, 7, 30)),
+        ]);
+
+        let fields = describe_exif(&tiff);
+        let summary: Vec<(MetadataCategory, &str, &str)> = fields
+            .iter()
+            .map(|f| (f.category, f.name.as_str(), f.value.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    MetadataCategory::Location,
+                    "GPS position",
+                    "51.500000, -0.125000"
+                ),
+                (MetadataCategory::Camera, "Make", "Phone"),
+                (MetadataCategory::Author, "Artist", "Jane Doe"),
+                (MetadataCategory::Camera, "BodySerialNumber", "SN123"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_exif_without_exif_is_empty() {
+        assert!(describe_exif(b"\x89PNG\r\n\x1a\n").is_empty());
+        assert!(describe_exif(b"plain text").is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+use super::{MetadataCategory, StrippedImage};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeSet;
+
+/// Application extensions needed to display the image: animation looping and ICC profiles.
+const KEPT_APPLICATIONS: [&[u8; 11]; 3] = [b"NETSCAPE2.0", b"ANIMEXTS1.0", b"ICCRGBG1012"];
+
+/// Removes comment extensions and metadata application extensions (such as XMP) from a
+/// GIF, copying every frame unchanged.
+pub(super) fn strip_gif(data: &[u8]) -> Result<StrippedImage> {
+    let mut removed = BTreeSet::new();
+    let truncated = || anyhow!("Truncated GIF file");
+
+    // Header and logical screen descriptor, followed by the global colour table
+    let flags = *data.get(10).ok_or_else(truncated)?;
+    let mut position = 13 + colour_table_size(flags);
+    let mut output = data.get(..position).ok_or_else(truncated)?.to_vec();
+
+    loop {
+        match *data.get(position).ok_or_else(truncated)? {
+            0x21 => {
+                let label = *data.get(position + 1).ok_or_else(truncated)?;
+                let end = skip_sub_blocks(data, position + 2)?;
+                let keep = match label {
+                    0xFE => {
+                        removed.insert(MetadataCategory::Comments);
+                        false
+                    }
+                    0xFF => {
+                        let identifier = data.get(position + 3..position + 14);
+                        let kept = identifier
+                            .is_some_and(|id| KEPT_APPLICATIONS.iter().any(|k| id == &k[..]));
+                        if !kept {
+                            if identifier == Some(&b"XMP DataXMP"[..]) {
+                                removed.insert(MetadataCategory::Xmp);
+                            } else {
+                                removed.insert(MetadataCategory::OtherExif);
+                            }
+                        }
+                        kept
+                    }
+                    // Graphic control and plain text extensions
+                    _ => true,
+                };
+                if keep {
+                    output.extend_from_slice(&data[position..end]);
+                }
+                position = end;
+            }
+            0x2C => {
+                // Image descriptor, local colour table, LZW code size and image data
+                let flags = *data.get(position + 9).ok_or_else(truncated)?;
+                let start = position;
+                position += 10 + colour_table_size(flags) + 1;
+                let end = skip_sub_blocks(data, position)?;
+                output.extend_from_slice(&data[start..end]);
+                position = end;
+            }
+            0x3B => {
+                // Anything after the trailer is dropped
+                output.push(0x3B);
+                break;
+            }
+            other => return Err(anyhow!("Invalid GIF block 0x{:02X}", other)),
+        }
+    }
+
+    Ok(StrippedImage {
+        data: output,
+        removed,
+        rotated: false,
+    })
+}
+
+fn colour_table_size(flags: u8) -> usize {
+    if flags & 0x80 != 0 {
+        3 << ((flags & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+/// Returns the position after a chain of data sub-blocks and its terminator.
+fn skip_sub_blocks(data: &[u8], mut position: usize) -> Result<usize> {
+    loop {
+        let size = *data
+            .get(position)
+            .ok_or_else(|| anyhow!("Truncated GIF data block"))? as usize;
+        position += 1 + size;
+        if size == 0 {
+            return Ok(position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_gif() -> Vec<u8> {
+        let image = image::RgbaImage::from_fn(4, 4, |x, y| {
+            image::Rgba([(x * 60) as u8, (y * 60) as u8, 128, 255])
+        });
+        let mut gif = Vec::new();
+        image::codecs::gif::GifEncoder::new(&mut gif)
+            .encode_frame(image::Frame::new(image))
+            .unwrap();
+        gif
+    }
+
+    #[test]
+    fn test_gif_round_trip_removes_comments_and_xmp() {
+        let original = encode_gif();
+        let insert_at = 13 + colour_table_size(original[10]);
+
+        let mut extensions = vec![0x21, 0xFE, 5];
+        extensions.extend_from_slice(b"hello\0");
+        extensions.extend_from_slice(&[0x21, 0xFF, 11]);
+        extensions.extend_from_slice(b"XMP DataXMP");
+        extensions.extend_from_slice(&[4]);
+        extensions.extend_from_slice(b"<x:>\0");
+        extensions.extend_from_slice(&[0x21, 0xFF, 11]);
+        extensions.extend_from_slice(b"NETSCAPE2.0");
+        extensions.extend_from_slice(&[3, 1, 0, 0, 0]);
+
+        let mut gif = original[..insert_at].to_vec();
+        gif.extend_from_slice(&extensions);
+        gif.extend_from_slice(&original[insert_at..]);
+
+        let result = strip_gif(&gif).unwrap();
+        let text = String::from_utf8_lossy(&result.data);
+        assert!(!text.contains("hello"));
+        assert!(!text.contains("XMP"));
+        assert!(text.contains("NETSCAPE2.0"));
+        assert_eq!(result.data.len(), original.len() + 19);
+        assert_eq!(
+            result.removed.into_iter().collect::<Vec<_>>(),
+            vec![MetadataCategory::Xmp, MetadataCategory::Comments]
+        );
+
+        let before = image::load_from_memory(&original).unwrap().to_rgba8();
+        let after = image::load_from_memory(&result.data).unwrap().to_rgba8();
+        assert_eq!(before, after);
+    }
+}
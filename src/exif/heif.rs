@@ -0,0 +1,329 @@
+use super::isobmff::{read_boxes, read_uint, IsoBox};
+use super::{filter_exif, MetadataCategory, StrippedImage};
+use anyhow::{anyhow, bail, Result};
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+const HEIF_BRANDS: [&[u8; 4]; 8] = [
+    b"heic", b"heix", b"heim", b"heis", b"mif1", b"msf1", b"avif", b"avis",
+];
+
+/// An EXIF block with no tags, used when nothing from the original fits.
+const EMPTY_TIFF: [u8; 14] = *b"MM\0\x2a\0\0\0\x08\0\0\0\0\0\0";
+
+/// Whether the data is a HEIF container (HEIC or AVIF).
+pub(super) fn is_heif(data: &[u8]) -> bool {
+    data.len() >= 12 && &data[4..8] == b"ftyp" && HEIF_BRANDS.iter().any(|b| &data[8..12] == *b)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum MetadataItem {
+    Exif,
+    Xmp,
+}
+
+/// Blanks the EXIF and XMP items of a HEIF image in place.
+///
+/// The items keep their size so no offsets change and the image data is untouched. EXIF
+/// items are replaced with the allow-listed tags; the orientation is dropped because HEIF
+/// viewers rotate using the `irot` property instead.
+pub(super) fn strip_heif(data: &[u8]) -> Result<StrippedImage> {
+    let mut output = data.to_vec();
+    let mut removed = BTreeSet::new();
+
+    let top_level = read_boxes(data, 0..data.len())?;
+    let meta = match top_level.iter().find(|b| &b.kind == b"meta") {
+        Some(meta) => meta,
+        None => {
+            return Ok(StrippedImage {
+                data: output,
+                removed,
+                rotated: false,
+            })
+        }
+    };
+    // `meta` is a full box: version and flags come before its children
+    let children = read_boxes(data, meta.contents.start + 4..meta.contents.end)?;
+    let find = |kind: &[u8; 4]| children.iter().find(|b| &b.kind == kind);
+
+    let items = match find(b"iinf") {
+        Some(iinf) => metadata_items(data, iinf)?,
+        None => Vec::new(),
+    };
+    let locations = match find(b"iloc") {
+        Some(iloc) => item_locations(data, iloc, find(b"idat").map(|b| b.contents.start))?,
+        None => Vec::new(),
+    };
+
+    for (id, item) in items {
+        let extents: Vec<Range<usize>> = locations
+            .iter()
+            .filter(|(item_id, _)| *item_id == id)
+            .map(|(_, range)| range.clone())
+            .filter(|range| range.end <= data.len())
+            .collect();
+        let contents: Vec<u8> = extents
+            .iter()
+            .flat_map(|range| data[range.clone()].iter().copied())
+            .collect();
+        if contents.is_empty() {
+            continue;
+        }
+
+        let replacement = match item {
+            MetadataItem::Exif => {
+                let mut replacement = vec![0; contents.len()];
+                let tiff = contents
+                    .get(..4)
+                    .map(|offset| u32::from_be_bytes([offset[0], offset[1], offset[2], offset[3]]))
+                    .and_then(|offset| contents.get(4 + offset as usize..));
+                let filtered = match tiff {
+                    Some(tiff) => filter_exif(tiff, false, &mut removed),
+                    None => {
+                        removed.insert(MetadataCategory::OtherExif);
+                        None
+                    }
+                };
+                let tiff = filtered
+                    .filter(|f| f.len() + 4 <= contents.len())
+                    .unwrap_or_else(|| EMPTY_TIFF.to_vec());
+                if tiff.len() + 4 <= contents.len() {
+                    replacement[4..4 + tiff.len()].copy_from_slice(&tiff);
+                }
+                replacement
+            }
+            MetadataItem::Xmp => {
+                removed.insert(MetadataCategory::Xmp);
+                // Whitespace is how XMP packets are padded anyway
+                vec![b' '; contents.len()]
+            }
+        };
+
+        let mut written = 0;
+        for range in extents {
+            let length = range.len();
+            output[range].copy_from_slice(&replacement[written..written + length]);
+            written += length;
+        }
+    }
+
+    Ok(StrippedImage {
+        data: output,
+        removed,
+        rotated: false,
+    })
+}
+
+/// Reads the item info box and returns the IDs of EXIF and XMP items.
+fn metadata_items(data: &[u8], iinf: &IsoBox) -> Result<Vec<(u64, MetadataItem)>> {
+    let start = iinf.contents.start;
+    let version = read_uint(data, start, 1)?;
+    let entries_start = if version == 0 { start + 6 } else { start + 8 };
+
+    let mut items = Vec::new();
+    for infe in read_boxes(data, entries_start..iinf.contents.end)? {
+        if &infe.kind != b"infe" {
+            continue;
+        }
+        let mut position = infe.contents.start;
+        let version = read_uint(data, position, 1)?;
+        if version < 2 {
+            continue;
+        }
+        position += 4;
+        let id_size = if version == 2 { 2 } else { 4 };
+        let id = read_uint(data, position, id_size)?;
+        position += id_size + 2;
+        let item_type = data
+            .get(position..position + 4)
+            .ok_or_else(|| anyhow!("Truncated HEIF item info"))?;
+        position += 4;
+
+        if item_type == b"Exif" {
+            items.push((id, MetadataItem::Exif));
+        } else if item_type == b"mime" {
+            // Item name, then the content type
+            let strings = data.get(position..infe.contents.end).unwrap_or_default();
+            let fields: Vec<&[u8]> = strings.split(|&b| b == 0).collect();
+            if fields.get(1) == Some(&&b"application/rdf+xml"[..]) {
+                items.push((id, MetadataItem::Xmp));
+            }
+        }
+    }
+    Ok(items)
+}
+
+/// Reads the item location box and returns the file ranges of each item's extents.
+fn item_locations(
+    data: &[u8],
+    iloc: &IsoBox,
+    idat_start: Option<usize>,
+) -> Result<Vec<(u64, Range<usize>)>> {
+    let mut position = iloc.contents.start;
+    let version = read_uint(data, position, 1)?;
+    if version > 2 {
+        bail!("Unsupported HEIF item location version {}", version);
+    }
+    position += 4;
+
+    let sizes = read_uint(data, position, 1)? as usize;
+    let (offset_size, length_size) = (sizes >> 4, sizes & 0x0F);
+    let sizes = read_uint(data, position + 1, 1)? as usize;
+    let base_offset_size = sizes >> 4;
+    let index_size = if version == 0 { 0 } else { sizes & 0x0F };
+    position += 2;
+
+    let count_size = if version < 2 { 2 } else { 4 };
+    let item_count = read_uint(data, position, count_size)?;
+    position += count_size;
+
+    let mut locations = Vec::new();
+    for _ in 0..item_count {
+        let id = read_uint(data, position, count_size)?;
+        position += count_size;
+        let construction_method = if version == 0 {
+            0
+        } else {
+            position += 2;
+            read_uint(data, position - 2, 2)? & 0x0F
+        };
+        position += 2; // data reference index
+        let base_offset = read_uint(data, position, base_offset_size)?;
+        position += base_offset_size;
+        let extent_count = read_uint(data, position, 2)?;
+        position += 2;
+
+        for _ in 0..extent_count {
+            position += index_size;
+            let offset = read_uint(data, position, offset_size)?;
+            position += offset_size;
+            let length = read_uint(data, position, length_size)?;
+            position += length_size;
+
+            let start = match (construction_method, idat_start) {
+                (0, _) => base_offset.saturating_add(offset),
+                (1, Some(idat)) => (idat as u64).saturating_add(base_offset + offset),
+                // Items built from other items hold no data of their own
+                _ => continue,
+            } as usize;
+            // A zero length extends to the end of the file
+            let end = if length == 0 {
+                data.len()
+            } else {
+                start.saturating_add(length as usize)
+            };
+            locations.push((id, start..end));
+        }
+    }
+    Ok(locations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exif::sample_exif;
+
+    fn iso_box(kind: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+        let mut output = ((contents.len() + 8) as u32).to_be_bytes().to_vec();
+        output.extend_from_slice(kind);
+        output.extend_from_slice(contents);
+        output
+    }
+
+    fn infe(id: u16, item_type: &[u8; 4], extra: &[u8]) -> Vec<u8> {
+        let mut contents = vec![2, 0, 0, 0];
+        contents.extend_from_slice(&id.to_be_bytes());
+        contents.extend_from_slice(&[0, 0]);
+        contents.extend_from_slice(item_type);
+        contents.extend_from_slice(b"\0");
+        contents.extend_from_slice(extra);
+        iso_box(b"infe", &contents)
+    }
+
+    /// A HEIC file with an image item, an EXIF item and an XMP item stored in `mdat`.
+    fn sample_heic() -> (Vec<u8>, Vec<u8>) {
+        let mut exif = vec![0, 0, 0, 0];
+        exif.extend_from_slice(&sample_exif());
+        let xmp = b"<x:xmpmeta><rdf:Description exif:GPSLatitude=\"51,30N\"/></x:xmpmeta>";
+        let pixels = b"compressed image data".to_vec();
+
+        let mut iinf_contents = vec![0, 0, 0, 0, 0, 3];
+        iinf_contents.extend_from_slice(&infe(1, b"hvc1", b""));
+        iinf_contents.extend_from_slice(&infe(2, b"Exif", b""));
+        iinf_contents.extend_from_slice(&infe(3, b"mime", b"application/rdf+xml\0"));
+        let iinf = iso_box(b"iinf", &iinf_contents);
+
+        let ftyp = iso_box(b"ftyp", b"heic\0\0\0\0mif1heic");
+        // iloc: version 0, 4-byte offsets and lengths, no base offset, 3 items
+        let iloc_len = 8 + 4 + 2 + 2 + 3 * 14;
+        let meta_len = 8 + 4 + iinf.len() + iloc_len;
+        let mdat_start = ftyp.len() + meta_len + 8;
+        let extents = [
+            (1u16, mdat_start, pixels.len()),
+            (2, mdat_start + pixels.len(), exif.len()),
+            (3, mdat_start + pixels.len() + exif.len(), xmp.len()),
+        ];
+        let mut iloc_contents = vec![0, 0, 0, 0, 0x44, 0x00, 0, 3];
+        for (id, offset, length) in extents {
+            iloc_contents.extend_from_slice(&id.to_be_bytes());
+            iloc_contents.extend_from_slice(&[0, 0, 0, 1]);
+            iloc_contents.extend_from_slice(&(offset as u32).to_be_bytes());
+            iloc_contents.extend_from_slice(&(length as u32).to_be_bytes());
+        }
+        let iloc = iso_box(b"iloc", &iloc_contents);
+
+        let mut meta_contents = vec![0, 0, 0, 0];
+        meta_contents.extend_from_slice(&iinf);
+        meta_contents.extend_from_slice(&iloc);
+        let meta = iso_box(b"meta", &meta_contents);
+
+        let mut mdat_contents = pixels.clone();
+        mdat_contents.extend_from_slice(&exif);
+        mdat_contents.extend_from_slice(xmp);
+
+        let mut file = ftyp;
+        file.extend_from_slice(&meta);
+        assert_eq!(file.len() + 8, mdat_start);
+        file.extend_from_slice(&iso_box(b"mdat", &mdat_contents));
+        (file, pixels)
+    }
+
+    #[test]
+    fn test_heif_round_trip_blanks_metadata_items() {
+        let (heic, pixels) = sample_heic();
+        assert!(is_heif(&heic));
+
+        let result = strip_heif(&heic).unwrap();
+        assert_eq!(result.data.len(), heic.len());
+        // Everything before the metadata, including the image data, is unchanged
+        let pixels_end = heic
+            .windows(pixels.len())
+            .position(|w| w == pixels)
+            .unwrap()
+            + pixels.len();
+        assert_eq!(result.data[..pixels_end], heic[..pixels_end]);
+
+        let text = String::from_utf8_lossy(&result.data);
+        assert!(!text.contains("SN123"));
+        assert!(!text.contains("GPSLatitude"));
+        assert!(text.contains("Jane Doe"));
+        assert_eq!(
+            result.removed.into_iter().collect::<Vec<_>>(),
+            vec![
+                MetadataCategory::Location,
+                MetadataCategory::Camera,
+                MetadataCategory::Timestamps,
+                MetadataCategory::Xmp
+            ]
+        );
+    }
+
+    #[test]
+    fn test_heif_without_metadata_is_unchanged() {
+        let mut file = iso_box(b"ftyp", b"avif\0\0\0\0avifmif1");
+        file.extend_from_slice(&iso_box(b"mdat", b"data"));
+        assert!(is_heif(&file));
+        assert_eq!(strip_heif(&file).unwrap().data, file);
+        assert!(!is_heif(b"\0\0\0\x18ftypisom\0\0\0\0"));
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::{anyhow, Result};
+use std::ops::Range;
+
+/// A box (atom) in an ISO base media file: MP4, MOV, HEIC or AVIF.
+#[derive(Debug)]
+pub(super) struct IsoBox {
+    /// Offset of the box header
+    pub start: usize,
+    pub kind: [u8; 4],
+    /// Range of the box contents, after the header
+    pub contents: Range<usize>,
+}
+
+/// Reads the boxes laid out one after another in a range of the file.
+pub(super) fn read_boxes(data: &[u8], range: Range<usize>) -> Result<Vec<IsoBox>> {
+    let mut boxes = Vec::new();
+    let mut position = range.start;
+    while position + 8 <= range.end {
+        let size = read_uint(data, position, 4)?;
+        let kind = [
+            data[position + 4],
+            data[position + 5],
+            data[position + 6],
+            data[position + 7],
+        ];
+        let (header, size) = match size {
+            0 => (8, (range.end - position) as u64),
+            1 => (16, read_uint(data, position + 8, 8)?),
+            size => (8, size),
+        };
+        let end = position
+            .checked_add(size as usize)
+            .filter(|&end| end <= range.end && size >= header as u64)
+            .ok_or_else(|| anyhow!("Invalid box size in media file"))?;
+        boxes.push(IsoBox {
+            start: position,
+            kind,
+            contents: position + header..end,
+        });
+        position = end;
+    }
+    Ok(boxes)
+}
+
+/// Reads a big-endian unsigned integer of `size` bytes.
+pub(super) fn read_uint(data: &[u8], position: usize, size: usize) -> Result<u64> {
+    let bytes = data
+        .get(position..position + size)
+        .ok_or_else(|| anyhow!("Truncated box in media file"))?;
+    Ok(bytes.iter().fold(0, |value, &b| (value << 8) | b as u64))
+}
@@ -1,14 +1,78 @@
+mod describe;
+mod gif;
+mod heif;
+mod isobmff;
+mod orientation;
+mod tags;
+mod tiff;
+mod video;
+
+pub use describe::*;
+pub use orientation::*;
+pub use tags::*;
+pub use video::*;
+
 use anyhow::{Context, Result};
 use image::ImageFormat;
+use std::collections::BTreeSet;
 use std::io::{Read, Seek, SeekFrom, Write};
 
-pub fn strip_exif(data: &[u8]) -> Result<Vec<u8>> {
+/// An image or video with its private metadata removed.
+#[derive(Debug)]
+pub struct StrippedImage {
+    pub data: Vec<u8>,
+    pub removed: BTreeSet<MetadataCategory>,
+    /// Whether the pixels were rotated to apply the EXIF orientation
+    pub rotated: bool,
+}
+
+impl StrippedImage {
+    fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            removed: BTreeSet::new(),
+            rotated: false,
+        }
+    }
+
+    /// Describes what was done, e.g. `rotated upright; removed GPS location, timestamps`.
+    pub fn summary(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if self.rotated {
+            parts.push("rotated upright".to_string());
+        }
+        if !self.removed.is_empty() {
+            let removed: Vec<String> = self.removed.iter().map(|c| c.to_string()).collect();
+            parts.push(format!("removed {}", removed.join(", ")));
+        }
+        (!parts.is_empty()).then(|| parts.join("; "))
+    }
+}
+
+/// Removes private metadata (GPS, camera serials, timestamps, comments, XMP, IPTC) from an
+/// image.
+///
+/// Orientation, copyright, colour space and ICC profiles are kept. JPEG, PNG, WebP, GIF,
+/// TIFF and HEIF/AVIF files are stripped without touching the image data, except JPEGs with
+/// an EXIF orientation other than 1, which are rotated upright and re-encoded. Other
+/// formats are re-encoded. MP4 and MOV videos have their location, device and creation
+/// time atoms blanked.
+pub fn strip_exif(data: &[u8]) -> Result<StrippedImage> {
+    if heif::is_heif(data) {
+        return heif::strip_heif(data);
+    }
+    if is_video(data) {
+        return video::strip_video(data);
+    }
+
     let format = detect_format(data)?;
 
     match format {
         ImageFormat::Jpeg => strip_jpeg_exif(data),
         ImageFormat::Png => strip_png_exif(data),
         ImageFormat::WebP => strip_webp_exif(data),
+        ImageFormat::Gif => gif::strip_gif(data),
+        ImageFormat::Tiff => tiff::strip_tiff(data),
         _ => strip_generic(data, format),
     }
 }
@@ -66,7 +130,92 @@ fn skip<R: Seek>(reader: &mut R, n: i64) -> Result<()> {
     Ok(())
 }
 
-fn strip_jpeg_exif(data: &[u8]) -> Result<Vec<u8>> {
+/// Quality used when a JPEG has to be re-encoded to apply its orientation.
+const ROTATED_JPEG_QUALITY: u8 = 95;
+
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+const XMP_HEADERS: [&[u8]; 2] = [
+    b"http://ns.adobe.com/xap/1.0/\0",
+    b"http://ns.adobe.com/xmp/extension/\0",
+];
+const ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+
+fn strip_jpeg_exif(data: &[u8]) -> Result<StrippedImage> {
+    let mut result = StrippedImage::new(Vec::new());
+    let mut exif = None;
+    let mut icc_segments = Vec::new();
+    let stripped = strip_jpeg_segments(data, &mut result.removed, &mut exif, &mut icc_segments)?;
+
+    let orientation = read_orientation(data).unwrap_or(1);
+    let rotated = if orientation != 1 {
+        rotate_jpeg(data, orientation).ok()
+    } else {
+        None
+    };
+    result.rotated = rotated.is_some();
+
+    let mut segments = Vec::new();
+    if let Some(tiff) = exif {
+        // The orientation tag only stays if the pixels couldn't be rotated
+        if let Some(filtered) = filter_exif(&tiff, !result.rotated, &mut result.removed) {
+            let mut payload = EXIF_HEADER.to_vec();
+            payload.extend_from_slice(&filtered);
+            segments.push(jpeg_segment(0xE1, &payload));
+        }
+    }
+    segments.extend(icc_segments);
+
+    result.data = insert_jpeg_segments(rotated.as_deref().unwrap_or(&stripped), &segments);
+    Ok(result)
+}
+
+/// Decodes the JPEG, applies the orientation to the pixels and re-encodes it.
+fn rotate_jpeg(data: &[u8], orientation: u32) -> Result<Vec<u8>> {
+    let image = image::load_from_memory(data).context("Failed to decode image")?;
+    let image = apply_orientation(image, orientation);
+    let mut output = std::io::Cursor::new(Vec::new());
+    image.write_to(
+        &mut output,
+        image::ImageOutputFormat::Jpeg(ROTATED_JPEG_QUALITY),
+    )?;
+    Ok(output.into_inner())
+}
+
+fn jpeg_segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+    let mut segment = vec![0xFF, marker];
+    segment.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    segment.extend_from_slice(payload);
+    segment
+}
+
+/// Inserts segments after the SOI marker and the JFIF header, if there is one.
+fn insert_jpeg_segments(data: &[u8], segments: &[Vec<u8>]) -> Vec<u8> {
+    if segments.is_empty() || data.len() < 4 {
+        return data.to_vec();
+    }
+
+    let mut position = 2;
+    if data[2..4] == [0xFF, 0xE0] && data.len() >= 6 {
+        position += 2 + u16::from_be_bytes([data[4], data[5]]) as usize;
+    }
+    let position = position.min(data.len());
+
+    let mut output = data[..position].to_vec();
+    for segment in segments {
+        output.extend_from_slice(segment);
+    }
+    output.extend_from_slice(&data[position..]);
+    output
+}
+
+/// Copies the image data and JFIF header, collecting the EXIF block and ICC profile
+/// segments and recording which kinds of metadata were dropped.
+fn strip_jpeg_segments(
+    data: &[u8],
+    removed: &mut BTreeSet<MetadataCategory>,
+    exif: &mut Option<Vec<u8>>,
+    icc_segments: &mut Vec<Vec<u8>>,
+) -> Result<Vec<u8>> {
     let mut source = std::io::Cursor::new(data);
     let mut destination = Vec::new();
 
@@ -84,9 +233,17 @@ fn strip_jpeg_exif(data: &[u8]) -> Result<Vec<u8>> {
                 destination.write_all(&marker)?;
                 copy_jpeg_data(&mut source, &mut destination)?;
             }
-            0xD8..=0xD9 => {
+            0xD8 => {
                 destination.write_all(&marker)?;
             }
+            0xD9 => {
+                destination.write_all(&marker)?;
+                // Trailers such as motion photo videos and extra preview images
+                if (source.position() as usize) < data.len() {
+                    removed.insert(MetadataCategory::TrailingData);
+                }
+                break;
+            }
             0xDA => {
                 copy_jpeg_header(&mut source, &mut destination, &marker)?;
                 copy_jpeg_data(&mut source, &mut destination)?;
@@ -112,7 +269,43 @@ fn strip_jpeg_exif(data: &[u8]) -> Result<Vec<u8>> {
                     skip(&mut source, size as i64 - 2)?;
                 }
             }
+            0xE1 | 0xE2 => {
+                let size = read_u16(&mut source)?;
+                let mut payload = vec![0u8; (size as usize).saturating_sub(2)];
+                source.read_exact(&mut payload)?;
+                if marker[1] == 0xE2 {
+                    if payload.starts_with(ICC_HEADER) {
+                        icc_segments.push(jpeg_segment(0xE2, &payload));
+                    }
+                } else if let Some(tiff) = payload.strip_prefix(EXIF_HEADER) {
+                    if exif.is_none() {
+                        *exif = Some(tiff.to_vec());
+                    } else {
+                        removed.insert(MetadataCategory::OtherExif);
+                    }
+                } else if XMP_HEADERS.iter().any(|h| payload.starts_with(h)) {
+                    removed.insert(MetadataCategory::Xmp);
+                }
+            }
+            0xEE => {
+                // The Adobe segment holds the colour transform needed to decode the image
+                let size = read_u16(&mut source)?;
+                let mut payload = vec![0u8; (size as usize).saturating_sub(2)];
+                source.read_exact(&mut payload)?;
+                if payload.starts_with(b"Adobe") {
+                    destination.write_all(&jpeg_segment(0xEE, &payload))?;
+                }
+            }
             _ => {
+                match marker[1] {
+                    0xED => {
+                        removed.insert(MetadataCategory::Iptc);
+                    }
+                    0xFE => {
+                        removed.insert(MetadataCategory::Comments);
+                    }
+                    _ => {}
+                }
                 let size = read_u16(&mut source)?;
                 skip(&mut source, size as i64 - 2)?;
             }
@@ -155,7 +348,8 @@ fn copy_jpeg_data<R: Read + Seek, W: Write>(source: &mut R, destination: &mut W)
     Ok(())
 }
 
-fn strip_png_exif(data: &[u8]) -> Result<Vec<u8>> {
+fn strip_png_exif(data: &[u8]) -> Result<StrippedImage> {
+    let mut removed = BTreeSet::new();
     let mut source = std::io::Cursor::new(data);
     let mut destination = Vec::new();
 
@@ -177,23 +371,80 @@ fn strip_png_exif(data: &[u8]) -> Result<Vec<u8>> {
 
         match &chunk {
             b"IDAT" | b"IEND" | b"IHDR" | b"PLTE" | b"acTL" | b"bKGD" | b"cHRM" | b"cICP"
-            | b"fRAc" | b"fcTL" | b"fdAT" | b"gAMA" | b"gIFg" | b"iCCP" | b"sBIT" | b"sRGB"
-            | b"sTER" | b"tRNS" => {
+            | b"fRAc" | b"fcTL" | b"fdAT" | b"gAMA" | b"gIFg" | b"iCCP" | b"pHYs" | b"sBIT"
+            | b"sRGB" | b"sTER" | b"tRNS" => {
                 destination.write_all(&size.to_be_bytes())?;
                 destination.write_all(&chunk)?;
                 let chunk_size = size as u64 + 4;
                 copy(&mut source, &mut destination, chunk_size)?;
             }
+            b"eXIf" => {
+                let mut contents = vec![0u8; size as usize];
+                source.read_exact(&mut contents)?;
+                skip(&mut source, 4)?;
+                if let Some(filtered) = filter_exif(&contents, true, &mut removed) {
+                    destination.write_all(&png_chunk(b"eXIf", &filtered))?;
+                }
+            }
             _ => {
-                skip(&mut source, size as i64 + 4)?;
+                let mut contents = vec![0u8; size as usize];
+                source.read_exact(&mut contents)?;
+                record_png_chunk(&chunk, &contents, &mut removed);
+                skip(&mut source, 4)?;
             }
         }
     }
 
-    Ok(destination)
+    Ok(StrippedImage {
+        data: destination,
+        removed,
+        rotated: false,
+    })
+}
+
+pub(crate) fn png_chunk(kind: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+    let mut chunk = (contents.len() as u32).to_be_bytes().to_vec();
+    chunk.extend_from_slice(kind);
+    chunk.extend_from_slice(contents);
+    let crc = crc32(&chunk[4..]);
+    chunk.extend_from_slice(&crc.to_be_bytes());
+    chunk
+}
+
+/// CRC-32 as used by PNG chunks.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn record_png_chunk(chunk: &[u8; 4], contents: &[u8], removed: &mut BTreeSet<MetadataCategory>) {
+    match chunk {
+        b"tEXt" | b"zTXt" | b"iTXt" => {
+            if contents.starts_with(b"XML:com.adobe.xmp\0") {
+                removed.insert(MetadataCategory::Xmp);
+            } else {
+                removed.insert(MetadataCategory::Comments);
+            }
+        }
+        b"tIME" => {
+            removed.insert(MetadataCategory::Timestamps);
+        }
+        _ => {}
+    }
 }
 
-fn strip_webp_exif(data: &[u8]) -> Result<Vec<u8>> {
+fn strip_webp_exif(data: &[u8]) -> Result<StrippedImage> {
+    let mut removed = BTreeSet::new();
     let mut source = std::io::Cursor::new(data);
     let mut destination = Vec::new();
 
@@ -206,7 +457,8 @@ fn strip_webp_exif(data: &[u8]) -> Result<Vec<u8>> {
     }
 
     let mut webp_data = Vec::new();
-    while webp_chunk(&mut source, &mut webp_data)? {}
+    while webp_chunk(&mut source, &mut webp_data, &mut removed)? {}
+    update_vp8x_flags(&mut webp_data);
 
     let size = webp_data.len() as u32 + 4;
     destination.write_all(b"RIFF")?;
@@ -214,10 +466,18 @@ fn strip_webp_exif(data: &[u8]) -> Result<Vec<u8>> {
     destination.write_all(b"WEBP")?;
     destination.write_all(&webp_data)?;
 
-    Ok(destination)
+    Ok(StrippedImage {
+        data: destination,
+        removed,
+        rotated: false,
+    })
 }
 
-fn webp_chunk<R: Read + Seek, W: Write>(source: &mut R, destination: &mut W) -> Result<bool> {
+fn webp_chunk<R: Read + Seek, W: Write>(
+    source: &mut R,
+    destination: &mut W,
+    removed: &mut BTreeSet<MetadataCategory>,
+) -> Result<bool> {
     let mut code = [0u8; 4];
     if source.read_exact(&mut code).is_err() {
         return Ok(false);
@@ -227,12 +487,30 @@ fn webp_chunk<R: Read + Seek, W: Write>(source: &mut R, destination: &mut W) ->
     let total_size = if size % 2 > 0 { size + 1 } else { size };
 
     match &code {
-        b"ALPH" | b"ANIM" | b"ANMF" | b"VP8 " | b"VP8L" | b"VP8X" => {
+        b"ALPH" | b"ANIM" | b"ANMF" | b"ICCP" | b"VP8 " | b"VP8L" | b"VP8X" => {
             destination.write_all(&code)?;
             destination.write_all(&size.to_le_bytes())?;
             copy(source, destination, total_size as u64)?;
         }
+        b"EXIF" => {
+            let mut contents = vec![0u8; size as usize];
+            source.read_exact(&mut contents)?;
+            skip(source, (total_size - size) as i64)?;
+            // Some encoders keep the JPEG-style header in front of the TIFF data
+            let tiff = contents.strip_prefix(EXIF_HEADER).unwrap_or(&contents);
+            if let Some(filtered) = filter_exif(tiff, true, removed) {
+                destination.write_all(b"EXIF")?;
+                destination.write_all(&(filtered.len() as u32).to_le_bytes())?;
+                destination.write_all(&filtered)?;
+                if filtered.len() % 2 > 0 {
+                    destination.write_all(&[0])?;
+                }
+            }
+        }
         _ => {
+            if &code == b"XMP " {
+                removed.insert(MetadataCategory::Xmp);
+            }
             skip(source, total_size as i64)?;
         }
     }
@@ -240,14 +518,46 @@ fn webp_chunk<R: Read + Seek, W: Write>(source: &mut R, destination: &mut W) ->
     Ok(true)
 }
 
-fn strip_generic(data: &[u8], format: ImageFormat) -> Result<Vec<u8>> {
+/// Makes the VP8X header's EXIF, XMP and ICC flags match the chunks that were kept.
+fn update_vp8x_flags(webp_data: &mut [u8]) {
+    if webp_data.len() < 9 || &webp_data[..4] != b"VP8X" {
+        return;
+    }
+
+    let mut present = 0u8;
+    let mut position = 0;
+    while position + 8 <= webp_data.len() {
+        let size = u32::from_le_bytes([
+            webp_data[position + 4],
+            webp_data[position + 5],
+            webp_data[position + 6],
+            webp_data[position + 7],
+        ]) as usize;
+        present |= match &webp_data[position..position + 4] {
+            b"ICCP" => 0x20,
+            b"EXIF" => 0x08,
+            b"XMP " => 0x04,
+            _ => 0,
+        };
+        position += 8 + size + size % 2;
+    }
+
+    webp_data[8] = (webp_data[8] & !0x2C) | present;
+}
+
+pub(super) fn strip_generic(data: &[u8], format: ImageFormat) -> Result<StrippedImage> {
     let img = image::load_from_memory(data).context("Failed to load image")?;
+    // Re-encoding drops the orientation tag along with everything else
+    let orientation = read_orientation(data).unwrap_or(1);
+    let img = apply_orientation(img, orientation);
 
     let mut buffer = std::io::Cursor::new(Vec::new());
     img.write_to(&mut buffer, format)
         .context("Failed to re-encode image")?;
 
-    Ok(buffer.into_inner())
+    let mut result = StrippedImage::new(buffer.into_inner());
+    result.rotated = orientation != 1;
+    Ok(result)
 }
 
 #[cfg(test)]
@@ -261,7 +571,7 @@ mod tests {
             b's', b't', 0xFF, 0xD9,
         ];
 
-        let result = strip_jpeg_exif(&jpeg_with_exif).unwrap();
+        let result = strip_jpeg_exif(&jpeg_with_exif).unwrap().data;
 
         assert!(!result.contains(&0xE1), "Should not contain EXIF marker");
         assert!(
@@ -278,7 +588,7 @@ mod tests {
             0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0xFF, 0xD9,
         ];
 
-        let result = strip_jpeg_exif(&jpeg_no_exif).unwrap();
+        let result = strip_jpeg_exif(&jpeg_no_exif).unwrap().data;
 
         assert_eq!(result.len(), jpeg_no_exif.len());
         assert_eq!(result, jpeg_no_exif.to_vec());
@@ -297,7 +607,7 @@ mod tests {
         let iend_chunk = create_png_chunk(b"IEND", &[]);
         png_with_exif.extend_from_slice(&iend_chunk);
 
-        let result = strip_png_exif(&png_with_exif).unwrap();
+        let result = strip_png_exif(&png_with_exif).unwrap().data;
 
         let result_str = String::from_utf8_lossy(&result);
         assert!(
@@ -306,6 +616,181 @@ mod tests {
         );
     }
 
+    /// A 64x32 JPEG with the sample EXIF block, an ICC profile and a comment.
+    fn phone_photo() -> Vec<u8> {
+        let mut encoded = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(64, 32)
+            .write_to(&mut encoded, image::ImageOutputFormat::Jpeg(90))
+            .unwrap();
+        let encoded = encoded.into_inner();
+
+        let mut exif = EXIF_HEADER.to_vec();
+        exif.extend_from_slice(&sample_exif());
+        let mut icc = ICC_HEADER.to_vec();
+        icc.extend_from_slice(b"\x01\x01profile");
+
+        let segments = vec![
+            jpeg_segment(0xE1, &exif),
+            jpeg_segment(0xE2, &icc),
+            jpeg_segment(0xFE, b"taken at home"),
+        ];
+        insert_jpeg_segments(&encoded, &segments)
+    }
+
+    #[test]
+    fn test_jpeg_orientation_is_applied_and_allow_list_kept() {
+        let photo = phone_photo();
+        assert_eq!(read_orientation(&photo), Some(6));
+
+        let result = strip_exif(&photo).unwrap();
+        assert!(result.rotated);
+        let decoded = image::load_from_memory(&result.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (32, 64));
+
+        let exif = exif_reader::Reader::new()
+            .read_from_container(&mut std::io::Cursor::new(&result.data))
+            .unwrap();
+        assert!(exif
+            .get_field(exif_reader::Tag::Orientation, exif_reader::In::PRIMARY)
+            .is_none());
+        assert!(exif
+            .get_field(exif_reader::Tag::Copyright, exif_reader::In::PRIMARY)
+            .is_some());
+        assert!(exif
+            .get_field(exif_reader::Tag::BodySerialNumber, exif_reader::In::PRIMARY)
+            .is_none());
+
+        let text = String::from_utf8_lossy(&result.data);
+        assert!(text.contains("ICC_PROFILE"));
+        assert!(!text.contains("taken at home"));
+        assert!(!text.contains("SN123"));
+        assert_eq!(
+            result.summary().unwrap(),
+            "rotated upright; removed GPS location, camera make, model and serial numbers, \
+             timestamps, comments"
+        );
+    }
+
+    fn encode(format: image::ImageOutputFormat) -> Vec<u8> {
+        let image = image::RgbImage::from_fn(16, 8, |x, y| {
+            image::Rgb([(x * 16) as u8, (y * 32) as u8, ((x + y) * 8) as u8])
+        });
+        let mut encoded = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut encoded, format)
+            .unwrap();
+        encoded.into_inner()
+    }
+
+    fn pixels(data: &[u8]) -> image::RgbaImage {
+        image::load_from_memory(data).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn test_jpeg_round_trip_removes_xmp_iptc_and_trailers() {
+        let original = encode(image::ImageOutputFormat::Jpeg(90));
+        let mut xmp = XMP_HEADERS[0].to_vec();
+        xmp.extend_from_slice(b"<x:xmpmeta>secret location</x:xmpmeta>");
+        let segments = vec![
+            jpeg_segment(0xE1, &xmp),
+            jpeg_segment(0xED, b"Photoshop 3.0\0IPTC caption"),
+            jpeg_segment(0xEE, b"Adobe\0\x64\0\0\0\0\x01"),
+        ];
+        let mut jpeg = insert_jpeg_segments(&original, &segments);
+        jpeg.extend_from_slice(b"motion photo video");
+
+        let result = strip_exif(&jpeg).unwrap();
+        let text = String::from_utf8_lossy(&result.data);
+        assert!(!text.contains("secret location"));
+        assert!(!text.contains("IPTC caption"));
+        assert!(!text.contains("motion photo"));
+        assert!(text.contains("Adobe"));
+        assert!(!result.rotated);
+        assert_eq!(
+            result.removed.iter().copied().collect::<Vec<_>>(),
+            vec![
+                MetadataCategory::Xmp,
+                MetadataCategory::Iptc,
+                MetadataCategory::TrailingData
+            ]
+        );
+        assert_eq!(pixels(&result.data), pixels(&original));
+    }
+
+    #[test]
+    fn test_png_round_trip_filters_exif_and_text() {
+        let original = encode(image::ImageOutputFormat::Png);
+        // Ancillary chunks go right after IHDR (8-byte signature + 25-byte chunk)
+        let mut png = original[..33].to_vec();
+        png.extend_from_slice(&png_chunk(b"eXIf", &sample_exif()));
+        png.extend_from_slice(&png_chunk(
+            b"iTXt",
+            b"XML:com.adobe.xmp\0\0\0\0\0<x:xmpmeta/>",
+        ));
+        png.extend_from_slice(&png_chunk(b"zTXt", b"Comment\0\0compressed"));
+        png.extend_from_slice(&png_chunk(b"tIME", &[0x07, 0xE8, 5, 1, 10, 0, 0]));
+        png.extend_from_slice(&original[33..]);
+
+        let result = strip_exif(&png).unwrap();
+        let text = String::from_utf8_lossy(&result.data);
+        assert!(text.contains("eXIf"));
+        assert!(text.contains("Jane Doe"));
+        assert!(!text.contains("SN123"));
+        assert!(!text.contains("xmpmeta"));
+        assert!(!text.contains("tIME"));
+        assert_eq!(
+            result.removed.iter().copied().collect::<Vec<_>>(),
+            vec![
+                MetadataCategory::Location,
+                MetadataCategory::Camera,
+                MetadataCategory::Timestamps,
+                MetadataCategory::Xmp,
+                MetadataCategory::Comments
+            ]
+        );
+        assert_eq!(read_orientation(&result.data), Some(6));
+        assert_eq!(pixels(&result.data), pixels(&original));
+    }
+
+    #[test]
+    fn test_webp_round_trip_updates_vp8x_flags() {
+        let rgba = pixels(&encode(image::ImageOutputFormat::Png));
+        let lossless = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+            .encode_lossless()
+            .to_vec();
+        let bitstream = &lossless[12..];
+
+        let webp_chunk = |code: &[u8; 4], contents: &[u8]| {
+            let mut chunk = code.to_vec();
+            chunk.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            chunk.extend_from_slice(contents);
+            if !contents.len().is_multiple_of(2) {
+                chunk.push(0);
+            }
+            chunk
+        };
+        // Canvas size is stored as width - 1 and height - 1 in 24 bits each
+        let vp8x = [0x2C, 0, 0, 0, 15, 0, 0, 7, 0, 0];
+        let mut chunks = webp_chunk(b"VP8X", &vp8x);
+        chunks.extend_from_slice(&webp_chunk(b"ICCP", b"profile"));
+        chunks.extend_from_slice(bitstream);
+        chunks.extend_from_slice(&webp_chunk(b"EXIF", &sample_exif()));
+        chunks.extend_from_slice(&webp_chunk(b"XMP ", b"<x:xmpmeta/>"));
+        let mut webp = b"RIFF".to_vec();
+        webp.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+        webp.extend_from_slice(b"WEBP");
+        webp.extend_from_slice(&chunks);
+
+        let result = strip_exif(&webp).unwrap();
+        let text = String::from_utf8_lossy(&result.data);
+        assert!(!text.contains("xmpmeta"));
+        assert!(!text.contains("SN123"));
+        assert!(text.contains("ICCP"));
+        assert_eq!(result.data[20] & 0x2C, 0x28);
+        assert!(result.removed.contains(&MetadataCategory::Xmp));
+        assert_eq!(pixels(&result.data), rgba);
+    }
+
     fn create_png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
         let mut chunk = Vec::new();
 
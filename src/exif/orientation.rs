@@ -0,0 +1,74 @@
+use image::DynamicImage;
+use std::io::Cursor;
+
+/// Reads the EXIF Orientation tag (1-8), if the image has one.
+pub fn read_orientation(data: &[u8]) -> Option<u32> {
+    let exif = exif_reader::Reader::new()
+        .read_from_container(&mut Cursor::new(data))
+        .ok()?;
+    let field = exif.get_field(exif_reader::Tag::Orientation, exif_reader::In::PRIMARY)?;
+    field
+        .value
+        .get_uint(0)
+        .filter(|value| (1..=8).contains(value))
+}
+
+/// Rotates and flips the pixels so the image displays upright without the Orientation tag.
+pub fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Encodes a JPEG with an APP1 segment holding only the Orientation tag.
+#[cfg(test)]
+pub(crate) fn jpeg_with_orientation(width: u32, height: u32, orientation: u16) -> Vec<u8> {
+    let mut jpeg = Cursor::new(Vec::new());
+    DynamicImage::new_rgb8(width, height)
+        .write_to(&mut jpeg, image::ImageOutputFormat::Jpeg(90))
+        .unwrap();
+    let jpeg = jpeg.into_inner();
+
+    let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+    tiff.extend_from_slice(&[0x01, 0x12, 0x00, 0x03, 0, 0, 0, 1]);
+    tiff.extend_from_slice(&orientation.to_be_bytes());
+    tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+
+    let mut app1 = b"Exif\0\0".to_vec();
+    app1.extend_from_slice(&tiff);
+
+    let mut output = jpeg[..2].to_vec();
+    output.extend_from_slice(&[0xFF, 0xE1]);
+    output.extend_from_slice(&(app1.len() as u16 + 2).to_be_bytes());
+    output.extend_from_slice(&app1);
+    output.extend_from_slice(&jpeg[2..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_orientation() {
+        assert_eq!(read_orientation(&jpeg_with_orientation(8, 4, 6)), Some(6));
+        assert_eq!(read_orientation(&jpeg_with_orientation(8, 4, 1)), Some(1));
+        assert_eq!(read_orientation(b"not an image"), None);
+    }
+
+    #[test]
+    fn test_apply_orientation_swaps_dimensions_for_rotations() {
+        let image = DynamicImage::new_rgb8(8, 4);
+        let rotated = apply_orientation(image.clone(), 6);
+        assert_eq!((rotated.width(), rotated.height()), (4, 8));
+        let flipped = apply_orientation(image, 3);
+        assert_eq!((flipped.width(), flipped.height()), (8, 4));
+    }
+}
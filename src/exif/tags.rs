@@ -0,0 +1,188 @@
+use exif_reader::{Context, Field, In, Tag};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::io::Cursor;
+
+/// Kind of metadata removed from an image, used to tell the user what was stripped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MetadataCategory {
+    Location,
+    Camera,
+    Author,
+    Timestamps,
+    Thumbnail,
+    OtherExif,
+    Xmp,
+    Iptc,
+    Comments,
+    TrailingData,
+}
+
+impl fmt::Display for MetadataCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MetadataCategory::Location => "GPS location",
+            MetadataCategory::Camera => "camera make, model and serial numbers",
+            MetadataCategory::Author => "author and owner names",
+            MetadataCategory::Timestamps => "timestamps",
+            MetadataCategory::Thumbnail => "embedded thumbnail",
+            MetadataCategory::OtherExif => "other EXIF tags",
+            MetadataCategory::Xmp => "XMP",
+            MetadataCategory::Iptc => "IPTC",
+            MetadataCategory::Comments => "comments",
+            MetadataCategory::TrailingData => "data after the image",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Windows "Authors" field, which `exif_reader` has no name for.
+pub(super) const XP_AUTHOR: Tag = Tag(Context::Tiff, 0x9C9D);
+
+/// Tags that are safe to keep: they describe how to display the image, not where or how
+/// it was taken.
+const KEPT_TAGS: [Tag; 3] = [Tag::Orientation, Tag::Copyright, Tag::ColorSpace];
+
+/// Rewrites a TIFF-format EXIF block with only the allow-listed tags.
+///
+/// Returns the new block, or `None` when nothing is left to keep. `keep_orientation` is
+/// false when the orientation has already been applied to the pixels.
+pub(super) fn filter_exif(
+    tiff: &[u8],
+    keep_orientation: bool,
+    removed: &mut BTreeSet<MetadataCategory>,
+) -> Option<Vec<u8>> {
+    let exif = match exif_reader::Reader::new().read_raw(tiff.to_vec()) {
+        Ok(exif) => exif,
+        Err(_) => {
+            // Unreadable EXIF is dropped entirely
+            removed.insert(MetadataCategory::OtherExif);
+            return None;
+        }
+    };
+
+    let mut kept = Vec::new();
+    for field in exif.fields() {
+        if field.ifd_num == In::PRIMARY && KEPT_TAGS.contains(&field.tag) {
+            if field.tag != Tag::Orientation || keep_orientation {
+                kept.push(field);
+            }
+        } else if let Some(category) = categorize(field) {
+            removed.insert(category);
+        }
+    }
+    if kept.is_empty() {
+        return None;
+    }
+
+    let mut writer = exif_reader::experimental::Writer::new();
+    for field in &kept {
+        writer.push_field(field);
+    }
+    let mut output = Cursor::new(Vec::new());
+    writer.write(&mut output, exif.little_endian()).ok()?;
+    Some(output.into_inner())
+}
+
+pub(super) fn categorize(field: &Field) -> Option<MetadataCategory> {
+    if field.ifd_num != In::PRIMARY {
+        return Some(MetadataCategory::Thumbnail);
+    }
+    match field.tag {
+        // Offsets to the other IFDs; their contents are categorized on their own
+        Tag::ExifIFDPointer | Tag::GPSInfoIFDPointer | Tag::InteropIFDPointer => None,
+        Tag::Make
+        | Tag::Model
+        | Tag::BodySerialNumber
+        | Tag::LensSerialNumber
+        | Tag::LensMake
+        | Tag::LensModel
+        | Tag::ImageUniqueID => Some(MetadataCategory::Camera),
+        Tag::Artist | Tag::CameraOwnerName | XP_AUTHOR => Some(MetadataCategory::Author),
+        Tag::DateTime
+        | Tag::DateTimeOriginal
+        | Tag::DateTimeDigitized
+        | Tag::SubSecTime
+        | Tag::SubSecTimeOriginal
+        | Tag::SubSecTimeDigitized
+        | Tag::OffsetTime
+        | Tag::OffsetTimeOriginal
+        | Tag::OffsetTimeDigitized => Some(MetadataCategory::Timestamps),
+        Tag(Context::Gps, _) => Some(MetadataCategory::Location),
+        _ => Some(MetadataCategory::OtherExif),
+    }
+}
+
+/// Builds an EXIF block with a mix of kept and private tags.
+#[cfg(test)]
+pub(crate) fn sample_exif() -> Vec<u8> {
+    use exif_reader::Value;
+
+    let field = |tag, value| Field {
+        tag,
+        ifd_num: In::PRIMARY,
+        value,
+    };
+    let fields = [
+        field(Tag::Orientation, Value::Short(vec![6])),
+        field(Tag::Copyright, Value::Ascii(vec![b"Jane Doe".to_vec()])),
+        field(Tag::Make, Value::Ascii(vec![b"Phone".to_vec()])),
+        field(Tag::BodySerialNumber, Value::Ascii(vec![b"SN123".to_vec()])),
+        field(
+            Tag::DateTimeOriginal,
+            Value::Ascii(vec![b"2024:05:01 10:00:00".to_vec()]),
+        ),
+        field(Tag::ColorSpace, Value::Short(vec![1])),
+        field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"N".to_vec()])),
+    ];
+    let mut writer = exif_reader::experimental::Writer::new();
+    for field in &fields {
+        writer.push_field(field);
+    }
+    let mut output = Cursor::new(Vec::new());
+    writer.write(&mut output, false).unwrap();
+    output.into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_exif_keeps_allow_listed_tags() {
+        let mut removed = BTreeSet::new();
+        let filtered = filter_exif(&sample_exif(), true, &mut removed).unwrap();
+
+        let exif = exif_reader::Reader::new().read_raw(filtered).unwrap();
+        let tags: Vec<Tag> = exif
+            .fields()
+            .map(|f| f.tag)
+            .filter(|tag| *tag != Tag::ExifIFDPointer)
+            .collect();
+        assert_eq!(
+            tags,
+            vec![Tag::Orientation, Tag::Copyright, Tag::ColorSpace]
+        );
+        assert_eq!(
+            removed.into_iter().collect::<Vec<_>>(),
+            vec![
+                MetadataCategory::Location,
+                MetadataCategory::Camera,
+                MetadataCategory::Timestamps
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_exif_drops_applied_orientation() {
+        let mut removed = BTreeSet::new();
+        let filtered = filter_exif(&sample_exif(), false, &mut removed).unwrap();
+        let exif = exif_reader::Reader::new().read_raw(filtered).unwrap();
+        assert!(exif.get_field(Tag::Orientation, In::PRIMARY).is_none());
+        assert!(exif.get_field(Tag::Copyright, In::PRIMARY).is_some());
+
+        let mut removed = BTreeSet::new();
+        assert!(filter_exif(b"garbage", true, &mut removed).is_none());
+        assert!(removed.contains(&MetadataCategory::OtherExif));
+    }
+}
@@ -0,0 +1,216 @@
+use super::{strip_generic, MetadataCategory, StrippedImage, XP_AUTHOR};
+use anyhow::{anyhow, Result};
+use exif_reader::{Context, Field, In, Tag};
+use image::ImageFormat;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Cursor;
+
+/// Tags that describe how to decode and display the image data.
+const IMAGE_TAGS: [u16; 31] = [
+    0x00FE, // NewSubfileType
+    0x00FF, // SubfileType
+    0x0100, // ImageWidth
+    0x0101, // ImageLength
+    0x0102, // BitsPerSample
+    0x0103, // Compression
+    0x0106, // PhotometricInterpretation
+    0x010A, // FillOrder
+    0x0112, // Orientation
+    0x0115, // SamplesPerPixel
+    0x0116, // RowsPerStrip
+    0x011A, // XResolution
+    0x011B, // YResolution
+    0x011C, // PlanarConfiguration
+    0x0128, // ResolutionUnit
+    0x012D, // TransferFunction
+    0x013D, // Predictor
+    0x013E, // WhitePoint
+    0x013F, // PrimaryChromaticities
+    0x0140, // ColorMap
+    0x0142, // TileWidth
+    0x0143, // TileLength
+    0x014C, // InkSet
+    0x0152, // ExtraSamples
+    0x0153, // SampleFormat
+    0x015B, // JPEGTables
+    0x0211, // YCbCrCoefficients
+    0x0212, // YCbCrSubSampling
+    0x0213, // YCbCrPositioning
+    0x0214, // ReferenceBlackWhite
+    0x8773, // ICC profile
+];
+
+const STRIP_OFFSETS: Tag = Tag(Context::Tiff, 0x0111);
+const STRIP_BYTE_COUNTS: Tag = Tag(Context::Tiff, 0x0117);
+const TILE_OFFSETS: Tag = Tag(Context::Tiff, 0x0144);
+const TILE_BYTE_COUNTS: Tag = Tag(Context::Tiff, 0x0145);
+/// Locations of the image data, rebuilt by the writer.
+const DATA_TAGS: [Tag; 4] = [
+    STRIP_OFFSETS,
+    STRIP_BYTE_COUNTS,
+    TILE_OFFSETS,
+    TILE_BYTE_COUNTS,
+];
+
+/// Image data of one IFD, either as strips or as tiles.
+enum Segments<'a> {
+    Strips(Vec<&'a [u8]>),
+    Tiles(Vec<&'a [u8]>),
+}
+
+/// Rewrites a TIFF with only the image structure tags and copyright, keeping the pixel
+/// data byte for byte. Every page is kept.
+pub(super) fn strip_tiff(data: &[u8]) -> Result<StrippedImage> {
+    let exif = match exif_reader::Reader::new().read_raw(data.to_vec()) {
+        Ok(exif) => exif,
+        // Layouts the TIFF parser doesn't understand are re-encoded instead
+        Err(_) => return strip_generic(data, ImageFormat::Tiff),
+    };
+
+    let mut removed = BTreeSet::new();
+    let mut kept = Vec::new();
+    let mut ifds = BTreeMap::new();
+    for field in exif.fields() {
+        ifds.entry(field.ifd_num.index())
+            .or_insert_with(Vec::new)
+            .push(field);
+        let Tag(context, number) = field.tag;
+        if context == Context::Tiff && (IMAGE_TAGS.contains(&number) || field.tag == Tag::Copyright)
+        {
+            kept.push(field);
+        } else if !DATA_TAGS.contains(&field.tag) {
+            removed.insert(categorize(field));
+        }
+    }
+
+    let mut segments = Vec::new();
+    for (&index, fields) in &ifds {
+        match image_segments(data, fields) {
+            Some(found) => segments.push((In(index), found)),
+            None => return strip_generic(data, ImageFormat::Tiff),
+        }
+    }
+
+    let mut writer = exif_reader::experimental::Writer::new();
+    for field in &kept {
+        writer.push_field(field);
+    }
+    for (ifd, found) in &segments {
+        match found {
+            Segments::Strips(strips) => writer.set_strips(strips, *ifd),
+            Segments::Tiles(tiles) => writer.set_tiles(tiles, *ifd),
+        }
+    }
+
+    let mut output = Cursor::new(Vec::new());
+    writer
+        .write(&mut output, exif.little_endian())
+        .map_err(|e| anyhow!("Failed to write TIFF: {}", e))?;
+
+    Ok(StrippedImage {
+        data: output.into_inner(),
+        removed,
+        rotated: false,
+    })
+}
+
+fn categorize(field: &Field) -> MetadataCategory {
+    match field.tag {
+        Tag(Context::Tiff, 0x02BC) => MetadataCategory::Xmp,
+        // IPTC, and Photoshop image resources which usually carry IPTC
+        Tag(Context::Tiff, 0x83BB | 0x8649) => MetadataCategory::Iptc,
+        Tag::Make | Tag::Model => MetadataCategory::Camera,
+        Tag::Artist | XP_AUTHOR => MetadataCategory::Author,
+        Tag::DateTime => MetadataCategory::Timestamps,
+        Tag(Context::Gps, _) => MetadataCategory::Location,
+        _ => MetadataCategory::OtherExif,
+    }
+}
+
+/// Finds the strips or tiles of an IFD in the file.
+fn image_segments<'a>(data: &'a [u8], fields: &[&Field]) -> Option<Segments<'a>> {
+    let find = |tag: Tag| fields.iter().find(|f| f.tag == tag).map(|f| &f.value);
+    let slices = |offsets: &exif_reader::Value, counts: &exif_reader::Value| {
+        offsets
+            .iter_uint()?
+            .zip(counts.iter_uint()?)
+            .map(|(offset, count)| data.get(offset as usize..)?.get(..count as usize))
+            .collect::<Option<Vec<_>>>()
+    };
+
+    if let (Some(offsets), Some(counts)) = (find(STRIP_OFFSETS), find(STRIP_BYTE_COUNTS)) {
+        slices(offsets, counts).map(Segments::Strips)
+    } else if let (Some(offsets), Some(counts)) = (find(TILE_OFFSETS), find(TILE_BYTE_COUNTS)) {
+        slices(offsets, counts).map(Segments::Tiles)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif_reader::Value;
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+
+    fn ascii(text: &str) -> Value {
+        Value::Ascii(vec![text.as_bytes().to_vec()])
+    }
+
+    #[test]
+    fn test_tiff_round_trip_keeps_pixels() {
+        let pixels: Vec<u8> = (0..12).map(|n| n * 20).collect();
+        let fields = vec![
+            field(Tag::ImageWidth, Value::Long(vec![2])),
+            field(Tag::ImageLength, Value::Long(vec![2])),
+            field(Tag::BitsPerSample, Value::Short(vec![8, 8, 8])),
+            field(Tag::Compression, Value::Short(vec![1])),
+            field(Tag::PhotometricInterpretation, Value::Short(vec![2])),
+            field(Tag::SamplesPerPixel, Value::Short(vec![3])),
+            field(Tag::RowsPerStrip, Value::Long(vec![2])),
+            field(Tag::PlanarConfiguration, Value::Short(vec![1])),
+            field(Tag::Make, ascii("Phone")),
+            field(Tag::DateTime, ascii("2024:05:01 10:00:00")),
+            field(Tag::Software, ascii("Editor 1.0")),
+            field(Tag::Copyright, ascii("Jane Doe")),
+            field(Tag::GPSLatitudeRef, ascii("N")),
+        ];
+        let strips = [pixels.as_slice()];
+        let mut writer = exif_reader::experimental::Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        writer.set_strips(&strips, In::PRIMARY);
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, true).unwrap();
+        let tiff = tiff.into_inner();
+
+        let result = strip_tiff(&tiff).unwrap();
+        let text = String::from_utf8_lossy(&result.data);
+        assert!(!text.contains("Phone"));
+        assert!(!text.contains("Editor"));
+        assert!(!text.contains("2024:05:01"));
+        assert!(text.contains("Jane Doe"));
+        assert_eq!(
+            result.removed.into_iter().collect::<Vec<_>>(),
+            vec![
+                MetadataCategory::Location,
+                MetadataCategory::Camera,
+                MetadataCategory::Timestamps,
+                MetadataCategory::OtherExif
+            ]
+        );
+
+        let before = image::load_from_memory(&tiff).unwrap().to_rgb8();
+        let after = image::load_from_memory(&result.data).unwrap().to_rgb8();
+        assert_eq!(before, after);
+        assert_eq!(after.into_raw(), pixels);
+    }
+}
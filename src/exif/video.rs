@@ -0,0 +1,268 @@
+use super::isobmff::{read_boxes, read_uint, IsoBox};
+use super::{heif, MetadataCategory, StrippedImage};
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+/// User data atoms with private information, in `udta` and iTunes-style `ilst` lists.
+const PRIVATE_ATOMS: [(&[u8; 4], MetadataCategory); 6] = [
+    (b"\xA9xyz", MetadataCategory::Location),
+    (b"loci", MetadataCategory::Location),
+    (b"\xA9mak", MetadataCategory::Camera),
+    (b"\xA9mod", MetadataCategory::Camera),
+    (b"\xA9swr", MetadataCategory::Camera),
+    (b"\xA9day", MetadataCategory::Timestamps),
+];
+
+/// Whether the data is an MP4 or QuickTime file (including M4A audio).
+pub fn is_video(data: &[u8]) -> bool {
+    data.len() >= 12 && &data[4..8] == b"ftyp" && !heif::is_heif(data)
+}
+
+/// Removes location, device and creation time metadata from an MP4 or MOV file.
+///
+/// Atoms are blanked in place and renamed to `free`, so the file keeps its size, no chunk
+/// offsets change and the audio and video data are untouched.
+pub(super) fn strip_video(data: &[u8]) -> Result<StrippedImage> {
+    let mut scrubber = Scrubber {
+        data,
+        output: data.to_vec(),
+        removed: BTreeSet::new(),
+    };
+    for atom in read_boxes(data, 0..data.len())? {
+        if &atom.kind == b"moov" {
+            scrubber.scrub_container(&atom)?;
+        }
+    }
+
+    Ok(StrippedImage {
+        data: scrubber.output,
+        removed: scrubber.removed,
+        rotated: false,
+    })
+}
+
+struct Scrubber<'a> {
+    data: &'a [u8],
+    output: Vec<u8>,
+    removed: BTreeSet<MetadataCategory>,
+}
+
+impl Scrubber<'_> {
+    /// Walks `moov`, `trak` and `mdia` atoms.
+    fn scrub_container(&mut self, container: &IsoBox) -> Result<()> {
+        for atom in read_boxes(self.data, container.contents.clone())? {
+            match &atom.kind {
+                b"trak" | b"mdia" => self.scrub_container(&atom)?,
+                b"mvhd" | b"tkhd" | b"mdhd" => self.clear_times(&atom),
+                b"udta" => self.scrub_user_data(&atom)?,
+                b"meta" => self.scrub_metadata(&atom)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Zeroes the creation and modification times of a movie, track or media header.
+    fn clear_times(&mut self, header: &IsoBox) {
+        let start = header.contents.start;
+        // Version 1 headers use 64-bit times
+        let length = if self.data.get(start) == Some(&1) {
+            16
+        } else {
+            8
+        };
+        if let Some(times) = self.output.get_mut(start + 4..start + 4 + length) {
+            if times.iter().any(|&b| b != 0) {
+                times.fill(0);
+                self.removed.insert(MetadataCategory::Timestamps);
+            }
+        }
+    }
+
+    fn scrub_user_data(&mut self, udta: &IsoBox) -> Result<()> {
+        for atom in read_boxes(self.data, udta.contents.clone())? {
+            if &atom.kind == b"meta" {
+                self.scrub_metadata(&atom)?;
+            } else if let Some(category) = private_atom(&atom.kind) {
+                self.blank(&atom, category);
+            }
+        }
+        Ok(())
+    }
+
+    /// Scrubs a metadata atom: QuickTime `keys` with an `ilst`, or an iTunes-style `ilst`.
+    fn scrub_metadata(&mut self, meta: &IsoBox) -> Result<()> {
+        // QuickTime metadata atoms start with `hdlr`; MP4 ones have a version and flags first
+        let start = meta.contents.start;
+        let children_start = if self.data.get(start + 4..start + 8) == Some(b"hdlr") {
+            start
+        } else {
+            start + 4
+        };
+        let children = read_boxes(self.data, children_start..meta.contents.end)?;
+
+        let keys = match children.iter().find(|atom| &atom.kind == b"keys") {
+            Some(keys) => read_keys(self.data, keys)?,
+            None => Vec::new(),
+        };
+        let Some(ilst) = children.iter().find(|atom| &atom.kind == b"ilst") else {
+            return Ok(());
+        };
+
+        for item in read_boxes(self.data, ilst.contents.clone())? {
+            // QuickTime items are named by their 1-based index into the keys
+            let key = (u32::from_be_bytes(item.kind) as usize)
+                .checked_sub(1)
+                .and_then(|index| keys.get(index));
+            let category = match key {
+                Some(key) => key_category(key),
+                None => private_atom(&item.kind),
+            };
+            if let Some(category) = category {
+                self.blank(&item, category);
+            }
+        }
+        Ok(())
+    }
+
+    /// Turns an atom into a `free` atom of the same size.
+    fn blank(&mut self, atom: &IsoBox, category: MetadataCategory) {
+        self.output[atom.start + 4..atom.start + 8].copy_from_slice(b"free");
+        self.output[atom.contents.clone()].fill(0);
+        self.removed.insert(category);
+    }
+}
+
+fn private_atom(kind: &[u8; 4]) -> Option<MetadataCategory> {
+    PRIVATE_ATOMS
+        .iter()
+        .find(|(atom, _)| *atom == kind)
+        .map(|(_, category)| *category)
+}
+
+/// Reads the names from a QuickTime `keys` atom, e.g. `com.apple.quicktime.make`.
+fn read_keys(data: &[u8], keys: &IsoBox) -> Result<Vec<String>> {
+    let count = read_uint(data, keys.contents.start + 4, 4)?;
+    let mut names = Vec::new();
+    let mut position = keys.contents.start + 8;
+    for _ in 0..count {
+        let size = read_uint(data, position, 4)? as usize;
+        let name = data
+            .get(position + 8..position + size.max(8))
+            .unwrap_or_default();
+        names.push(String::from_utf8_lossy(name).to_lowercase());
+        position += size.max(8);
+    }
+    Ok(names)
+}
+
+fn key_category(key: &str) -> Option<MetadataCategory> {
+    if key.contains("location") {
+        Some(MetadataCategory::Location)
+    } else if key.ends_with(".make") || key.ends_with(".model") || key.ends_with(".software") {
+        Some(MetadataCategory::Camera)
+    } else if key.ends_with("creationdate") {
+        Some(MetadataCategory::Timestamps)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom(kind: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+        let mut output = ((contents.len() + 8) as u32).to_be_bytes().to_vec();
+        output.extend_from_slice(kind);
+        output.extend_from_slice(contents);
+        output
+    }
+
+    fn header_with_times() -> Vec<u8> {
+        let mut contents = vec![0, 0, 0, 0];
+        contents.extend_from_slice(&0xDEAD_BEEFu32.to_be_bytes());
+        contents.extend_from_slice(&0xDEAD_BEEFu32.to_be_bytes());
+        contents.extend_from_slice(&[0, 0, 0x02, 0x58, 0, 0, 0x0B, 0xB8]);
+        contents
+    }
+
+    fn phone_video() -> Vec<u8> {
+        let key = |name: &str| atom(b"mdta", name.as_bytes());
+        let mut keys = vec![0, 0, 0, 0, 0, 0, 0, 3];
+        keys.extend_from_slice(&key("com.apple.quicktime.location.ISO6709"));
+        keys.extend_from_slice(&key("com.apple.quicktime.make"));
+        keys.extend_from_slice(&key("com.apple.quicktime.title"));
+
+        let item = |index: u32, value: &str| {
+            let mut data = vec![0, 0, 0, 1, 0, 0, 0, 0];
+            data.extend_from_slice(value.as_bytes());
+            atom(&index.to_be_bytes(), &atom(b"data", &data))
+        };
+        let mut ilst = item(1, "+51.5007-000.1246/");
+        ilst.extend_from_slice(&item(2, "Apple"));
+        ilst.extend_from_slice(&item(3, "Holiday"));
+
+        let mut meta = atom(b"hdlr", &[0; 24]);
+        meta.extend_from_slice(&atom(b"keys", &keys));
+        meta.extend_from_slice(&atom(b"ilst", &ilst));
+
+        let mut udta = atom(b"\xA9xyz", b"\0\x12\x15\xC7+51.5007-000.1246/");
+        udta.extend_from_slice(&atom(b"\xA9mod", b"\0\x09\x15\xC7iPhone 15"));
+        udta.extend_from_slice(&atom(b"\xA9nam", b"\0\x07\x15\xC7Holiday"));
+
+        let mdia = atom(b"mdhd", &header_with_times());
+        let mut trak = atom(b"tkhd", &header_with_times());
+        trak.extend_from_slice(&atom(b"mdia", &mdia));
+
+        let mut moov = atom(b"mvhd", &header_with_times());
+        moov.extend_from_slice(&atom(b"trak", &trak));
+        moov.extend_from_slice(&atom(b"udta", &udta));
+        moov.extend_from_slice(&atom(b"meta", &meta));
+
+        let mut file = atom(b"ftyp", b"qt  \0\0\0\0qt  ");
+        file.extend_from_slice(&atom(b"moov", &moov));
+        file.extend_from_slice(&atom(b"mdat", b"video frames"));
+        file
+    }
+
+    #[test]
+    fn test_strip_video_blanks_private_atoms_in_place() {
+        let video = phone_video();
+        assert!(is_video(&video));
+
+        let result = strip_video(&video).unwrap();
+        assert_eq!(result.data.len(), video.len());
+        assert!(result.data.ends_with(&atom(b"mdat", b"video frames")));
+
+        let text = String::from_utf8_lossy(&result.data);
+        assert!(!text.contains("+51.5007"));
+        assert!(!text.contains("Apple"));
+        assert!(!text.contains("iPhone"));
+        assert!(text.contains("Holiday"));
+        assert!(!result
+            .data
+            .windows(4)
+            .any(|w| w == 0xDEAD_BEEFu32.to_be_bytes()));
+        assert_eq!(
+            result.removed.into_iter().collect::<Vec<_>>(),
+            vec![
+                MetadataCategory::Location,
+                MetadataCategory::Camera,
+                MetadataCategory::Timestamps
+            ]
+        );
+
+        // The blanked atoms are still valid, so the file can be scrubbed again
+        let again = strip_video(&result.data).unwrap();
+        assert_eq!(again.data, result.data);
+        assert!(again.removed.is_empty());
+    }
+
+    #[test]
+    fn test_is_video() {
+        assert!(is_video(b"\0\0\0\x18ftypisom\0\0\x02\0"));
+        assert!(!is_video(b"\0\0\0\x18ftypheic\0\0\0\0"));
+        assert!(!is_video(b"\x89PNG\r\n\x1a\n...."));
+    }
+}
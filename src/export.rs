@@ -0,0 +1,503 @@
+use crate::config::{
+    BunnyProviderConfig, Config, FTPSFTPProviderConfig, ProviderConfig, S3ProviderConfig, Secret,
+};
+use crate::providers::{
+    PasteRsProvider, TransferProtocol, UguuProvider, X0AtProvider, ZeroX0STProvider,
+};
+use anyhow::{bail, Context, Result};
+
+/// Uploader definitions `pst export` can generate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// ShareX custom uploader (.sxcu)
+    Sharex,
+    /// Shell script that takes a screenshot with Flameshot and uploads it
+    Flameshot,
+    /// curl command for uploading $FILE
+    Curl,
+}
+
+/// A public HTTP service and the requests it accepts, from its provider type.
+struct HttpEndpoint {
+    names: &'static [&'static str],
+    url: &'static str,
+    /// Query asking for the file's URL as plain text
+    text_query: &'static str,
+    /// Multipart field holding the file, or `None` when the file is the request body
+    field: Option<&'static str>,
+    /// Query and response expression for ShareX
+    sharex_query: &'static str,
+    sharex_response: &'static str,
+    text_only: bool,
+}
+
+const HTTP_ENDPOINTS: [HttpEndpoint; 4] = [
+    HttpEndpoint {
+        names: ZeroX0STProvider::NAMES,
+        url: ZeroX0STProvider::ENDPOINT,
+        text_query: "",
+        field: Some(ZeroX0STProvider::FILE_FIELD),
+        sharex_query: "",
+        sharex_response: "{response}",
+        text_only: false,
+    },
+    HttpEndpoint {
+        names: X0AtProvider::NAMES,
+        url: X0AtProvider::ENDPOINT,
+        text_query: "",
+        field: Some(X0AtProvider::FILE_FIELD),
+        sharex_query: "",
+        sharex_response: "{response}",
+        text_only: false,
+    },
+    HttpEndpoint {
+        names: PasteRsProvider::NAMES,
+        url: PasteRsProvider::ENDPOINT,
+        text_query: "",
+        field: None,
+        sharex_query: "",
+        sharex_response: "{response}",
+        text_only: true,
+    },
+    HttpEndpoint {
+        names: UguuProvider::NAMES,
+        url: UguuProvider::ENDPOINT,
+        text_query: "?output=text",
+        field: Some(UguuProvider::FILE_FIELD),
+        sharex_query: "?output=json",
+        sharex_response: "{json:files[0].url}",
+        text_only: false,
+    },
+];
+
+/// Generates an uploader definition for a configured provider.
+///
+/// Secrets are only written out with `include_secrets`. Without it, the shell formats
+/// refer to `{ env }`, `{ command }` and `{ file }` secrets instead of embedding them.
+pub fn export(
+    config: &Config,
+    provider: &str,
+    format: ExportFormat,
+    include_secrets: bool,
+) -> Result<String> {
+    let Some(provider_config) = config.get_provider_config(provider) else {
+        let mut names: Vec<&String> = config.providers.keys().collect();
+        names.sort();
+        bail!(
+            "Unknown provider '{}'. Configured providers: {}",
+            provider,
+            names
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    };
+
+    let secrets = Secrets {
+        provider,
+        include: include_secrets,
+        shell: format != ExportFormat::Sharex,
+    };
+    match (format, provider_config) {
+        (ExportFormat::Sharex, ProviderConfig::Http(_)) => {
+            Ok(sharex_http(provider, http_endpoint(provider)?))
+        }
+        (ExportFormat::Sharex, ProviderConfig::Bunny(bunny)) => {
+            sharex_bunny(provider, bunny, &secrets)
+        }
+        (ExportFormat::Sharex, ProviderConfig::S3(_)) => bail!(
+            "ShareX custom uploaders can't sign S3 requests. Use ShareX's built-in Amazon S3 destination, or run `pst serve` and point ShareX at it"
+        ),
+        (ExportFormat::Sharex, ProviderConfig::FtpSftp(_)) => bail!(
+            "ShareX custom uploaders only speak HTTP. Use ShareX's built-in FTP/SFTP destination, or run `pst serve` and point ShareX at it"
+        ),
+        (ExportFormat::Curl, provider_config) => {
+            let upload = shell_upload(provider, provider_config, &secrets)?;
+            Ok(format!(
+                "# Upload $FILE to {}, printing its URL\n{}",
+                provider,
+                upload.join("\n")
+            ))
+        }
+        (ExportFormat::Flameshot, provider_config) => {
+            let upload = shell_upload(provider, provider_config, &secrets)?;
+            Ok(flameshot_script(provider, &upload))
+        }
+    }
+}
+
+fn http_endpoint(provider: &str) -> Result<&'static HttpEndpoint> {
+    let name = provider.to_lowercase();
+    HTTP_ENDPOINTS
+        .iter()
+        .find(|endpoint| endpoint.names.contains(&name.as_str()))
+        .with_context(|| format!("pst doesn't know the upload endpoint of '{}'", provider))
+}
+
+/// Decides how each secret ends up in the output.
+struct Secrets<'a> {
+    provider: &'a str,
+    include: bool,
+    /// Whether the output is run by a shell, which can look secrets up itself
+    shell: bool,
+}
+
+impl Secrets<'_> {
+    /// The secret for use inside a double-quoted shell word, or as a plain value.
+    fn value(&self, secret: &Secret, what: &str) -> Result<String> {
+        if self.include {
+            let value = secret
+                .resolve()
+                .with_context(|| format!("Failed to read the {} of {}", what, self.provider))?;
+            return Ok(match self.shell {
+                true => escape_double_quoted(&value),
+                false => value,
+            });
+        }
+
+        match secret {
+            Secret::Env { env } if self.shell => {
+                // The name ends up inside `${...}`, where anything else would be expanded
+                if env.is_empty() || !env.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    bail!(
+                        "Invalid environment variable name '{}' for the {} of {}",
+                        env,
+                        what,
+                        self.provider
+                    );
+                }
+                Ok(format!("${{{}}}", env))
+            }
+            Secret::Command { command } if self.shell => Ok(format!("$({})", command)),
+            Secret::File { file } if self.shell => Ok(format!("$(cat \"{}\")", home_path(file))),
+            _ => {
+                let hint = match self.shell {
+                    true => ", or store it as { env = \"...\" } to refer to it instead",
+                    false => "",
+                };
+                bail!(
+                    "Exporting {} would write out its {}. Pass --include-secrets to include it{}",
+                    self.provider,
+                    what,
+                    hint
+                )
+            }
+        }
+    }
+}
+
+fn sharex_http(provider: &str, endpoint: &HttpEndpoint) -> String {
+    let mut uploader = serde_json::json!({
+        "Version": "15.0.0",
+        "Name": format!("pst: {}", provider),
+        "DestinationType": if endpoint.text_only {
+            "TextUploader"
+        } else {
+            "ImageUploader, TextUploader, FileUploader"
+        },
+        "RequestMethod": "POST",
+        "RequestURL": format!("{}{}", endpoint.url, endpoint.sharex_query),
+        "URL": endpoint.sharex_response,
+    });
+    match endpoint.field {
+        Some(field) => {
+            uploader["Body"] = "MultipartFormData".into();
+            uploader["FileFormName"] = field.into();
+        }
+        None => uploader["Body"] = "Binary".into(),
+    }
+    pretty(&uploader)
+}
+
+fn sharex_bunny(provider: &str, bunny: &BunnyProviderConfig, secrets: &Secrets) -> Result<String> {
+    let uploader = serde_json::json!({
+        "Version": "15.0.0",
+        "Name": format!("pst: {}", provider),
+        "DestinationType": "ImageUploader, TextUploader, FileUploader",
+        "RequestMethod": "PUT",
+        "RequestURL": format!("{}/{{filename}}", bunny_storage_url(bunny)),
+        "Headers": {
+            "AccessKey": secrets.value(&bunny.access_key, "access key")?,
+        },
+        "Body": "Binary",
+        "URL": format!("{}/{{filename}}", bunny.public_url.trim_end_matches('/')),
+    });
+    Ok(pretty(&uploader))
+}
+
+fn pretty(value: &serde_json::Value) -> String {
+    serde_json::to_string_pretty(value).expect("JSON values always serialize")
+}
+
+/// Shell lines that upload `$FILE` and print its URL.
+fn shell_upload(
+    provider: &str,
+    provider_config: &ProviderConfig,
+    secrets: &Secrets,
+) -> Result<Vec<String>> {
+    let (command, public_url) = match provider_config {
+        ProviderConfig::Http(_) => {
+            let endpoint = http_endpoint(provider)?;
+            let body = match endpoint.field {
+                Some(field) => format!("-F \"{}=@$FILE\"", field),
+                None => "--data-binary \"@$FILE\"".to_string(),
+            };
+            return Ok(vec![format!(
+                "curl -fsS {} '{}{}'",
+                body, endpoint.url, endpoint.text_query
+            )]);
+        }
+        ProviderConfig::Bunny(bunny) => (
+            format!(
+                "curl -fsS -o /dev/null -T \"$FILE\" -H \"AccessKey: {}\" \"{}/$NAME\"",
+                secrets.value(&bunny.access_key, "access key")?,
+                escape_double_quoted(&bunny_storage_url(bunny))
+            ),
+            bunny.public_url.as_str(),
+        ),
+        ProviderConfig::S3(s3) => (s3_command(s3, secrets)?, s3.public_url.as_str()),
+        ProviderConfig::FtpSftp(ftp) => (ftp_command(ftp, secrets)?, ftp.public_url.as_str()),
+    };
+
+    // Storage providers get a random name with the file's extension, like uploads from pst
+    Ok(vec![
+        "BASE=\"${FILE##*/}\"".to_string(),
+        "case \"$BASE\" in *.*) EXT=\".${BASE##*.}\" ;; *) EXT=\"\" ;; esac".to_string(),
+        "NAME=\"$(od -An -N6 -tx1 /dev/urandom | tr -d ' \\n')$EXT\"".to_string(),
+        format!(
+            "{} && echo \"{}/$NAME\"",
+            command,
+            escape_double_quoted(public_url.trim_end_matches('/'))
+        ),
+    ])
+}
+
+fn bunny_storage_url(bunny: &BunnyProviderConfig) -> String {
+    let host = match bunny.region.as_deref() {
+        Some(region) if !region.is_empty() => format!("{}.storage.bunnycdn.com", region),
+        _ => "storage.bunnycdn.com".to_string(),
+    };
+    format!("https://{}/{}", host, bunny.storage_zone)
+}
+
+/// Uses curl's built-in request signing, available since curl 7.75.
+fn s3_command(s3: &S3ProviderConfig, secrets: &Secrets) -> Result<String> {
+    let url = match &s3.endpoint {
+        Some(endpoint) => format!("{}/{}", endpoint.trim_end_matches('/'), s3.bucket),
+        None => format!("https://{}.s3.{}.amazonaws.com", s3.bucket, s3.region),
+    };
+    Ok(format!(
+        "curl -fsS -o /dev/null -T \"$FILE\" --aws-sigv4 \"aws:amz:{}:s3\" --user \"{}:{}\" -H \"x-amz-content-sha256: UNSIGNED-PAYLOAD\" \"{}/$NAME\"",
+        escape_double_quoted(&s3.region),
+        escape_double_quoted(&s3.access_key_id),
+        secrets.value(&s3.secret_access_key, "secret access key")?,
+        escape_double_quoted(&url)
+    ))
+}
+
+fn ftp_command(ftp: &FTPSFTPProviderConfig, secrets: &Secrets) -> Result<String> {
+    let scheme = match ftp.protocol {
+        TransferProtocol::Sftp => "sftp",
+        TransferProtocol::Ftp | TransferProtocol::Ftps => "ftp",
+    };
+    let directory = ftp.directory.trim_matches('/');
+    let url = match directory {
+        "" => format!("{}://{}:{}", scheme, ftp.host, ftp.port),
+        directory => format!("{}://{}:{}/{}", scheme, ftp.host, ftp.port, directory),
+    };
+
+    let mut options = vec!["-fsS".to_string(), "-T \"$FILE\"".to_string()];
+    if ftp.protocol == TransferProtocol::Ftps {
+        options.push("--ssl-reqd".to_string());
+    }
+    if !ftp.directory_mode.eq_ignore_ascii_case("existing_only") {
+        options.push("--ftp-create-dirs".to_string());
+    }
+    let password = match &ftp.password {
+        Some(password) => secrets.value(password, "password")?,
+        None => String::new(),
+    };
+    options.push(format!(
+        "--user \"{}:{}\"",
+        escape_double_quoted(&ftp.username),
+        password
+    ));
+    if let Some(key) = &ftp.ssh_private_key {
+        options.push(format!("--key \"{}\"", home_path(key)));
+        if let Some(passphrase) = &ftp.ssh_key_passphrase {
+            options.push(format!(
+                "--pass \"{}\"",
+                secrets.value(passphrase, "key passphrase")?
+            ));
+        }
+    }
+
+    Ok(format!(
+        "curl {} \"{}/$NAME\"",
+        options.join(" "),
+        escape_double_quoted(&url)
+    ))
+}
+
+fn flameshot_script(provider: &str, upload: &[String]) -> String {
+    let (setup, last) = upload.split_at(upload.len() - 1);
+    let mut script = format!(
+        "#!/bin/sh
+# Takes a screenshot with Flameshot, uploads it to {provider} and copies the URL.
+# Generated by `pst export flameshot {provider}`, bind it to a key to use it.
+set -eu
+
+FILE=\"${{TMPDIR:-/tmp}}/pst-screenshot-$$.png\"
+trap 'rm -f \"$FILE\"' EXIT
+flameshot gui --raw > \"$FILE\"
+# Nothing is written when the capture is cancelled
+[ -s \"$FILE\" ] || exit 0
+
+"
+    );
+    for line in setup {
+        script.push_str(line);
+        script.push('\n');
+    }
+    script.push_str(&format!(
+        "URL=$({})
+printf '%s' \"$URL\" | {{ wl-copy 2>/dev/null || xclip -selection clipboard 2>/dev/null || true; }}
+notify-send \"Screenshot uploaded\" \"$URL\" 2>/dev/null || true
+echo \"$URL\"",
+        last[0]
+    ));
+    script
+}
+
+/// A path for a double-quoted shell word, where `~` would not be expanded.
+fn home_path(path: &str) -> String {
+    match path.strip_prefix("~/") {
+        Some(rest) => format!("$HOME/{}", escape_double_quoted(rest)),
+        None => escape_double_quoted(path),
+    }
+}
+
+fn escape_double_quoted(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        let mut config = Config::default_with_ftp();
+        if let Some(ProviderConfig::Bunny(bunny)) = config.providers.get_mut("bunny") {
+            bunny.storage_zone = "zone".to_string();
+            bunny.public_url = "https://cdn.example.com/".to_string();
+            bunny.access_key = Secret::from("inline-key");
+        }
+        config
+    }
+
+    #[test]
+    fn test_http_providers() {
+        let config = config();
+        let sxcu: serde_json::Value =
+            serde_json::from_str(&export(&config, "uguu", ExportFormat::Sharex, false).unwrap())
+                .unwrap();
+        assert_eq!(sxcu["FileFormName"], "files[]");
+        assert_eq!(sxcu["URL"], "{json:files[0].url}");
+        assert_eq!(sxcu["RequestURL"], "https://uguu.se/upload?output=json");
+        let curl = export(&config, "uguu", ExportFormat::Curl, false).unwrap();
+        assert!(
+            curl.ends_with("curl -fsS -F \"files[]=@$FILE\" 'https://uguu.se/upload?output=text'"),
+            "{}",
+            curl
+        );
+
+        let curl = export(&config, "paste_rs", ExportFormat::Curl, false).unwrap();
+        assert!(
+            curl.ends_with("curl -fsS --data-binary \"@$FILE\" 'https://paste.rs'"),
+            "{}",
+            curl
+        );
+        assert!(export(&config, "missing", ExportFormat::Curl, false).is_err());
+    }
+
+    #[test]
+    fn test_secrets_need_opting_in() {
+        let mut config = config();
+        let error = export(&config, "bunny", ExportFormat::Sharex, false).unwrap_err();
+        assert!(error.to_string().contains("--include-secrets"), "{}", error);
+
+        let sxcu: serde_json::Value =
+            serde_json::from_str(&export(&config, "bunny", ExportFormat::Sharex, true).unwrap())
+                .unwrap();
+        assert_eq!(sxcu["Headers"]["AccessKey"], "inline-key");
+        assert_eq!(
+            sxcu["RequestURL"],
+            "https://storage.bunnycdn.com/zone/{filename}"
+        );
+        assert_eq!(sxcu["URL"], "https://cdn.example.com/{filename}");
+
+        // Shell output can look an env secret up instead of embedding it
+        if let Some(ProviderConfig::Bunny(bunny)) = config.providers.get_mut("bunny") {
+            bunny.access_key = Secret::Env {
+                env: "PST_BUNNY_ACCESS_KEY".to_string(),
+            };
+        }
+        let script = export(&config, "bunny", ExportFormat::Flameshot, false).unwrap();
+        assert!(script.contains("-H \"AccessKey: ${PST_BUNNY_ACCESS_KEY}\""));
+        assert!(script.contains("echo \"https://cdn.example.com/$NAME\""));
+        assert!(export(&config, "bunny", ExportFormat::Sharex, false).is_err());
+    }
+
+    #[test]
+    fn test_ftp_and_quoting() {
+        let mut config = config();
+        if let Some(ProviderConfig::FtpSftp(ftp)) = config.providers.get_mut("ftp_sftp") {
+            ftp.protocol = TransferProtocol::Ftps;
+            ftp.password = Some(Secret::from("pa\"$s"));
+        }
+        let curl = export(&config, "ftp_sftp", ExportFormat::Curl, true).unwrap();
+        assert!(curl.contains("--ssl-reqd"), "{}", curl);
+        assert!(curl.contains(":pa\\\"\\$s\""), "{}", curl);
+        assert!(export(&config, "ftp_sftp", ExportFormat::Sharex, true).is_err());
+
+        assert_eq!(home_path("~/.ssh/id"), "$HOME/.ssh/id");
+        assert_eq!(home_path("/a/`b`"), "/a/\\`b\\`");
+    }
+
+    #[test]
+    fn test_paths_and_names_are_escaped() {
+        let mut config = config();
+        if let Some(ProviderConfig::FtpSftp(ftp)) = config.providers.get_mut("ftp_sftp") {
+            ftp.password = Some(Secret::File {
+                file: "~/keys/\"$(id)`id`.txt".to_string(),
+            });
+            ftp.ssh_private_key = Some("/keys/\"; rm -rf ~; \"$HOME`x`".to_string());
+        }
+        let curl = export(&config, "ftp_sftp", ExportFormat::Curl, false).unwrap();
+        assert!(
+            curl.contains(r#"$(cat "$HOME/keys/\"\$(id)\`id\`.txt")"#),
+            "{}",
+            curl
+        );
+        assert!(
+            curl.contains(r#"--key "/keys/\"; rm -rf ~; \"\$HOME\`x\`""#),
+            "{}",
+            curl
+        );
+
+        if let Some(ProviderConfig::FtpSftp(ftp)) = config.providers.get_mut("ftp_sftp") {
+            ftp.password = Some(Secret::Env {
+                env: "X:-$(id)".to_string(),
+            });
+        }
+        assert!(export(&config, "ftp_sftp", ExportFormat::Curl, false).is_err());
+    }
+}
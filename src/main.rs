@@ -1,17 +1,389 @@
 use crate::clipboard::ClipboardContent;
+use crate::config::{InputSource, RoutingContext};
+use crate::detect::{ContentKind, TextEncoding};
+use crate::render::PageTheme;
+use crate::transform::ConvertFormat;
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::io::{stdin, AsyncReadExt};
 
 mod clipboard;
 mod config;
+mod config_command;
+mod detect;
+mod events;
 mod exif;
+mod export;
+mod inspect;
 mod models;
 mod orchestrator;
+mod prepare;
 mod providers;
-mod redirect_generator;
+mod qr;
+mod redaction;
+mod render;
+mod sanitize;
+mod scanner;
+mod serve;
+mod shortener;
+mod template;
+mod transcript;
+mod transform;
+
+/// Uploads a rendered HTML page next to a paste and returns a response pointing to it.
+///
+/// Falls back to the raw upload's response when the page can't be rendered or uploaded.
+async fn upload_rendered_page(
+    orchestrator: &crate::orchestrator::UploadOrchestrator,
+    request: &crate::models::UploadRequest,
+    response: crate::models::UploadResponse,
+    lang: Option<&str>,
+    theme: PageTheme,
+) -> crate::models::UploadResponse {
+    let (Some(raw_url), Some(provider_id)) = (response.url.clone(), response.provider_id.clone())
+    else {
+        return response;
+    };
+    let Ok(source) = std::str::from_utf8(&request.content) else {
+        return response;
+    };
+
+    let page_name = render::page_filename(&raw_url);
+    let options = render::PageOptions {
+        title: page_name.trim_end_matches(".html"),
+        raw_url: Some(&raw_url),
+        theme,
+    };
+    let filename = request.filename.as_deref();
+    let html = match render::render_document(source, filename, lang, &options) {
+        Ok(html) => html,
+        Err(e) => {
+            eprintln!("Warning: Failed to render page ({}), using raw URL", e);
+            return response;
+        }
+    };
+
+    let page_request = crate::models::UploadRequest::new(
+        html.into_bytes(),
+        Some(page_name),
+        crate::models::UploadType::Paste,
+        Some(crate::models::UploadOptions {
+            skip_sanitize: false,
+            ..request.options.clone()
+        }),
+        false,
+    );
+    let page_response = orchestrator
+        .upload_to_specific_provider(&page_request, &provider_id, None)
+        .await;
+
+    if !page_response.success {
+        eprintln!(
+            "Warning: Failed to upload rendered page ({}), using raw URL",
+            page_response
+                .error
+                .unwrap_or_else(|| "Unknown error".to_string())
+        );
+        return response;
+    }
+
+    eprintln!("Raw file: {}", raw_url);
+    let mut metadata = response.metadata.unwrap_or_default();
+    metadata
+        .provider_specific
+        .insert("raw_url".to_string(), raw_url);
+    crate::models::UploadResponse {
+        url: page_response.url,
+        metadata: Some(metadata),
+        ..response
+    }
+}
+
+/// Uploads a PNG QR code of the URL next to the upload, recording its URL as `qr_url`.
+async fn upload_qr_png(
+    orchestrator: &crate::orchestrator::UploadOrchestrator,
+    mut response: crate::models::UploadResponse,
+) -> crate::models::UploadResponse {
+    let (Some(url), Some(provider_id)) = (response.url.clone(), response.provider_id.clone())
+    else {
+        return response;
+    };
+    let png = match qr::render_png(&url) {
+        Ok(png) => png,
+        Err(e) => {
+            eprintln!("Warning: Failed to create QR code ({})", e);
+            return response;
+        }
+    };
+
+    let request = crate::models::UploadRequest::new(
+        png,
+        Some("*.png".to_string()),
+        crate::models::UploadType::Image,
+        None,
+        false,
+    );
+    let qr_response = orchestrator
+        .upload_to_specific_provider(&request, &provider_id, None)
+        .await;
+    match qr_response.url.filter(|_| qr_response.success) {
+        Some(qr_url) => {
+            eprintln!("QR code: {}", qr_url);
+            response
+                .metadata
+                .get_or_insert_with(Default::default)
+                .provider_specific
+                .insert("qr_url".to_string(), qr_url);
+        }
+        None => eprintln!(
+            "Warning: Failed to upload QR code to {} ({})",
+            provider_id,
+            qr_response
+                .error
+                .unwrap_or_else(|| "Unknown error".to_string())
+        ),
+    }
+    response
+}
+
+/// Uploads the images, their thumbnails and an album page to one page-hosting provider,
+/// returning a response pointing to the album. Images get the same EXIF stripping and SVG
+/// sanitizing as a single upload.
+async fn upload_gallery(
+    args: &Args,
+    gallery: &GalleryArgs,
+    config: Arc<crate::config::Config>,
+) -> Result<crate::models::UploadResponse> {
+    use crate::models::{UploadOptions, UploadRequest, UploadType};
+
+    let orchestrator = crate::orchestrator::UploadOrchestrator::new(config.clone());
+    let group = args.group.clone().unwrap_or_else(|| "images".to_string());
+    let options = UploadOptions {
+        expiration: args.expires.clone(),
+        pages_only: true,
+        skip_sanitize: args.no_sanitize,
+        ..Default::default()
+    };
+    let prepare_options = prepare::PrepareOptions {
+        group: group.clone(),
+        strip_exif: config.general.strip_exif && !args.no_exif,
+        require_stripped: true,
+        sanitize_svg: !args.no_sanitize,
+        ..Default::default()
+    };
+
+    // Each image gets its own bar, kept on screen once it's done
+    let display = crate::models::ProgressDisplay::new(args.progress);
+
+    // The first upload picks the provider; everything else goes to the same one
+    let mut provider_id = args.provider.clone();
+    let upload = |request: UploadRequest,
+                  provider: Option<String>,
+                  progress: Option<crate::models::ProgressTracker>| {
+        let orchestrator = &orchestrator;
+        let group = &group;
+        async move {
+            let progress = progress.as_ref();
+            match provider {
+                Some(name) => {
+                    orchestrator
+                        .upload_to_specific_provider(&request, &name, progress)
+                        .await
+                }
+                None => orchestrator.upload(&request, group, progress).await,
+            }
+        }
+    };
+
+    let mut images = Vec::new();
+    for file in &gallery.images {
+        let content = tokio::fs::read(file)
+            .await
+            .with_context(|| format!("Failed to read file: {}", file))?;
+        let Some(content) = prepare_gallery_image(&config, file, content, &prepare_options)? else {
+            continue;
+        };
+
+        let extension = std::path::Path::new(file)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("img");
+        let thumbnail = render::make_thumbnail(&content);
+        let request = UploadRequest::new(
+            content,
+            Some(format!("*.{}", extension)),
+            UploadType::Image,
+            Some(options.clone()),
+            false,
+        );
+        let progress = display.tracker(request.content.len() as u64, file);
+        let response = upload(request, provider_id.clone(), progress).await;
+        let Some(url) = response.url.clone().filter(|_| response.success) else {
+            anyhow::bail!(
+                "Failed to upload {}: {}\n\
+                 Galleries need an enabled storage provider (ftp_sftp, s3 or bunny) in group '{}'",
+                file,
+                response
+                    .error
+                    .unwrap_or_else(|| "Unknown error".to_string()),
+                group
+            );
+        };
+        if provider_id.is_none() {
+            provider_id = response.provider_id.clone();
+        }
+        display.println(&format!("Uploaded {} to {}", file, url));
+
+        let thumbnail_url = match thumbnail {
+            Some(data) => {
+                let page_name = render::page_filename(&url);
+                let name = format!("{}.thumb.jpg", page_name.trim_end_matches(".html"));
+                let request = UploadRequest::new(
+                    data,
+                    Some(name),
+                    UploadType::Image,
+                    Some(options.clone()),
+                    false,
+                );
+                let response = upload(request, provider_id.clone(), None).await;
+                match response.url.filter(|_| response.success) {
+                    Some(thumbnail_url) => thumbnail_url,
+                    None => {
+                        display
+                            .println(&format!("Warning: Failed to upload thumbnail for {}", file));
+                        url.clone()
+                    }
+                }
+            }
+            None => url.clone(),
+        };
+
+        images.push(render::GalleryImage {
+            url,
+            thumbnail_url,
+            caption: render::caption_for(file),
+        });
+    }
+
+    if images.is_empty() {
+        anyhow::bail!("No images left to upload");
+    }
+
+    let page_options = render::PageOptions {
+        title: &gallery.title,
+        raw_url: None,
+        theme: config.render.theme,
+    };
+    let html = render::render_gallery(&images, &page_options)?;
+    let request = UploadRequest::new(
+        html.into_bytes(),
+        Some("*.html".to_string()),
+        UploadType::Paste,
+        Some(options),
+        false,
+    );
+    Ok(upload(request, provider_id, None).await)
+}
+
+/// Checks that a gallery file is an image and cleans it up like a single upload. Images
+/// whose metadata can't be stripped are skipped, returning `None`, unless `--no-exif` was
+/// given.
+fn prepare_gallery_image(
+    config: &crate::config::Config,
+    file: &str,
+    content: Vec<u8>,
+    options: &prepare::PrepareOptions,
+) -> Result<Option<Vec<u8>>> {
+    let (_, _, upload_type, _) = determine_upload_type(&content, Some(file), false);
+    if upload_type != crate::models::UploadType::Image {
+        anyhow::bail!("Not an image: {}", file);
+    }
+    match prepare::prepare(
+        config,
+        content,
+        Some(file.to_string()),
+        &upload_type,
+        options,
+    ) {
+        Ok(prepared) => Ok(Some(prepared.content)),
+        Err(prepare::PrepareError::Metadata(e)) => {
+            eprintln!(
+                "Warning: Skipping {} ({:#}), use --no-exif to upload it as is",
+                file, e
+            );
+            Ok(None)
+        }
+        Err(e) => Err(anyhow::Error::from(e).context(format!("Failed to prepare {}", file))),
+    }
+}
+
+/// Resolves `--format` or `--template`, if given.
+fn output_template(
+    args: &Args,
+    config: &crate::config::Config,
+) -> Result<Option<template::OutputTemplate>> {
+    match (&args.format, &args.template) {
+        (Some(name), _) => template::OutputTemplate::named(name, &config.templates).map(Some),
+        (None, Some(text)) => template::OutputTemplate::parse(text)
+            .context("Invalid --template")
+            .map(Some),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Prints the upload result in the requested format, exiting with status 1 on failure.
+///
+/// With a template, the formatted link replaces the URL and is what gets copied.
+fn print_response(
+    response: crate::models::UploadResponse,
+    output: &OutputFormat,
+    template: Option<&template::OutputTemplate>,
+    copy_url: bool,
+) -> Result<()> {
+    let formatted = template.and_then(|t| t.render(&response));
+    match output {
+        OutputFormat::Url | OutputFormat::Qr => {
+            if let Some(raw_url) = response.url.clone() {
+                if matches!(output, OutputFormat::Qr) {
+                    print!("{}", qr::render_terminal(&raw_url)?);
+                }
+                let url = formatted.unwrap_or(raw_url);
+                println!("{}", url);
+
+                if copy_url {
+                    if let Err(e) = copy_to_clipboard(&url) {
+                        eprintln!("Warning: Failed to copy to clipboard: {}", e);
+                    } else {
+                        eprintln!("URL copied to clipboard");
+                    }
+                }
+            } else {
+                eprintln!(
+                    "Error: {}",
+                    response
+                        .error
+                        .unwrap_or_else(|| "Unknown error".to_string())
+                );
+                std::process::exit(1);
+            }
+        }
+        OutputFormat::Json => {
+            let mut json = response.to_json();
+            if let Some(formatted) = formatted {
+                json["formatted"] = serde_json::Value::String(formatted);
+            }
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        OutputFormat::Verbose => {
+            println!("{:#?}", response);
+        }
+    }
+
+    Ok(())
+}
 
 fn copy_to_clipboard(text: &str) -> Result<(), Box<dyn std::error::Error>> {
     use arboard::Clipboard;
@@ -52,7 +424,7 @@ struct Args {
     #[clap(short, long, value_name = "FORMAT", default_value = "url")]
     output: OutputFormat,
 
-    /// Provider group to use (files, pastes, images)
+    /// Provider group to use (files, pastes, images); overrides routing rules
     #[clap(short, long, value_name = "GROUP")]
     group: Option<String>,
 
@@ -72,13 +444,269 @@ struct Args {
     #[clap(long)]
     copy_to_clipboard: bool,
 
-    /// Keep EXIF metadata when uploading images (disabled by default)
+    /// Print the link as markdown, html, bbcode, org or a template from [templates] in the
+    /// config; images are embedded
+    #[clap(long, value_name = "FORMAT", conflicts_with = "template")]
+    format: Option<String>,
+
+    /// Print the link using a template, e.g. '![{name}]({url}) ({size_human})'
+    #[clap(long, value_name = "TEMPLATE")]
+    template: Option<String>,
+
+    /// Also upload a PNG QR code of the URL to the same provider and print its URL
+    #[clap(long)]
+    qr_png: bool,
+
+    /// Write machine-readable upload events (attempts, retries, progress) to stderr
+    #[clap(long, value_name = "FORMAT")]
+    events: Option<EventFormat>,
+
+    /// Write events to this inherited file descriptor instead of stderr
+    #[clap(long, value_name = "FD", requires = "events")]
+    events_fd: Option<i32>,
+
+    /// Keep EXIF metadata when uploading images and videos (disabled by default)
     #[clap(long)]
     no_exif: bool,
 
-    /// Create a redirect HTML file that redirects to the provided URL
-    #[clap(short, long, value_name = "URL", conflicts_with = "file", conflicts_with = "input_file", conflicts_with = "clipboard")]
+    /// Upload SVG and HTML files without removing scripts
+    #[clap(long)]
+    no_sanitize: bool,
+
+    /// Shrink images wider than this many pixels before upload
+    #[clap(long, value_name = "PIXELS")]
+    max_width: Option<u32>,
+
+    /// Shrink images taller than this many pixels before upload
+    #[clap(long, value_name = "PIXELS")]
+    max_height: Option<u32>,
+
+    /// Convert images to another format before upload
+    #[clap(long, value_name = "FORMAT")]
+    convert: Option<ConvertFormat>,
+
+    /// Quality for converted JPEG, WebP and AVIF images (1-100)
+    #[clap(long, value_name = "QUALITY", value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
+
+    /// Recompress PNG images losslessly before upload
+    #[clap(long)]
+    optimize_png: bool,
+
+    /// Replace detected secrets in pastes instead of refusing to upload, and apply the
+    /// given redaction rules (e.g. --redact=email,internal_ip)
+    #[clap(
+        long,
+        value_name = "RULES",
+        num_args = 0..=1,
+        require_equals = true,
+        value_delimiter = ',',
+        default_missing_value = ""
+    )]
+    redact: Option<Vec<String>>,
+
+    /// Print the text that would be uploaded after redaction, without uploading
+    #[clap(long)]
+    dry_run: bool,
+
+    /// Also upload an HTML page for pastes on storage providers: highlighted source code,
+    /// or a rendered document for Markdown and Jupyter notebooks
+    #[clap(long)]
+    render: bool,
+
+    /// Language used to highlight the rendered page (e.g. rust, py); defaults to the extension
+    #[clap(long, value_name = "LANG")]
+    lang: Option<String>,
+
+    /// Colour theme of the rendered page
+    #[clap(long, value_name = "THEME")]
+    theme: Option<PageTheme>,
+
+    /// Create a short link: an HTML page that redirects to the provided URL
+    #[clap(
+        short,
+        long,
+        value_name = "URL",
+        conflicts_with = "file",
+        conflicts_with = "input_file",
+        conflicts_with = "clipboard"
+    )]
     redirect: Option<String>,
+
+    /// Custom slug for the short link, used as the redirect page's filename
+    #[clap(
+        long,
+        value_name = "SLUG",
+        requires = "redirect",
+        conflicts_with = "filename"
+    )]
+    slug: Option<String>,
+
+    /// Title shown when the short link is shared
+    #[clap(long, value_name = "TITLE", requires = "redirect")]
+    title: Option<String>,
+
+    /// Description shown when the short link is shared
+    #[clap(long, value_name = "TEXT", requires = "redirect")]
+    description: Option<String>,
+
+    /// Image URL shown when the short link is shared
+    #[clap(long, value_name = "URL", requires = "redirect")]
+    image: Option<String>,
+
+    /// Fetch the preview title, description and image from the target page
+    #[clap(long, requires = "redirect")]
+    fetch_preview: bool,
+
+    /// Show a "you are leaving" page instead of redirecting immediately
+    #[clap(long, requires = "redirect")]
+    interstitial: bool,
+
+    /// Seconds to wait before redirecting
+    #[clap(long, value_name = "SECONDS", requires = "redirect")]
+    delay: Option<u32>,
+
+    /// Config file to use instead of ~/.config/pst/config.toml [env: PST_CONFIG]
+    #[clap(long, value_name = "PATH", global = true)]
+    config: Option<std::path::PathBuf>,
+
+    /// Apply a [profiles.<NAME>] section from the config [env: PST_PROFILE]
+    #[clap(long, value_name = "NAME", global = true)]
+    profile: Option<String>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a command and upload its output as a paste
+    Run(RunArgs),
+    /// Upload several images and an album page linking them
+    Gallery(GalleryArgs),
+    /// Show the metadata and secrets a file or the clipboard would leak, without uploading
+    Inspect(InspectArgs),
+    /// Manage the configuration
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+    /// Accept uploads over HTTP from curl, ShareX and other 0x0.st clients
+    Serve(ServeArgs),
+    /// Print a ShareX, Flameshot or curl uploader for a configured provider
+    Export(ExportArgs),
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Create the config file, asking which providers to enable
+    Init {
+        /// Enable these providers without asking, e.g. `--enable 0x0st --enable s3`
+        #[clap(long, value_name = "PROVIDER")]
+        enable: Vec<String>,
+        /// Replace an existing config file
+        #[clap(long)]
+        force: bool,
+    },
+    /// Print the effective config after profiles and overrides, with secrets hidden
+    Show,
+    /// Check the config for errors
+    Validate,
+    /// Open the config in $EDITOR, saving it only when it is valid
+    Edit,
+    /// Upgrade a config file to the current layout
+    Migrate {
+        /// File to migrate, defaults to the config file
+        #[clap(value_name = "FILE")]
+        file: Option<std::path::PathBuf>,
+        /// Show the changes without writing them
+        #[clap(long)]
+        dry_run: bool,
+    },
+    /// Store a password or key in the OS keyring, for use as `{ keyring = "<NAME>" }`
+    SetSecret {
+        /// Name of the keyring entry, e.g. `bunny`
+        #[clap(value_name = "NAME")]
+        name: String,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct ExportArgs {
+    /// Kind of uploader to generate
+    #[clap(value_name = "FORMAT", value_enum)]
+    format: export::ExportFormat,
+
+    /// Provider to upload to, as named in the config
+    #[clap(value_name = "PROVIDER")]
+    provider: String,
+
+    /// Write passwords and keys into the output instead of refusing
+    #[clap(long)]
+    include_secrets: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
+    /// Address to listen on
+    #[clap(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// Listen on a unix socket instead, readable only by the current user
+    #[clap(long, value_name = "PATH", conflicts_with = "listen")]
+    socket: Option<PathBuf>,
+
+    /// Require `Authorization: Bearer <TOKEN>`, defaults to $PST_SERVE_TOKEN
+    #[clap(long, value_name = "TOKEN")]
+    token: Option<String>,
+
+    /// Reject uploads larger than this
+    #[clap(long, value_name = "MB", default_value_t = 100)]
+    max_size_mb: u64,
+}
+
+#[derive(clap::Args, Debug)]
+struct InspectArgs {
+    /// File to inspect
+    #[clap(value_name = "FILE", conflicts_with = "clipboard")]
+    file: Option<String>,
+
+    /// Inspect the clipboard instead of a file
+    #[clap(short = 'c', long)]
+    clipboard: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct GalleryArgs {
+    /// Title of the album page
+    #[clap(long, value_name = "TITLE", default_value = "Gallery")]
+    title: String,
+
+    /// Images to upload
+    #[clap(value_name = "IMAGES", required = true)]
+    images: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct RunArgs {
+    /// Only upload the output when the command fails
+    #[clap(long)]
+    on_failure: bool,
+
+    /// Show the command output on stderr while it runs
+    #[clap(long)]
+    tee: bool,
+
+    /// Remove ANSI colour codes from the captured output
+    #[clap(long)]
+    strip_ansi: bool,
+
+    /// Command to run, e.g. `pst run -- cargo test`
+    #[clap(
+        value_name = "COMMAND",
+        required = true,
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
+    command: Vec<String>,
 }
 
 fn get_file_path(args: &Args) -> Result<Option<&String>> {
@@ -97,6 +725,51 @@ enum OutputFormat {
     Url,
     Json,
     Verbose,
+    /// The URL as a QR code for scanning with a phone, followed by the URL
+    Qr,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum EventFormat {
+    /// One JSON object per line
+    Ndjson,
+}
+
+/// Runs a `pst config` subcommand. These work even when the config file is broken.
+fn run_config_command(command: &ConfigCommand, options: &crate::config::LoadOptions) -> Result<()> {
+    match command {
+        ConfigCommand::Init { enable, force } => config_command::init(options, enable, *force),
+        ConfigCommand::Show => config_command::show(options),
+        ConfigCommand::Validate => config_command::validate(options),
+        ConfigCommand::Edit => config_command::edit(options),
+        ConfigCommand::Migrate { file, dry_run } => {
+            config_command::migrate(options, file.as_deref(), *dry_run)
+        }
+        ConfigCommand::SetSecret { name } => config_command::set_secret(name),
+    }
+}
+
+/// Opens the event stream requested with `--events`, if any.
+fn event_sink(args: &Args) -> Result<Option<Arc<events::EventSink>>> {
+    let Some(EventFormat::Ndjson) = args.events else {
+        return Ok(None);
+    };
+    let sink = match args.events_fd {
+        Some(fd) => events::EventSink::from_fd(fd)?,
+        None => events::EventSink::stderr(),
+    };
+    Ok(Some(Arc::new(sink)))
+}
+
+/// Emits the final event for an upload.
+fn emit_finished(sink: Option<&events::EventSink>, response: &crate::models::UploadResponse) {
+    if let Some(sink) = sink {
+        sink.emit(events::Event::Finished {
+            success: response.success,
+            url: response.url.clone(),
+            error: response.error.clone(),
+        });
+    }
 }
 
 fn is_stdin_pipe() -> bool {
@@ -138,7 +811,7 @@ fn determine_upload_type(
     content: &[u8],
     filename: Option<&str>,
     from_clipboard: bool,
-) -> (String, Option<String>, crate::models::UploadType) {
+) -> (String, Option<String>, crate::models::UploadType, String) {
     if let Some(name) = filename {
         let ext = std::path::Path::new(name)
             .extension()
@@ -147,11 +820,12 @@ fn determine_upload_type(
 
         let text_extensions = [
             "txt", "md", "rs", "py", "js", "json", "toml", "yaml", "yml", "html", "css", "log",
-            "xml", "csv", "ini", "conf", "sh", "bat",
+            "xml", "csv", "ini", "conf", "sh", "bat", "markdown", "ipynb",
         ];
 
         let image_extensions = [
-            "png", "jpg", "jpeg", "gif", "webp", "svg", "ico", "bmp", "tif", "tiff",
+            "png", "jpg", "jpeg", "gif", "webp", "svg", "ico", "bmp", "tif", "tiff", "heic", "heif",
+            "avif",
         ];
 
         if let Some(ref ext) = ext {
@@ -160,6 +834,7 @@ fn determine_upload_type(
                     "pastes".to_string(),
                     Some(name.to_string()),
                     crate::models::UploadType::Paste,
+                    detect::mime_for_filename(name).to_string(),
                 );
             }
             if image_extensions.contains(&ext.as_str()) {
@@ -167,6 +842,7 @@ fn determine_upload_type(
                     "images".to_string(),
                     Some(name.to_string()),
                     crate::models::UploadType::Image,
+                    detect::mime_for_filename(name).to_string(),
                 );
             }
         }
@@ -176,7 +852,9 @@ fn determine_upload_type(
     if from_clipboard {
         // Try to detect if clipboard contains image data by checking file extension
         if let Some(name) = filename {
-            let image_extensions = ["png", "jpg", "jpeg", "gif", "webp", "bmp", "tif", "tiff"];
+            let image_extensions = [
+                "png", "jpg", "jpeg", "gif", "webp", "bmp", "tif", "tiff", "heic", "heif", "avif",
+            ];
 
             let ext = std::path::Path::new(name)
                 .extension()
@@ -189,82 +867,254 @@ fn determine_upload_type(
                         "images".to_string(),
                         Some(name.to_string()),
                         crate::models::UploadType::Image,
+                        detect::mime_for_filename(name).to_string(),
                     );
                 }
             }
         }
     }
 
+    // Fall back to magic bytes, e.g. for piped content without a filename
+    if let Some(sniffed) = detect::sniff(content) {
+        let (group, upload_type) = match sniffed.kind {
+            ContentKind::Image => ("images", crate::models::UploadType::Image),
+            ContentKind::Text => ("pastes", crate::models::UploadType::Paste),
+            _ => ("files", crate::models::UploadType::File),
+        };
+        let filename = filename
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("*.{}", sniffed.extension));
+        return (
+            group.to_string(),
+            Some(filename),
+            upload_type,
+            sniffed.mime.to_string(),
+        );
+    }
+
+    let mime = filename
+        .map(detect::mime_for_filename)
+        .unwrap_or(detect::DEFAULT_MIME);
+
     if !is_binary_content(content) && is_definitely_text(content) {
+        let mime = if mime == detect::DEFAULT_MIME {
+            "text/plain"
+        } else {
+            mime
+        };
         (
             "pastes".to_string(),
             filename.map(|s| s.to_string()),
             crate::models::UploadType::Paste,
+            mime.to_string(),
         )
     } else {
         (
             "files".to_string(),
             filename.map(|s| s.to_string()),
             crate::models::UploadType::File,
+            mime.to_string(),
         )
     }
 }
 
+/// Reads the clipboard as content and a filename for the upload.
+async fn read_clipboard() -> Result<(Vec<u8>, Option<String>)> {
+    let clipboard_content =
+        ClipboardContent::from_clipboard().context("Failed to read clipboard content")?;
+
+    match clipboard_content {
+        ClipboardContent::Text(text) => {
+            let random_name = clipboard::generate_random_filename("txt");
+            Ok((text.into_bytes(), Some(random_name)))
+        }
+        ClipboardContent::Image { data, format } => {
+            let extension = clipboard::get_clipboard_extension(&format);
+            let random_name = clipboard::generate_random_filename(extension);
+            Ok((data, Some(random_name)))
+        }
+        ClipboardContent::Files(paths) => {
+            if paths.len() == 1 {
+                // Single file from clipboard
+                let path = &paths[0];
+                let content = tokio::fs::read(path).await.with_context(|| {
+                    format!("Failed to read file from clipboard path: {:?}", path)
+                })?;
+                let filename = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|s| s.to_string());
+                Ok((content, filename))
+            } else {
+                // Multiple files - create a text listing
+                let file_list = paths
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let random_name = clipboard::generate_random_filename("txt");
+                Ok((file_list.into_bytes(), Some(random_name)))
+            }
+        }
+        ClipboardContent::Empty => {
+            anyhow::bail!("Clipboard is empty");
+        }
+    }
+}
+
+/// Prints what a file or the clipboard would leak if uploaded, without uploading it.
+async fn inspect_input(
+    args: &Args,
+    inspect_args: &InspectArgs,
+    config: &crate::config::Config,
+) -> Result<()> {
+    let from_clipboard = inspect_args.clipboard || args.clipboard;
+    let (content, filename) = match &inspect_args.file {
+        Some(file) => {
+            let content = tokio::fs::read(file)
+                .await
+                .with_context(|| format!("Failed to read file: {}", file))?;
+            (content, Some(file.clone()))
+        }
+        None if from_clipboard => read_clipboard().await?,
+        None => anyhow::bail!("No input provided. Pass a file or use --clipboard."),
+    };
+
+    let (_, _, upload_type, _) =
+        determine_upload_type(&content, filename.as_deref(), from_clipboard);
+    let content = decode_paste(content, &upload_type);
+    let report = inspect::inspect(&content, &upload_type, &config.secret_scan)?;
+
+    match args.output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report.to_json())?),
+        OutputFormat::Url | OutputFormat::Qr => println!("{}", report.to_text()),
+        OutputFormat::Verbose => println!("{:#?}", report),
+    }
+    Ok(())
+}
+
+/// Converts UTF-16 pastes to UTF-8 so they display correctly everywhere.
+fn decode_paste(content: Vec<u8>, upload_type: &crate::models::UploadType) -> Vec<u8> {
+    match detect::sniff(&content).and_then(|s| s.encoding) {
+        Some(encoding @ (TextEncoding::Utf16Le | TextEncoding::Utf16Be))
+            if *upload_type == crate::models::UploadType::Paste =>
+        {
+            detect::decode_text(&content, encoding)
+                .map(String::into_bytes)
+                .unwrap_or(content)
+        }
+        _ => content,
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
 
     let args = Args::parse();
 
-    let (content, filename): (Vec<u8>, Option<String>) = if let Some(target_url) = &args.redirect {
-        if !target_url.starts_with("http://") && !target_url.starts_with("https://") {
-            anyhow::bail!("Redirect URL must start with http:// or https://");
+    let load_options = crate::config::LoadOptions {
+        path: args.config.clone(),
+        profile: args.profile.clone(),
+        project_dir: std::env::current_dir().ok(),
+    };
+
+    if let Some(Command::Config(command)) = &args.command {
+        return run_config_command(command, &load_options);
+    }
+    let config = Arc::new(
+        crate::config::Config::load(&load_options).with_context(|| "Failed to load config")?,
+    );
+
+    if let Some(Command::Gallery(gallery_args)) = &args.command {
+        let template = output_template(&args, &config)?;
+        let response = upload_gallery(&args, gallery_args, config.clone()).await?;
+        let should_copy = args.copy_to_clipboard || config.general.copy_to_clipboard;
+        return print_response(response, &args.output, template.as_ref(), should_copy);
+    }
+
+    if let Some(Command::Inspect(inspect_args)) = &args.command {
+        return inspect_input(&args, inspect_args, &config).await;
+    }
+
+    if let Some(Command::Export(export_args)) = &args.command {
+        let output = export::export(
+            &config,
+            &export_args.provider,
+            export_args.format,
+            export_args.include_secrets,
+        )?;
+        println!("{}", output);
+        return Ok(());
+    }
+
+    if let Some(Command::Serve(serve_args)) = &args.command {
+        let options = serve::ServeOptions {
+            listen: serve_args.listen.clone(),
+            socket: serve_args.socket.clone(),
+            token: serve_args
+                .token
+                .clone()
+                .or_else(|| std::env::var("PST_SERVE_TOKEN").ok())
+                .filter(|token| !token.is_empty()),
+            max_size: serve_args.max_size_mb * 1024 * 1024,
+        };
+        return serve::run(config, options).await;
+    }
+
+    let events = event_sink(&args)?;
+    let template = output_template(&args, &config)?;
+
+    let mut command_status = None;
+    let mut shorten_url = None;
+
+    let (content, filename, source) = if let Some(Command::Run(run_args)) = &args.command {
+        let options = transcript::CaptureOptions {
+            tee: run_args.tee,
+            strip_ansi: run_args.strip_ansi,
+        };
+        let transcript = transcript::run(&run_args.command, &options).await?;
+
+        if run_args.on_failure && transcript.success() {
+            return Ok(());
         }
-        let html_content = redirect_generator::generate_redirect_html(target_url);
-        let filename = args.filename.clone();
-        (html_content, filename)
-    } else if args.clipboard {
-        // Handle clipboard upload
-        let clipboard_content =
-            ClipboardContent::from_clipboard().context("Failed to read clipboard content")?;
+        command_status = Some(transcript.status.code().unwrap_or(1));
 
-        match clipboard_content {
-            ClipboardContent::Text(text) => {
-                let random_name = clipboard::generate_random_filename("txt");
-                (text.into_bytes(), Some(random_name))
-            }
-            ClipboardContent::Image { data, format } => {
-                let extension = clipboard::get_clipboard_extension(&format);
-                let random_name = clipboard::generate_random_filename(extension);
-                (data, Some(random_name))
-            }
-            ClipboardContent::Files(paths) => {
-                if paths.len() == 1 {
-                    // Single file from clipboard
-                    let path = &paths[0];
-                    let content = tokio::fs::read(path).await.with_context(|| {
-                        format!("Failed to read file from clipboard path: {:?}", path)
-                    })?;
-                    let filename = path
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .map(|s| s.to_string());
-                    (content, filename)
-                } else {
-                    // Multiple files - create a text listing
-                    let file_list = paths
-                        .iter()
-                        .map(|p| p.display().to_string())
-                        .collect::<Vec<_>>()
-                        .join("\n");
-                    let random_name = clipboard::generate_random_filename("txt");
-                    (file_list.into_bytes(), Some(random_name))
-                }
+        (
+            transcript.render(),
+            Some("*.log".to_string()),
+            Some(InputSource::Command),
+        )
+    } else if let Some(target_url) = &args.redirect {
+        let mut page = shortener::RedirectPage::new(target_url)?;
+        page.preview = shortener::LinkPreview {
+            title: args.title.clone(),
+            description: args.description.clone(),
+            image: args.image.clone(),
+        };
+        if args.fetch_preview {
+            match shortener::fetch_preview(&page.target, config.general.timeout_seconds).await {
+                Ok(fetched) => page.preview = std::mem::take(&mut page.preview).or(fetched),
+                Err(e) => eprintln!("Warning: Failed to fetch link preview: {:#}", e),
             }
-            ClipboardContent::Empty => {
-                anyhow::bail!("Clipboard is empty");
+        }
+        page.interstitial = args.interstitial;
+        page.delay = args.delay;
+
+        let filename = match &args.slug {
+            Some(slug) => {
+                shortener::validate_slug(slug)?;
+                Some(format!("{}.html", slug))
             }
+            None => args.filename.clone(),
+        };
+        if page.is_plain() && args.slug.is_none() {
+            shorten_url = Some(page.target.clone());
         }
+        (page.to_html(), filename, None)
+    } else if args.clipboard {
+        let (content, filename) = read_clipboard().await?;
+        (content, filename, Some(InputSource::Clipboard))
     } else if let Some(file) = get_file_path(&args)? {
         let path = PathBuf::from(file);
         if !path.exists() {
@@ -281,7 +1131,7 @@ async fn main() -> Result<()> {
             .and_then(|e| e.to_str())
             .map(|s| format!("*.{}", s)); // * prefix means "use this extension with random name"
 
-        (content, ext)
+        (content, ext, Some(InputSource::File))
     } else if is_stdin_pipe() {
         let mut buffer = Vec::new();
         stdin()
@@ -309,20 +1159,48 @@ async fn main() -> Result<()> {
             anyhow::bail!("No input received from stdin");
         }
 
-        (buffer, None)
+        (buffer, None, Some(InputSource::Stdin))
     } else {
         anyhow::bail!("No input provided. Use --file, --clipboard, or pipe data.");
     };
 
-    let (detected_group, detected_filename, detected_upload_type) =
+    let (detected_group, detected_filename, detected_upload_type, detected_mime) =
         determine_upload_type(&content, filename.as_deref(), args.clipboard);
 
+    let content = decode_paste(content, &detected_upload_type);
+
     let is_redirect = args.redirect.is_some();
-    let has_custom_filename = args.filename.is_some();
+    let has_custom_filename = args.filename.is_some() || args.slug.is_some();
+
+    // `--group` replaces routing, including the rule's expiry and EXIF setting
+    let route = match source {
+        Some(source) if !is_redirect && args.group.is_none() => {
+            let original_name = get_file_path(&args)?
+                .and_then(|f| std::path::Path::new(f).file_name())
+                .and_then(|n| n.to_str())
+                .map(|s| s.to_string());
+            let route_name = args
+                .filename
+                .clone()
+                .or(original_name)
+                .or_else(|| detected_filename.clone());
+
+            config.find_route(&RoutingContext {
+                filename: route_name.as_deref(),
+                mime: Some(&detected_mime),
+                size: content.len() as u64,
+                source,
+            })
+        }
+        _ => None,
+    };
+
     let group = if is_redirect {
         args.group.unwrap_or_else(|| "pastes".to_string())
     } else {
-        args.group.unwrap_or(detected_group)
+        args.group
+            .or_else(|| route.map(|r| r.group.clone()))
+            .unwrap_or(detected_group)
     };
     let final_filename = if is_redirect && !has_custom_filename {
         None
@@ -337,49 +1215,108 @@ async fn main() -> Result<()> {
 
     let force_provider = args.provider.clone();
 
-    let config = Arc::new(
-        crate::config::Config::load()
-            .with_context(|| "Failed to load config from ~/.config/pst/config.toml")?,
-    );
+    let mut transform_config = config.image_transform(&group);
+    transform_config.max_width = args.max_width.or(transform_config.max_width);
+    transform_config.max_height = args.max_height.or(transform_config.max_height);
+    transform_config.format = args.convert.or(transform_config.format);
+    transform_config.quality = args.quality.or(transform_config.quality);
+    transform_config.optimize_png |= args.optimize_png;
 
-    let should_strip_exif = !is_redirect && config.general.strip_exif && !args.no_exif;
+    let strip_exif = route
+        .and_then(|r| r.strip_exif)
+        .unwrap_or(config.general.strip_exif);
 
-    let processed_content = if upload_type == crate::models::UploadType::Image && should_strip_exif
-    {
-        match exif::strip_exif(&content) {
-            Ok(stripped) => {
+    let prepared = if is_redirect {
+        prepare::Prepared {
+            content,
+            filename: final_filename,
+            trusted_only: false,
+        }
+    } else {
+        let options = prepare::PrepareOptions {
+            group: group.clone(),
+            provider: force_provider.clone(),
+            transform: transform_config,
+            strip_exif: strip_exif && !args.no_exif,
+            require_stripped: false,
+            sanitize_svg: !args.no_sanitize,
+            extra_redactions: args
+                .redact
+                .iter()
+                .flatten()
+                .filter(|name| !name.is_empty())
+                .cloned()
+                .collect(),
+            redact_secrets: args.redact.is_some(),
+            allow_secrets: args.dry_run,
+        };
+        match prepare::prepare(&config, content, final_filename, &upload_type, &options) {
+            Ok(prepared) => prepared,
+            Err(prepare::PrepareError::Secrets(report)) => {
                 eprintln!(
-                    "Stripped EXIF metadata from image (original: {} bytes, stripped: {} bytes)",
-                    content.len(),
-                    stripped.len()
+                    "Error: Possible secrets detected, refusing to upload to a public provider:\n{}",
+                    report
                 );
-                stripped
+                eprintln!("Use --redact to replace them, or mark a private provider as trusted.");
+                std::process::exit(1);
             }
-            Err(e) => {
-                eprintln!(
-                    "Warning: Failed to strip EXIF ({}), using original image",
-                    e
+            Err(prepare::PrepareError::Svg(e)) => {
+                return Err(
+                    e.context("Failed to sanitize SVG, use --no-sanitize to upload it as is")
                 );
-                content
             }
+            Err(e) => return Err(e.into()),
         }
-    } else {
-        content
     };
+    let processed_content = prepared.content;
+    let final_filename = prepared.filename;
+    let trusted_only = prepared.trusted_only;
+
+    if args.dry_run {
+        if upload_type == crate::models::UploadType::Paste {
+            std::io::stdout()
+                .write_all(&processed_content)
+                .context("Failed to write dry run output")?;
+        } else {
+            eprintln!(
+                "Dry run: {} bytes would be uploaded to group '{}'",
+                processed_content.len(),
+                group
+            );
+        }
+        return Ok(());
+    }
 
     let request = crate::models::UploadRequest::new(
         processed_content,
         final_filename,
-        upload_type,
+        upload_type.clone(),
         Some(crate::models::UploadOptions {
-            expiration: args.expires,
+            expiration: args.expires.or_else(|| route.and_then(|r| r.expires.clone())),
             secret_url: false,
             custom_name: None,
+            trusted_only,
+            shorten_url,
+            pages_only: false,
+            attachment: false,
+            skip_sanitize: args.no_sanitize,
         }),
         is_redirect,
     );
 
-    let orchestrator = Arc::new(crate::orchestrator::UploadOrchestrator::new(config.clone()));
+    if let Some(sink) = &events {
+        sink.emit(events::Event::Started {
+            filename: request.filename.clone(),
+            bytes: request.file_size(),
+            group: group.clone(),
+        });
+    }
+
+    let mut orchestrator = crate::orchestrator::UploadOrchestrator::new(config.clone());
+    if let Some(sink) = &events {
+        orchestrator = orchestrator.with_events(sink.clone());
+    }
+    let orchestrator = Arc::new(orchestrator);
 
     let progress = orchestrator.create_progress_tracker(&request, "upload", args.progress);
     let progress_ref = progress.as_ref();
@@ -392,43 +1329,93 @@ async fn main() -> Result<()> {
         orchestrator.upload(&request, &group, progress_ref).await
     };
 
-    match args.output {
-        OutputFormat::Url => {
-            if let Some(url) = response.url {
-                println!("{}", url);
+    let should_render = (args.render || config.should_render(&group))
+        && response.success
+        && upload_type == crate::models::UploadType::Paste
+        && !is_redirect
+        && response
+            .provider_id
+            .as_deref()
+            .is_some_and(|id| orchestrator.hosts_pages(id));
+    let response = if should_render {
+        let theme = args.theme.unwrap_or(config.render.theme);
+        upload_rendered_page(
+            &orchestrator,
+            &request,
+            response,
+            args.lang.as_deref(),
+            theme,
+        )
+        .await
+    } else {
+        response
+    };
 
-                // Copy to clipboard if enabled
-                let should_copy = args.copy_to_clipboard || config.general.copy_to_clipboard;
-                if should_copy {
-                    if let Err(e) = copy_to_clipboard(&url) {
-                        eprintln!("Warning: Failed to copy to clipboard: {}", e);
-                    } else {
-                        eprintln!("URL copied to clipboard");
-                    }
-                }
-            } else {
-                eprintln!(
-                    "Error: {}",
-                    response
-                        .error
-                        .unwrap_or_else(|| "Unknown error".to_string())
-                );
-                std::process::exit(1);
-            }
-        }
-        OutputFormat::Json => {
-            let json_output = serde_json::json!({
-                "success": response.success,
-                "url": response.url,
-                "provider": response.provider,
-                "error": response.error,
-            });
-            println!("{}", serde_json::to_string_pretty(&json_output)?);
-        }
-        OutputFormat::Verbose => {
-            println!("{:#?}", response);
-        }
+    let response = if args.qr_png && response.success {
+        upload_qr_png(&orchestrator, response).await
+    } else {
+        response
+    };
+
+    emit_finished(events.as_deref(), &response);
+
+    let should_copy = args.copy_to_clipboard || config.general.copy_to_clipboard;
+    print_response(response, &args.output, template.as_ref(), should_copy)?;
+
+    // Propagate the exit status of `pst run` so it can still fail a script
+    if let Some(code) = command_status.filter(|&code| code != 0) {
+        std::process::exit(code);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_gallery_image_handles_mixed_formats() {
+        let config = crate::config::Config::default_with_ftp();
+        let options = prepare::PrepareOptions {
+            group: "images".to_string(),
+            strip_exif: true,
+            require_stripped: true,
+            sanitize_svg: true,
+            ..Default::default()
+        };
+
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(4, 4)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+        let png = png.into_inner();
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg"><script>x()</script></svg>"#;
+        // Not decodable, so the EXIF stripper fails on it
+        let ico = b"\x00\x00\x01\x00 not really an icon".to_vec();
+
+        let prepared = prepare_gallery_image(&config, "shot.png", png.clone(), &options)
+            .unwrap()
+            .unwrap();
+        assert!(image::load_from_memory(&prepared).is_ok());
+
+        let prepared = prepare_gallery_image(&config, "logo.svg", svg.to_vec(), &options)
+            .unwrap()
+            .unwrap();
+        assert!(!String::from_utf8(prepared).unwrap().contains("<script"));
+
+        // Skipped rather than uploaded with its metadata, unless stripping is turned off
+        let prepared = prepare_gallery_image(&config, "favicon.ico", ico.clone(), &options);
+        assert!(prepared.unwrap().is_none());
+        let no_exif = prepare::PrepareOptions {
+            strip_exif: false,
+            ..options
+        };
+        let prepared = prepare_gallery_image(&config, "favicon.ico", ico.clone(), &no_exif);
+        assert_eq!(prepared.unwrap(), Some(ico));
+
+        assert!(
+            prepare_gallery_image(&config, "notes.pdf", b"%PDF-1.7".to_vec(), &no_exif).is_err()
+        );
+    }
+}
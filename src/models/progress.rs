@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -13,6 +14,8 @@ pub struct UploadProgress {
 pub trait ProgressCallback: Send + Sync {
     fn call(&self, progress: &UploadProgress);
     fn finish(&self) {}
+    /// Called when an upload starts over, e.g. with the next provider
+    fn reset(&self) {}
 }
 
 #[derive(Default)]
@@ -24,12 +27,15 @@ impl ProgressCallback for NoOpCallback {
     fn finish(&self) {}
 }
 
+/// Redraws a single status line, used without the `progress` feature.
+#[cfg(not(feature = "progress"))]
 #[derive(Clone)]
 pub struct VerboseProgressCallback {
     enabled: bool,
     last_output: Arc<Mutex<String>>,
 }
 
+#[cfg(not(feature = "progress"))]
 impl VerboseProgressCallback {
     pub fn new(enabled: bool) -> Self {
         Self {
@@ -46,6 +52,7 @@ impl VerboseProgressCallback {
     }
 }
 
+#[cfg(not(feature = "progress"))]
 impl ProgressCallback for VerboseProgressCallback {
     fn call(&self, progress: &UploadProgress) {
         if !self.enabled {
@@ -80,7 +87,99 @@ impl ProgressCallback for VerboseProgressCallback {
     }
 }
 
-fn format_bytes(bytes: u64) -> String {
+/// Draws an `indicatif` bar with throughput and ETA.
+#[cfg(feature = "progress")]
+pub struct BarProgressCallback {
+    bar: indicatif::ProgressBar,
+}
+
+#[cfg(feature = "progress")]
+impl BarProgressCallback {
+    pub fn new(bar: indicatif::ProgressBar, label: &str) -> Self {
+        let style = indicatif::ProgressStyle::with_template(
+            "{msg:20!} [{bar:30.cyan/blue}] {bytes:>10}/{total_bytes:10} {binary_bytes_per_sec:>12} ETA {eta:>3} {prefix}",
+        )
+        .expect("progress template is valid")
+        .progress_chars("=> ");
+        bar.set_style(style);
+        bar.set_message(label.to_string());
+        Self { bar }
+    }
+}
+
+#[cfg(feature = "progress")]
+impl ProgressCallback for BarProgressCallback {
+    fn call(&self, progress: &UploadProgress) {
+        self.bar.set_length(progress.total_bytes);
+        self.bar.set_position(progress.bytes_uploaded);
+        self.bar.set_prefix(progress.provider.clone());
+    }
+
+    fn finish(&self) {
+        self.bar.finish();
+    }
+
+    fn reset(&self) {
+        self.bar.reset();
+    }
+}
+
+/// Hands out progress trackers for one or more uploads, drawn as stacked `indicatif` bars
+/// with the `progress` feature and as a status line otherwise.
+#[derive(Clone)]
+pub struct ProgressDisplay {
+    enabled: bool,
+    #[cfg(feature = "progress")]
+    bars: indicatif::MultiProgress,
+}
+
+impl ProgressDisplay {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            #[cfg(feature = "progress")]
+            bars: indicatif::MultiProgress::new(),
+        }
+    }
+
+    /// Creates a tracker for one upload, or `None` when progress is disabled.
+    pub fn tracker(&self, total_bytes: u64, label: &str) -> Option<ProgressTracker> {
+        self.callback(total_bytes, label)
+            .map(|callback| ProgressTracker::new(total_bytes, callback, label.to_string()))
+    }
+
+    /// Creates the callback that draws one upload, or `None` when progress is disabled.
+    pub fn callback(&self, total_bytes: u64, label: &str) -> Option<Arc<dyn ProgressCallback>> {
+        if !self.enabled {
+            return None;
+        }
+
+        #[cfg(feature = "progress")]
+        let callback = Arc::new(BarProgressCallback::new(
+            self.bars.add(indicatif::ProgressBar::new(total_bytes)),
+            label,
+        ));
+        #[cfg(not(feature = "progress"))]
+        let callback = {
+            let _ = (total_bytes, label);
+            Arc::new(VerboseProgressCallback::new(true))
+        };
+
+        Some(callback)
+    }
+
+    /// Prints a line without breaking up the bars.
+    pub fn println(&self, line: &str) {
+        #[cfg(feature = "progress")]
+        if self.enabled {
+            let _ = self.bars.println(line);
+            return;
+        }
+        eprintln!("{}", line);
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
     const GB: u64 = MB * 1024;
@@ -98,50 +197,94 @@ fn format_bytes(bytes: u64) -> String {
 
 #[derive(Clone)]
 pub struct ProgressTracker {
-    total_bytes: u64,
-    bytes_uploaded: Arc<std::sync::atomic::AtomicU64>,
+    total_bytes: Arc<AtomicU64>,
+    bytes_uploaded: Arc<AtomicU64>,
     callback: Arc<dyn ProgressCallback>,
-    provider: String,
+    provider: Arc<Mutex<String>>,
 }
 
 impl ProgressTracker {
     pub fn new(total_bytes: u64, callback: Arc<dyn ProgressCallback>, provider: String) -> Self {
         Self {
-            total_bytes,
-            bytes_uploaded: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            total_bytes: Arc::new(AtomicU64::new(total_bytes)),
+            bytes_uploaded: Arc::new(AtomicU64::new(0)),
             callback,
-            provider,
+            provider: Arc::new(Mutex::new(provider)),
         }
     }
 
     pub fn add_progress(&self, bytes: u64) {
-        let current = self
-            .bytes_uploaded
-            .fetch_add(bytes, std::sync::atomic::Ordering::SeqCst);
-        let total = current + bytes;
+        let current = self.bytes_uploaded.fetch_add(bytes, Ordering::SeqCst);
+        self.report(current + bytes);
+    }
+
+    /// Starts over at zero, e.g. before retrying or falling back to the next provider.
+    pub fn reset(&self, provider: &str, total_bytes: u64) {
+        self.total_bytes.store(total_bytes, Ordering::SeqCst);
+        self.bytes_uploaded.store(0, Ordering::SeqCst);
+        *self.provider.lock().unwrap() = provider.to_string();
+        self.callback.reset();
+        self.report(0);
+    }
+
+    pub fn finish(&self) {
+        self.report(self.total_bytes.load(Ordering::SeqCst));
+        self.callback.finish();
+    }
 
+    fn report(&self, bytes_uploaded: u64) {
+        let total_bytes = self.total_bytes.load(Ordering::SeqCst);
         let progress = UploadProgress {
-            bytes_uploaded: total,
-            total_bytes: self.total_bytes,
-            provider: self.provider.clone(),
-            percentage: if self.total_bytes > 0 {
-                (total as f64 / self.total_bytes as f64) * 100.0
+            bytes_uploaded,
+            total_bytes,
+            provider: self.provider.lock().unwrap().clone(),
+            percentage: if total_bytes > 0 {
+                (bytes_uploaded as f64 / total_bytes as f64) * 100.0
             } else {
                 0.0
             },
         };
-
         self.callback.call(&progress);
     }
+}
 
-    pub fn finish(&self) {
-        let progress = UploadProgress {
-            bytes_uploaded: self.total_bytes,
-            total_bytes: self.total_bytes,
-            provider: self.provider.clone(),
-            percentage: 100.0,
-        };
-        self.callback.call(&progress);
-        self.callback.finish();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Recorder(Mutex<Vec<(u64, u64, String)>>);
+
+    impl ProgressCallback for Recorder {
+        fn call(&self, progress: &UploadProgress) {
+            self.0.lock().unwrap().push((
+                progress.bytes_uploaded,
+                progress.total_bytes,
+                progress.provider.clone(),
+            ));
+        }
+    }
+
+    #[test]
+    fn test_tracker_reset_starts_over_with_next_provider() {
+        let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+        let tracker = ProgressTracker::new(100, recorder.clone(), "upload".to_string());
+
+        tracker.reset("0x0st", 100);
+        tracker.add_progress(60);
+        tracker.reset("uguu", 80);
+        tracker.add_progress(50);
+        tracker.finish();
+
+        let calls = recorder.0.lock().unwrap();
+        assert_eq!(
+            *calls,
+            vec![
+                (0, 100, "0x0st".to_string()),
+                (60, 100, "0x0st".to_string()),
+                (0, 80, "uguu".to_string()),
+                (50, 80, "uguu".to_string()),
+                (80, 80, "uguu".to_string()),
+            ]
+        );
     }
 }
@@ -24,6 +24,17 @@ pub struct UploadOptions {
     pub secret_url: bool,
     #[allow(dead_code)]
     pub custom_name: Option<String>,
+    /// Only upload to providers marked as trusted, e.g. because the content contains secrets
+    pub trusted_only: bool,
+    /// Only upload to providers that serve HTML pages, e.g. for files linked from a gallery
+    pub pages_only: bool,
+    /// Target of a plain redirect, for providers that can shorten links natively instead of
+    /// hosting the redirect page
+    pub shorten_url: Option<String>,
+    /// Ask the provider to serve the upload as a download rather than display it
+    pub attachment: bool,
+    /// Upload HTML as is, e.g. with `--no-sanitize`
+    pub skip_sanitize: bool,
 }
 
 impl UploadRequest {
@@ -43,13 +54,21 @@ impl UploadRequest {
         }
     }
 
-    #[allow(dead_code)]
     pub fn file_size(&self) -> u64 {
         self.content.len() as u64
     }
 }
 
 impl UploadType {
+    /// Lowercase name used in JSON output, e.g. `paste`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            UploadType::Paste => "paste",
+            UploadType::File => "file",
+            UploadType::Image => "image",
+        }
+    }
+
     #[allow(dead_code)]
     pub fn is_text(&self) -> bool {
         matches!(self, UploadType::Paste)
@@ -5,28 +5,57 @@ pub struct UploadResponse {
     pub success: bool,
     pub url: Option<String>,
     pub provider: String,
+    /// Configured name of the provider that produced the response, set by the orchestrator
+    pub provider_id: Option<String>,
     pub error: Option<String>,
-    #[allow(dead_code)]
     pub metadata: Option<ResponseMetadata>,
+    /// Every provider tried, in order, set by the orchestrator
+    pub attempts: Vec<Attempt>,
 }
 
 #[derive(Debug, Clone, Default)]
-#[allow(dead_code)]
 pub struct ResponseMetadata {
+    /// Name the upload is served under
     pub filename: Option<String>,
+    /// Name of the file that was uploaded, when it had one
+    pub original_name: Option<String>,
     pub file_size: Option<u64>,
+    /// Hex-encoded SHA-256 of the uploaded bytes
+    pub sha256: Option<String>,
+    pub mime_type: Option<String>,
+    pub upload_type: Option<String>,
+    /// Provider group the upload was routed through
+    pub group: Option<String>,
     pub expiration: Option<String>,
+    /// Secret the provider accepts for deleting or managing the upload
+    pub delete_token: Option<String>,
+    pub elapsed_ms: Option<u64>,
     pub provider_specific: HashMap<String, String>,
 }
 
+/// How one provider handled an upload, including its retries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attempt {
+    pub provider: String,
+    pub success: bool,
+    /// Kind of error, such as `http_error` or `connection_failed`
+    pub error_class: Option<&'static str>,
+    pub error: Option<String>,
+    pub http_status: Option<u16>,
+    pub retries: u32,
+    pub duration_ms: u64,
+}
+
 impl UploadResponse {
     pub fn success(url: String, provider: String, metadata: Option<ResponseMetadata>) -> Self {
         Self {
             success: true,
             url: Some(url),
             provider,
+            provider_id: None,
             error: None,
             metadata,
+            attempts: Vec::new(),
         }
     }
 
@@ -35,8 +64,10 @@ impl UploadResponse {
             success: false,
             url: None,
             provider,
+            provider_id: None,
             error: Some(error),
             metadata: None,
+            attempts: Vec::new(),
         }
     }
 
@@ -57,8 +88,89 @@ impl UploadResponse {
             success: false,
             url: None,
             provider: "all".to_string(),
+            provider_id: None,
             error: Some(format!("All providers failed: {}", errors_str.join("; "))),
             metadata: None,
+            attempts: Vec::new(),
         }
     }
+
+    /// Formats the response for `--output json`, including metadata and every attempt.
+    pub fn to_json(&self) -> serde_json::Value {
+        let metadata = self.metadata.clone().unwrap_or_default();
+        serde_json::json!({
+            "success": self.success,
+            "url": self.url,
+            "provider": self.provider,
+            "provider_id": self.provider_id,
+            "error": self.error,
+            "filename": metadata.filename,
+            "original_name": metadata.original_name,
+            "size": metadata.file_size,
+            "sha256": metadata.sha256,
+            "mime_type": metadata.mime_type,
+            "type": metadata.upload_type,
+            "group": metadata.group,
+            "expires": metadata.expiration,
+            "delete_token": metadata.delete_token,
+            "elapsed_ms": metadata.elapsed_ms,
+            "provider_specific": metadata.provider_specific,
+            "attempts": self.attempts.iter().map(|a| serde_json::json!({
+                "provider": a.provider,
+                "success": a.success,
+                "error_class": a.error_class,
+                "error": a.error,
+                "http_status": a.http_status,
+                "retries": a.retries,
+                "duration_ms": a.duration_ms,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json_includes_metadata_and_attempts() {
+        let mut response = UploadResponse::success(
+            "https://0x0.st/abc.txt".to_string(),
+            "0x0.st".to_string(),
+            Some(ResponseMetadata {
+                file_size: Some(5),
+                delete_token: Some("secret".to_string()),
+                ..Default::default()
+            }),
+        );
+        response.attempts = vec![
+            Attempt {
+                provider: "uguu.se".to_string(),
+                success: false,
+                error_class: Some("http_error"),
+                error: Some("HTTP error 503: busy".to_string()),
+                http_status: Some(503),
+                retries: 3,
+                duration_ms: 7012,
+            },
+            Attempt {
+                provider: "0x0.st".to_string(),
+                success: true,
+                error_class: None,
+                error: None,
+                http_status: None,
+                retries: 0,
+                duration_ms: 412,
+            },
+        ];
+
+        let json = response.to_json();
+        assert_eq!(json["success"], true);
+        assert_eq!(json["size"], 5);
+        assert_eq!(json["delete_token"], "secret");
+        assert!(json["sha256"].is_null());
+        assert_eq!(json["attempts"][0]["http_status"], 503);
+        assert_eq!(json["attempts"][0]["retries"], 3);
+        assert_eq!(json["attempts"][1]["success"], true);
+    }
 }
@@ -1,17 +1,26 @@
-use crate::config::{Config, ProviderConfig};
+use crate::config::{Config, HtmlPolicy, ProviderConfig};
+use crate::detect;
+use crate::events::{Event, EventProgressCallback, EventSink};
 use crate::models::{
-    ProgressTracker, UploadRequest, UploadResponse, UploadType, VerboseProgressCallback,
+    Attempt, ProgressDisplay, ProgressTracker, UploadRequest, UploadResponse, UploadType,
 };
 use crate::providers::{
     BunnyProvider, DirectoryMode, FTPProvider, FtpProviderConfig, PasteRsProvider, S3Provider,
-    TransferProtocol, UguuProvider, UploadError, UploadService, X0AtProvider, ZeroX0STProvider,
+    UguuProvider, UploadError, UploadService, X0AtProvider, ZeroX0STProvider,
 };
+use crate::sanitize;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 
 pub struct UploadOrchestrator {
     providers: Vec<Box<dyn UploadService>>,
     provider_names: HashMap<String, usize>,
+    trusted: Vec<bool>,
+    html_policies: Vec<HtmlPolicy>,
+    events: Option<Arc<EventSink>>,
     config: Arc<Config>,
     max_retries: u32,
     retry_delay_ms: u64,
@@ -23,6 +32,8 @@ impl UploadOrchestrator {
     pub fn new(config: Arc<Config>) -> Self {
         let mut providers: Vec<Box<dyn UploadService>> = Vec::new();
         let mut provider_names: HashMap<String, usize> = HashMap::new();
+        let mut trusted: Vec<bool> = Vec::new();
+        let mut html_policies: Vec<HtmlPolicy> = Vec::new();
 
         let timeout_seconds = config.general.timeout_seconds;
         let max_retries = config.general.max_retries;
@@ -33,6 +44,8 @@ impl UploadOrchestrator {
             {
                 let index = providers.len();
                 providers.push(provider);
+                trusted.push(provider_config.is_trusted());
+                html_policies.push(provider_config.html_policy());
                 provider_names.insert(name.clone(), index);
             }
         }
@@ -40,6 +53,9 @@ impl UploadOrchestrator {
         Self {
             providers,
             provider_names,
+            trusted,
+            html_policies,
+            events: None,
             config,
             max_retries,
             retry_delay_ms,
@@ -47,31 +63,45 @@ impl UploadOrchestrator {
         }
     }
 
+    /// Reports attempts, retries, failures and progress to the sink as they happen.
+    pub fn with_events(mut self, events: Arc<EventSink>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
     pub fn create_progress_tracker(
         &self,
         request: &UploadRequest,
         provider_name: &str,
         show_progress: bool,
     ) -> Option<ProgressTracker> {
-        if !show_progress {
-            return None;
-        }
-
-        let callback = Arc::new(VerboseProgressCallback::new(true));
+        let total_bytes = request.content.len() as u64;
+        let display = ProgressDisplay::new(show_progress).callback(total_bytes, provider_name);
+        let callback = match &self.events {
+            Some(events) => Arc::new(EventProgressCallback::new(events.clone(), display)),
+            None => display?,
+        };
         Some(ProgressTracker::new(
-            request.content.len() as u64,
+            total_bytes,
             callback,
             provider_name.to_string(),
         ))
     }
 
+    fn emit(&self, event: Event) {
+        if let Some(events) = &self.events {
+            events.emit(event);
+        }
+    }
+
     pub async fn upload(
         &self,
         request: &UploadRequest,
         group: &str,
         progress: Option<&ProgressTracker>,
     ) -> UploadResponse {
-        let provider_indices = self.get_provider_indices_for_group(group, &request.upload_type);
+        let started = Instant::now();
+        let provider_indices = self.get_provider_indices_for_group(group, request);
 
         if provider_indices.is_empty() {
             return UploadResponse::failed(
@@ -80,34 +110,23 @@ impl UploadOrchestrator {
             );
         }
 
-        if let Some(p) = progress {
-            p.add_progress(0);
-        }
-
         let mut errors: Vec<UploadResponse> = Vec::new();
+        let mut attempts = Vec::new();
 
         for &index in &provider_indices {
-            let provider = self.providers[index].as_ref();
-            match self.try_upload(provider, request, progress).await {
-                Ok(response) if response.success => {
-                    if let Some(p) = progress {
-                        p.finish();
-                    }
-                    return response;
-                }
-                Ok(response) => {
-                    errors.push(response);
-                }
-                Err(error) => {
-                    errors.push(UploadResponse::failed(
-                        provider.provider_name().to_string(),
-                        error.to_string(),
-                    ));
-                }
+            let response = self.attempt(index, request, progress, &mut attempts).await;
+            if response.success {
+                return finish_response(response, attempts, started, Some(group));
             }
+            errors.push(response);
         }
 
-        UploadResponse::all_providers_failed(errors)
+        finish_response(
+            UploadResponse::all_providers_failed(errors),
+            attempts,
+            started,
+            Some(group),
+        )
     }
 
     pub async fn upload_to_specific_provider(
@@ -116,6 +135,7 @@ impl UploadOrchestrator {
         provider_name: &str,
         progress: Option<&ProgressTracker>,
     ) -> UploadResponse {
+        let started = Instant::now();
         let provider_index = self
             .providers
             .iter()
@@ -126,30 +146,36 @@ impl UploadOrchestrator {
         if let Some(index) = provider_index {
             let provider = self.providers[index].as_ref();
 
-            if !provider.supports_upload_type(request.upload_type.clone()) {
+            if request.options.trusted_only && !self.trusted[index] {
                 return UploadResponse::failed(
                     provider_name.to_string(),
                     format!(
-                        "Provider '{}' does not support this upload type",
+                        "Provider '{}' is not trusted to receive this content",
                         provider_name
                     ),
                 );
             }
 
-            if let Some(p) = progress {
-                p.add_progress(0);
+            if request.options.pages_only && !provider.capabilities().hosts_pages {
+                return UploadResponse::failed(
+                    provider_name.to_string(),
+                    format!("Provider '{}' does not serve HTML pages", provider_name),
+                );
             }
 
-            match self.try_upload(provider, request, progress).await {
-                Ok(response) if response.success => {
-                    if let Some(p) = progress {
-                        p.finish();
-                    }
-                    response
-                }
-                Ok(response) => response,
-                Err(error) => UploadResponse::failed(provider_name.to_string(), error.to_string()),
+            if !self.accepts(index, request) {
+                return UploadResponse::failed(
+                    provider_name.to_string(),
+                    format!(
+                        "Provider '{}' does not support this upload type",
+                        provider_name
+                    ),
+                );
             }
+
+            let mut attempts = Vec::new();
+            let response = self.attempt(index, request, progress, &mut attempts).await;
+            finish_response(response, attempts, started, None)
         } else {
             UploadResponse::failed(
                 provider_name.to_string(),
@@ -161,21 +187,187 @@ impl UploadOrchestrator {
         }
     }
 
-    fn get_provider_indices_for_group(&self, group: &str, upload_type: &UploadType) -> Vec<usize> {
+    /// Uploads to one provider, recording the attempt and describing a successful upload.
+    async fn attempt(
+        &self,
+        index: usize,
+        request: &UploadRequest,
+        progress: Option<&ProgressTracker>,
+        attempts: &mut Vec<Attempt>,
+    ) -> UploadResponse {
+        let provider = self.providers[index].as_ref();
+        let prepared = self.prepare(index, request);
+        let started = Instant::now();
+        let mut retries = 0;
+        let result = self
+            .try_upload(provider, &prepared, progress, &mut retries)
+            .await;
+
+        let mut attempt = Attempt {
+            provider: provider.provider_name().to_string(),
+            success: false,
+            error_class: None,
+            error: None,
+            http_status: None,
+            retries,
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+
+        let response = match result {
+            Ok(mut response) if response.success => {
+                if let Some(p) = progress {
+                    p.finish();
+                }
+                response.provider_id = Some(provider.provider_name().to_string());
+                self.describe(provider, &prepared, &mut response);
+                self.emit_succeeded(&response);
+                attempt.success = true;
+                response
+            }
+            Ok(response) => {
+                self.emit_failed(provider, response.error.as_deref());
+                attempt.error_class = Some("upload_failed");
+                attempt.error = response.error.clone();
+                response
+            }
+            Err(error) => {
+                self.emit_failed(provider, Some(&error.to_string()));
+                attempt.error_class = Some(error.class());
+                attempt.error = Some(error.to_string());
+                attempt.http_status = error.status_code();
+                UploadResponse::failed(provider.provider_name().to_string(), error.to_string())
+            }
+        };
+
+        attempts.push(attempt);
+        response
+    }
+
+    /// Fills in the response metadata for what was actually uploaded.
+    fn describe(
+        &self,
+        provider: &dyn UploadService,
+        request: &UploadRequest,
+        response: &mut UploadResponse,
+    ) {
+        let remote_name = response
+            .url
+            .as_deref()
+            .and_then(|url| url.rsplit('/').next())
+            .filter(|name| !name.is_empty())
+            .map(str::to_string);
+
+        let metadata = response.metadata.get_or_insert_with(Default::default);
+        if metadata.filename.is_none() {
+            metadata.filename = remote_name;
+        }
+        // `*.ext` only asks for a random name, so there is nothing to show
+        metadata.original_name = request
+            .filename
+            .as_deref()
+            .filter(|name| !name.starts_with('*') && !request.is_redirect)
+            .and_then(|name| std::path::Path::new(name).file_name())
+            .map(|name| name.to_string_lossy().into_owned());
+        metadata.file_size = Some(request.file_size());
+        metadata.sha256 = Some(format!("{:x}", Sha256::digest(&request.content)));
+        metadata.mime_type = Some(match metadata.filename.as_deref() {
+            Some(name) if name.contains('.') => detect::mime_for_filename(name).to_string(),
+            _ if request.upload_type == UploadType::Paste => "text/plain".to_string(),
+            _ => "application/octet-stream".to_string(),
+        });
+        metadata.upload_type = Some(request.upload_type.name().to_string());
+        if metadata.expiration.is_none() && provider.capabilities().supports_expiration {
+            metadata.expiration = request.options.expiration.clone();
+        }
+    }
+
+    /// Whether the named provider serves uploaded `.html` files as web pages.
+    pub fn hosts_pages(&self, provider_name: &str) -> bool {
+        self.providers
+            .iter()
+            .find(|p| p.provider_name() == provider_name)
+            .is_some_and(|p| p.capabilities().hosts_pages)
+    }
+
+    fn get_provider_indices_for_group(&self, group: &str, request: &UploadRequest) -> Vec<usize> {
         let provider_names = self.config.get_providers_for_group(group);
 
         provider_names
             .into_iter()
             .filter_map(|(name, _)| self.provider_names.get(&name).copied())
-            .filter(|&index| self.providers[index].supports_upload_type(upload_type.clone()))
+            .filter(|&index| !request.options.trusted_only || self.trusted[index])
+            .filter(|&index| {
+                !request.options.pages_only || self.providers[index].capabilities().hosts_pages
+            })
+            .filter(|&index| self.accepts(index, request))
             .collect()
     }
 
+    /// Whether the provider can handle the request, either by type or as a native short link.
+    fn accepts(&self, index: usize, request: &UploadRequest) -> bool {
+        let provider = &self.providers[index];
+        provider.supports_upload_type(request.upload_type.clone())
+            || (request.options.shorten_url.is_some()
+                && provider.capabilities().supports_shortening)
+    }
+
+    /// Applies the provider's HTML policy to HTML uploads. Redirect pages and uploads marked
+    /// `skip_sanitize` are passed through unchanged.
+    fn prepare<'a>(&self, index: usize, request: &'a UploadRequest) -> Cow<'a, UploadRequest> {
+        if request.is_redirect
+            || request.options.skip_sanitize
+            || !sanitize::is_html(request.filename.as_deref())
+        {
+            return Cow::Borrowed(request);
+        }
+
+        let provider = self.providers[index].as_ref();
+        let mut prepared = request.clone();
+        match self.html_policies[index] {
+            HtmlPolicy::Allow => return Cow::Borrowed(request),
+            HtmlPolicy::Attachment if provider.capabilities().supports_attachment => {
+                prepared.options.attachment = true;
+            }
+            HtmlPolicy::Csp => {
+                let html = String::from_utf8_lossy(&request.content);
+                prepared.content = sanitize::add_csp(&html).into_bytes();
+            }
+            HtmlPolicy::Attachment | HtmlPolicy::Sanitize => {
+                let html = String::from_utf8_lossy(&request.content);
+                let sanitized = sanitize::sanitize_html(&html);
+                if let Some(summary) = sanitized.summary() {
+                    log::info!(
+                        "Sanitized HTML for {}: {}",
+                        provider.provider_name(),
+                        summary
+                    );
+                }
+                prepared.content = sanitized.content.into_bytes();
+            }
+        }
+        Cow::Owned(prepared)
+    }
+
+    fn emit_succeeded(&self, response: &UploadResponse) {
+        self.emit(Event::Succeeded {
+            provider: response.provider_id.clone().unwrap_or_default(),
+            url: response.url.clone(),
+        });
+    }
+
+    fn emit_failed(&self, provider: &dyn UploadService, error: Option<&str>) {
+        self.emit(Event::ProviderFailed {
+            provider: provider.provider_name().to_string(),
+            error: error.unwrap_or("Unknown error").to_string(),
+        });
+    }
+
     async fn try_upload(
         &self,
         provider: &dyn UploadService,
         request: &UploadRequest,
         progress: Option<&ProgressTracker>,
+        retries: &mut u32,
     ) -> Result<UploadResponse, UploadError> {
         let content_size = request.content.len() as u64;
         if content_size > provider.max_file_size() {
@@ -192,19 +384,34 @@ impl UploadOrchestrator {
             )));
         }
 
-        let mut retries = 0;
         let mut last_error = None;
 
-        while retries <= self.max_retries {
+        while *retries <= self.max_retries {
+            self.emit(Event::ProviderAttempt {
+                provider: provider.provider_name().to_string(),
+                attempt: *retries + 1,
+            });
+            if let Some(p) = progress {
+                p.reset(provider.provider_name(), content_size);
+            }
             match provider.upload(request, progress).await {
                 Ok(response) => return Ok(response),
+                // Retrying would only prompt to unlock the secret again
+                Err(error @ UploadError::SecretUnavailable(_)) => return Err(error),
                 Err(error) => {
+                    let message = error.to_string();
                     last_error = Some(error);
 
-                    if retries < self.max_retries {
-                        let delay = self.retry_delay_ms * (2_u64.pow(retries));
+                    if *retries < self.max_retries {
+                        let delay = self.retry_delay_ms * (2_u64.pow(*retries));
+                        self.emit(Event::Retry {
+                            provider: provider.provider_name().to_string(),
+                            attempt: *retries + 1,
+                            delay_ms: delay,
+                            error: message,
+                        });
                         tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
-                        retries += 1;
+                        *retries += 1;
                     } else {
                         break;
                     }
@@ -217,27 +424,40 @@ impl UploadOrchestrator {
     }
 }
 
+/// Adds the attempts, total time and group to a finished upload.
+fn finish_response(
+    mut response: UploadResponse,
+    attempts: Vec<Attempt>,
+    started: Instant,
+    group: Option<&str>,
+) -> UploadResponse {
+    let metadata = response.metadata.get_or_insert_with(Default::default);
+    metadata.elapsed_ms = Some(started.elapsed().as_millis() as u64);
+    metadata.group = group.map(str::to_string);
+    response.attempts = attempts;
+    response
+}
+
 fn create_provider(
     name: &str,
     config: &ProviderConfig,
     timeout_seconds: u64,
 ) -> Option<Box<dyn UploadService>> {
     match name.to_lowercase().as_str() {
-        "0x0st" | "0x0.st" => Some(Box::new(ZeroX0STProvider::new(timeout_seconds))),
-        "paste_rs" | "paste.rs" => Some(Box::new(PasteRsProvider::new(timeout_seconds))),
-        "uguu" | "uguu.se" => Some(Box::new(UguuProvider::new(timeout_seconds))),
-        "x0at" | "x0.at" => Some(Box::new(X0AtProvider::new(timeout_seconds))),
+        name if ZeroX0STProvider::NAMES.contains(&name) => {
+            Some(Box::new(ZeroX0STProvider::new(timeout_seconds)))
+        }
+        name if PasteRsProvider::NAMES.contains(&name) => {
+            Some(Box::new(PasteRsProvider::new(timeout_seconds)))
+        }
+        name if UguuProvider::NAMES.contains(&name) => {
+            Some(Box::new(UguuProvider::new(timeout_seconds)))
+        }
+        name if X0AtProvider::NAMES.contains(&name) => {
+            Some(Box::new(X0AtProvider::new(timeout_seconds)))
+        }
         "ftp_sftp" | "ftp" | "sftp" => {
             if let ProviderConfig::FtpSftp(ftp_config) = config {
-                // Determine which protocol to use
-                let protocol = if ftp_config.enable_sftp {
-                    TransferProtocol::Sftp
-                } else if ftp_config.enable_ftps {
-                    TransferProtocol::Ftps
-                } else {
-                    TransferProtocol::Ftp
-                };
-
                 let ssh_key_path = ftp_config
                     .ssh_private_key
                     .clone()
@@ -247,7 +467,7 @@ fn create_provider(
                     .unwrap_or(DirectoryMode::CreateIfMissing);
 
                 Some(Box::new(FTPProvider::new(FtpProviderConfig {
-                    protocol,
+                    protocol: ftp_config.protocol,
                     host: ftp_config.host.clone(),
                     port: ftp_config.port,
                     username: ftp_config.username.clone(),
@@ -1,11 +1,12 @@
+use crate::config::{LazySecret, Secret};
 use crate::models::{ProgressTracker, UploadRequest, UploadResponse, UploadType};
-use crate::providers::{UploadError, UploadService};
+use crate::providers::{counting_body, ProviderCapabilities, UploadError, UploadService};
 use async_trait::async_trait;
 use rand::Rng;
 
 pub struct BunnyProvider {
     storage_zone: String,
-    access_key: String,
+    access_key: LazySecret,
     region: Option<String>,
     public_url: String,
     max_file_size_mb: u64,
@@ -15,7 +16,7 @@ pub struct BunnyProvider {
 impl BunnyProvider {
     pub fn new(
         storage_zone: String,
-        access_key: String,
+        access_key: Secret,
         region: Option<String>,
         public_url: String,
         max_file_size_mb: u64,
@@ -23,7 +24,7 @@ impl BunnyProvider {
     ) -> Self {
         Self {
             storage_zone,
-            access_key,
+            access_key: LazySecret::new(access_key),
             region,
             public_url,
             max_file_size_mb,
@@ -109,10 +110,22 @@ impl UploadService for BunnyProvider {
         self.max_file_size_mb * 1024 * 1024
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_expiration: false,
+            supports_custom_names: true,
+            requires_auth: true,
+            supports_direct_text: false,
+            hosts_pages: true,
+            supports_shortening: false,
+            supports_attachment: false,
+        }
+    }
+
     async fn upload(
         &self,
         request: &UploadRequest,
-        _progress: Option<&ProgressTracker>,
+        progress: Option<&ProgressTracker>,
     ) -> Result<UploadResponse, UploadError> {
         let content_size = request.content.len() as u64;
 
@@ -123,6 +136,11 @@ impl UploadService for BunnyProvider {
             });
         }
 
+        let access_key = self
+            .access_key
+            .get()
+            .map_err(|e| UploadError::SecretUnavailable(e.to_string()))?;
+
         let client = reqwest::Client::builder()
             .timeout(std::time::Duration::from_secs(self.timeout_seconds))
             .user_agent(format!("pst/{}", env!("CARGO_PKG_VERSION")))
@@ -134,9 +152,10 @@ impl UploadService for BunnyProvider {
 
         let response = client
             .put(&upload_url)
-            .header("AccessKey", &self.access_key)
-            .header("Content-Type", "application/octet-stream")
-            .body(request.content.clone())
+            .header("AccessKey", access_key)
+            .header("Content-Type", crate::detect::mime_for_filename(&filename))
+            .header(reqwest::header::CONTENT_LENGTH, request.content.len())
+            .body(counting_body(&request.content, progress))
             .send()
             .await
             .map_err(|e| UploadError::ConnectionFailed(e.to_string()))?;
@@ -148,10 +167,10 @@ impl UploadService for BunnyProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(UploadError::UploadFailed(format!(
-                "HTTP {}: {}",
-                status, error_text
-            )));
+            return Err(UploadError::HttpError {
+                status_code: status.as_u16(),
+                message: error_text,
+            });
         }
 
         let final_url = format!("{}/{}", self.public_url, filename);
@@ -172,7 +191,7 @@ mod tests {
     fn test_build_upload_url_with_region() {
         let provider = BunnyProvider::new(
             "my-storage-zone".to_string(),
-            "test-key".to_string(),
+            Secret::from("test-key"),
             Some("ny".to_string()),
             "https://cdn.example.com".to_string(),
             500,
@@ -190,7 +209,7 @@ mod tests {
     fn test_build_upload_url_without_region() {
         let provider = BunnyProvider::new(
             "my-storage-zone".to_string(),
-            "test-key".to_string(),
+            Secret::from("test-key"),
             None,
             "https://cdn.example.com".to_string(),
             500,
@@ -205,7 +224,7 @@ mod tests {
     fn test_build_upload_url_empty_region() {
         let provider = BunnyProvider::new(
             "my-storage-zone".to_string(),
-            "test-key".to_string(),
+            Secret::from("test-key"),
             Some("".to_string()),
             "https://cdn.example.com".to_string(),
             500,
@@ -220,7 +239,7 @@ mod tests {
     fn test_get_filename_with_extension() {
         let provider = BunnyProvider::new(
             "my-storage-zone".to_string(),
-            "test-key".to_string(),
+            Secret::from("test-key"),
             None,
             "https://cdn.example.com".to_string(),
             500,
@@ -244,7 +263,7 @@ mod tests {
     fn test_get_filename_with_custom_name() {
         let provider = BunnyProvider::new(
             "my-storage-zone".to_string(),
-            "test-key".to_string(),
+            Secret::from("test-key"),
             None,
             "https://cdn.example.com".to_string(),
             500,
@@ -267,7 +286,7 @@ mod tests {
     fn test_get_filename_paste_generates_txt() {
         let provider = BunnyProvider::new(
             "my-storage-zone".to_string(),
-            "test-key".to_string(),
+            Secret::from("test-key"),
             None,
             "https://cdn.example.com".to_string(),
             500,
@@ -285,7 +304,7 @@ mod tests {
     fn test_get_filename_redirect_generates_html() {
         let provider = BunnyProvider::new(
             "my-storage-zone".to_string(),
-            "test-key".to_string(),
+            Secret::from("test-key"),
             None,
             "https://cdn.example.com".to_string(),
             500,
@@ -303,7 +322,7 @@ mod tests {
     fn test_get_filename_file_generates_bin() {
         let provider = BunnyProvider::new(
             "my-storage-zone".to_string(),
-            "test-key".to_string(),
+            Secret::from("test-key"),
             None,
             "https://cdn.example.com".to_string(),
             500,
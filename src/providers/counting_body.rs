@@ -0,0 +1,76 @@
+use crate::models::ProgressTracker;
+use futures_util::stream::{self, Stream, StreamExt};
+use reqwest::multipart::Part;
+use reqwest::Body;
+
+/// Size of the chunks request bodies are streamed in, small enough for smooth progress bars.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Builds a request body that reports each chunk to the tracker as it is sent.
+///
+/// Without a tracker the content is sent in one piece. Streamed bodies have no length of
+/// their own, so callers should set `Content-Length` themselves.
+pub fn counting_body(content: &[u8], progress: Option<&ProgressTracker>) -> Body {
+    let Some(progress) = progress.cloned() else {
+        return Body::from(content.to_vec());
+    };
+
+    Body::wrap_stream(counting_stream(content, progress))
+}
+
+fn counting_stream(
+    content: &[u8],
+    progress: ProgressTracker,
+) -> impl Stream<Item = Result<Vec<u8>, std::io::Error>> {
+    let chunks: Vec<Vec<u8>> = content.chunks(CHUNK_SIZE).map(<[u8]>::to_vec).collect();
+    stream::iter(chunks).map(move |chunk| {
+        progress.add_progress(chunk.len() as u64);
+        Ok(chunk)
+    })
+}
+
+/// Builds a multipart file part that reports its progress like [`counting_body`].
+pub fn counting_part(content: &[u8], progress: Option<&ProgressTracker>) -> Part {
+    match progress {
+        Some(_) => Part::stream_with_length(counting_body(content, progress), content.len() as u64),
+        None => Part::bytes(content.to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ProgressCallback, UploadProgress};
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder(Mutex<Vec<u64>>);
+
+    impl ProgressCallback for Recorder {
+        fn call(&self, progress: &UploadProgress) {
+            self.0.lock().unwrap().push(progress.bytes_uploaded);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_counting_body_reports_chunks() {
+        let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+        let content = vec![0u8; CHUNK_SIZE * 2 + 10];
+        let tracker = ProgressTracker::new(content.len() as u64, recorder.clone(), "test".into());
+
+        let mut sent = Vec::new();
+        let mut stream = Box::pin(counting_stream(&content, tracker));
+        while let Some(chunk) = stream.next().await {
+            sent.extend_from_slice(&chunk.unwrap());
+        }
+
+        assert_eq!(sent, content);
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![
+                CHUNK_SIZE as u64,
+                CHUNK_SIZE as u64 * 2,
+                content.len() as u64
+            ]
+        );
+    }
+}
@@ -1,15 +1,19 @@
+use crate::config::{LazySecret, Secret};
 use crate::models::{ProgressTracker, UploadRequest, UploadResponse, UploadType};
-use crate::providers::{UploadError, UploadService};
+use crate::providers::{ProviderCapabilities, UploadError, UploadService};
 use async_ssh2_lite::{AsyncSession, TokioTcpStream};
 use async_trait::async_trait;
 use futures_util::io::AsyncWriteExt;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TransferProtocol {
     Ftp,
     Ftps,
+    #[default]
     Sftp,
 }
 
@@ -18,9 +22,9 @@ pub struct FTPProvider {
     host: String,
     port: u16,
     username: String,
-    password: Option<String>,
+    password: Option<LazySecret>,
     ssh_key_path: Option<PathBuf>,
-    ssh_key_passphrase: Option<String>,
+    ssh_key_passphrase: Option<LazySecret>,
     directory: String,
     public_url: String,
     #[allow(dead_code)]
@@ -54,9 +58,9 @@ pub struct FtpProviderConfig {
     pub host: String,
     pub port: u16,
     pub username: String,
-    pub password: Option<String>,
+    pub password: Option<Secret>,
     pub ssh_key_path: Option<String>,
-    pub ssh_key_passphrase: Option<String>,
+    pub ssh_key_passphrase: Option<Secret>,
     pub directory: String,
     pub public_url: String,
     pub directory_mode: DirectoryMode,
@@ -71,9 +75,9 @@ impl FTPProvider {
             host: config.host,
             port: config.port,
             username: config.username,
-            password: config.password,
+            password: config.password.map(LazySecret::new),
             ssh_key_path: config.ssh_key_path.map(PathBuf::from),
-            ssh_key_passphrase: config.ssh_key_passphrase,
+            ssh_key_passphrase: config.ssh_key_passphrase.map(LazySecret::new),
             directory: config.directory,
             public_url: config.public_url,
             directory_mode: config.directory_mode,
@@ -83,7 +87,13 @@ impl FTPProvider {
     }
 
     fn get_filename(&self, request: &UploadRequest) -> String {
-        if let Some(name) = &request.filename {
+        // Only the last part of a name is kept, so an upload can't land outside `directory`
+        let name = request
+            .filename
+            .as_deref()
+            .and_then(|name| name.rsplit(['/', '\\']).next())
+            .filter(|name| !matches!(name.trim(), "" | "." | ".."));
+        if let Some(name) = name {
             // Check for * prefix which means "use this extension with random name"
             if name.starts_with("*.") {
                 let ext = &name[1..]; // Remove * to get .ext
@@ -98,7 +108,7 @@ impl FTPProvider {
                     .collect();
                 return format!("{}{}", random, ext);
             }
-            name.clone()
+            name.to_string()
         } else if request.is_redirect {
             const CHARSET: &[u8] =
                 b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
@@ -153,6 +163,18 @@ impl UploadService for FTPProvider {
         self.max_file_size
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_expiration: false,
+            supports_custom_names: true,
+            requires_auth: true,
+            supports_direct_text: false,
+            hosts_pages: true,
+            supports_shortening: false,
+            supports_attachment: false,
+        }
+    }
+
     async fn upload(
         &self,
         request: &UploadRequest,
@@ -177,6 +199,14 @@ impl UploadService for FTPProvider {
     }
 }
 
+/// Unlocks an optional secret, only when the provider actually needs it.
+fn resolve(secret: Option<&LazySecret>) -> Result<Option<&str>, UploadError> {
+    secret
+        .map(|secret| secret.get())
+        .transpose()
+        .map_err(|e| UploadError::SecretUnavailable(e.to_string()))
+}
+
 impl FTPProvider {
     async fn upload_sftp(
         &self,
@@ -199,20 +229,16 @@ impl FTPProvider {
         let auth_result = if let Some(ref key_path) = self.ssh_key_path {
             if tokio::fs::metadata(key_path).await.is_ok() {
                 let key_path = std::path::Path::new(key_path);
+                let passphrase = resolve(self.ssh_key_passphrase.as_ref())?;
                 session
-                    .userauth_pubkey_file(
-                        &self.username,
-                        None,
-                        key_path,
-                        self.ssh_key_passphrase.as_deref(),
-                    )
+                    .userauth_pubkey_file(&self.username, None, key_path, passphrase)
                     .await
-            } else if let Some(ref password) = self.password {
+            } else if let Some(password) = resolve(self.password.as_ref())? {
                 session.userauth_password(&self.username, password).await
             } else {
                 return Err(UploadError::AuthenticationFailed);
             }
-        } else if let Some(ref password) = self.password {
+        } else if let Some(password) = resolve(self.password.as_ref())? {
             session.userauth_password(&self.username, password).await
         } else {
             return Err(UploadError::AuthenticationFailed);
@@ -281,3 +307,47 @@ impl FTPProvider {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> FTPProvider {
+        FTPProvider::new(FtpProviderConfig {
+            protocol: TransferProtocol::Sftp,
+            host: "files.example.com".to_string(),
+            port: 22,
+            username: "me".to_string(),
+            password: None,
+            ssh_key_path: None,
+            ssh_key_passphrase: None,
+            directory: "/var/www/uploads".to_string(),
+            public_url: "https://files.example.com".to_string(),
+            directory_mode: DirectoryMode::ExistingOnly,
+            max_file_size_mb: 100,
+            ascii_mode_for_pastes: false,
+        })
+    }
+
+    fn filename_for(name: &str) -> String {
+        let request = UploadRequest::new(
+            b"content".to_vec(),
+            Some(name.to_string()),
+            UploadType::File,
+            None,
+            false,
+        );
+        provider().get_filename(&request)
+    }
+
+    #[test]
+    fn test_get_filename_keeps_names_inside_directory() {
+        assert_eq!(filename_for("shot.png"), "shot.png");
+        assert_eq!(filename_for("../../etc/cron.d/job"), "job");
+        assert_eq!(filename_for("..\\..\\evil.bat"), "evil.bat");
+
+        let filename = filename_for("../..");
+        assert!(filename.ends_with(".bin"));
+        assert_eq!(filename.len(), 12); // 8 random chars + .bin
+    }
+}
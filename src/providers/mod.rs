@@ -1,4 +1,5 @@
 mod bunny;
+mod counting_body;
 mod ftp_provider;
 mod paste_rs;
 mod s3;
@@ -8,6 +9,7 @@ mod x0_at;
 mod zerox;
 
 pub use bunny::*;
+pub use counting_body::*;
 pub use ftp_provider::*;
 pub use paste_rs::*;
 pub use s3::*;
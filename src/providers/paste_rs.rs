@@ -1,5 +1,5 @@
 use crate::models::{ProgressTracker, UploadRequest, UploadResponse, UploadType};
-use crate::providers::{UploadError, UploadService};
+use crate::providers::{counting_body, UploadError, UploadService};
 use async_trait::async_trait;
 
 pub struct PasteRsProvider {
@@ -8,9 +8,13 @@ pub struct PasteRsProvider {
 }
 
 impl PasteRsProvider {
+    /// Names the provider can be configured under
+    pub const NAMES: &'static [&'static str] = &["paste_rs", "paste.rs"];
+    pub const ENDPOINT: &'static str = "https://paste.rs";
+
     pub fn new(timeout_seconds: u64) -> Self {
         Self {
-            endpoint: "https://paste.rs".to_string(),
+            endpoint: Self::ENDPOINT.to_string(),
             timeout_seconds,
         }
     }
@@ -33,7 +37,7 @@ impl UploadService for PasteRsProvider {
     async fn upload(
         &self,
         request: &UploadRequest,
-        _progress: Option<&ProgressTracker>,
+        progress: Option<&ProgressTracker>,
     ) -> Result<UploadResponse, UploadError> {
         let content_size = request.content.len() as u64;
 
@@ -51,7 +55,8 @@ impl UploadService for PasteRsProvider {
 
         let response = client
             .post(&self.endpoint)
-            .body(request.content.clone())
+            .header(reqwest::header::CONTENT_LENGTH, request.content.len())
+            .body(counting_body(&request.content, progress))
             .send()
             .await
             .map_err(|e| UploadError::ConnectionFailed(e.to_string()))?;
@@ -63,10 +68,10 @@ impl UploadService for PasteRsProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(UploadError::UploadFailed(format!(
-                "HTTP {}: {}",
-                status, error_text
-            )));
+            return Err(UploadError::HttpError {
+                status_code: status.as_u16(),
+                message: error_text,
+            });
         }
 
         let url = response
@@ -85,7 +90,7 @@ impl UploadService for PasteRsProvider {
         let url = if url.starts_with("http") {
             url
         } else {
-            format!("https://paste.rs/{}", url)
+            format!("{}/{}", Self::ENDPOINT, url)
         };
 
         Ok(UploadResponse::success(
@@ -1,5 +1,5 @@
 use crate::models::{ProgressTracker, UploadRequest, UploadResponse, UploadType};
-use crate::providers::{UploadError, UploadService};
+use crate::providers::{counting_part, UploadError, UploadService};
 use async_trait::async_trait;
 use serde::Deserialize;
 
@@ -22,9 +22,15 @@ pub struct UguuProvider {
 }
 
 impl UguuProvider {
+    /// Names the provider can be configured under
+    pub const NAMES: &'static [&'static str] = &["uguu", "uguu.se"];
+    pub const ENDPOINT: &'static str = "https://uguu.se/upload";
+    /// Multipart field holding the uploaded file
+    pub const FILE_FIELD: &'static str = "files[]";
+
     pub fn new(timeout_seconds: u64) -> Self {
         Self {
-            endpoint: "https://uguu.se/upload".to_string(),
+            endpoint: Self::ENDPOINT.to_string(),
             timeout_seconds,
         }
     }
@@ -50,7 +56,7 @@ impl UploadService for UguuProvider {
     async fn upload(
         &self,
         request: &UploadRequest,
-        _progress: Option<&ProgressTracker>,
+        progress: Option<&ProgressTracker>,
     ) -> Result<UploadResponse, UploadError> {
         let content_size = request.content.len() as u64;
 
@@ -76,38 +82,13 @@ impl UploadService for UguuProvider {
         let filename = request
             .filename
             .clone()
-            .unwrap_or_else(|| default_filename);
+            .unwrap_or(default_filename);
 
-        // Determine mime type from filename extension
-        let mime_type = request
-            .filename
-            .as_ref()
-            .and_then(|name| {
-                std::path::Path::new(name)
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-            })
-            .map(|ext| match ext.to_lowercase().as_str() {
-                "txt" | "log" | "md" => "text/plain",
-                "html" | "htm" => "text/html",
-                "css" => "text/css",
-                "js" => "application/javascript",
-                "json" => "application/json",
-                "xml" => "application/xml",
-                "png" => "image/png",
-                "jpg" | "jpeg" => "image/jpeg",
-                "gif" => "image/gif",
-                "webp" => "image/webp",
-                "svg" => "image/svg+xml",
-                "pdf" => "application/pdf",
-                "zip" => "application/zip",
-                _ => "application/octet-stream",
-            })
-            .unwrap_or("application/octet-stream");
+        let mime_type = crate::detect::mime_for_filename(&filename);
 
         let form = reqwest::multipart::Form::new().part(
-            "files[]",
-            reqwest::multipart::Part::bytes(request.content.clone())
+            Self::FILE_FIELD,
+            counting_part(&request.content, progress)
                 .file_name(filename)
                 .mime_str(mime_type)
                 .map_err(|e| UploadError::UploadFailed(e.to_string()))?,
@@ -128,10 +109,10 @@ impl UploadService for UguuProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(UploadError::UploadFailed(format!(
-                "HTTP {}: {}",
-                status, error_text
-            )));
+            return Err(UploadError::HttpError {
+                status_code: status.as_u16(),
+                message: error_text,
+            });
         }
 
         let response_text = response
@@ -10,7 +10,6 @@ pub enum UploadError {
     UploadFailed(String),
 
     #[error("HTTP error {status_code}: {message}")]
-    #[allow(dead_code)]
     HttpError { status_code: u16, message: String },
 
     #[error("File too large: max {max_size} bytes, got {actual_size} bytes")]
@@ -23,6 +22,9 @@ pub enum UploadError {
     #[error("Authentication failed")]
     AuthenticationFailed,
 
+    #[error("Secret unavailable: {0}")]
+    SecretUnavailable(String),
+
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
 
@@ -34,6 +36,33 @@ pub enum UploadError {
     Timeout(String),
 }
 
+impl UploadError {
+    /// Short machine-readable name for the kind of error, e.g. `http_error`.
+    pub fn class(&self) -> &'static str {
+        match self {
+            UploadError::ConnectionFailed(_) => "connection_failed",
+            UploadError::UploadFailed(_) => "upload_failed",
+            UploadError::HttpError { .. } => "http_error",
+            UploadError::FileTooLarge { .. } => "file_too_large",
+            UploadError::RateLimited { .. } => "rate_limited",
+            UploadError::AuthenticationFailed => "authentication_failed",
+            UploadError::SecretUnavailable(_) => "secret_unavailable",
+            UploadError::InvalidResponse(_) => "invalid_response",
+            UploadError::ProviderNotAvailable(_) => "provider_not_available",
+            UploadError::Timeout(_) => "timeout",
+        }
+    }
+
+    /// HTTP status returned by the provider, when the error came from one.
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            UploadError::HttpError { status_code, .. } => Some(*status_code),
+            UploadError::RateLimited { .. } => Some(429),
+            _ => None,
+        }
+    }
+}
+
 #[async_trait]
 pub trait UploadService: Send + Sync {
     fn provider_name(&self) -> &str;
@@ -52,13 +81,15 @@ pub trait UploadService: Send + Sync {
         true
     }
 
-    #[allow(dead_code)]
     fn capabilities(&self) -> ProviderCapabilities {
         ProviderCapabilities {
             supports_expiration: false,
             supports_custom_names: false,
             requires_auth: false,
             supports_direct_text: false,
+            hosts_pages: false,
+            supports_shortening: false,
+            supports_attachment: false,
         }
     }
 }
@@ -70,4 +101,10 @@ pub struct ProviderCapabilities {
     pub supports_custom_names: bool,
     pub requires_auth: bool,
     pub supports_direct_text: bool,
+    /// Uploaded `.html` files are served as web pages
+    pub hosts_pages: bool,
+    /// Redirects can be created from `UploadOptions::shorten_url` without an HTML page
+    pub supports_shortening: bool,
+    /// Uploads can be served with `Content-Disposition: attachment`
+    pub supports_attachment: bool,
 }
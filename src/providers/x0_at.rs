@@ -1,7 +1,138 @@
 use crate::models::{ProgressTracker, UploadRequest, UploadResponse, UploadType};
-use crate::providers::{ProviderCapabilities, UploadError, UploadService};
+use crate::providers::{counting_part, ProviderCapabilities, UploadError, UploadService};
 use async_trait::async_trait;
 
+pub struct X0AtProvider {
+    endpoint: String,
+    timeout_seconds: u64,
+}
+
+impl X0AtProvider {
+    /// Names the provider can be configured under
+    pub const NAMES: &'static [&'static str] = &["x0at", "x0.at"];
+    pub const ENDPOINT: &'static str = "https://x0.at/";
+    /// Multipart field holding the uploaded file
+    pub const FILE_FIELD: &'static str = "file";
+
+    pub fn new(timeout_seconds: u64) -> Self {
+        Self {
+            endpoint: Self::ENDPOINT.to_string(),
+            timeout_seconds,
+        }
+    }
+}
+
+#[async_trait]
+impl UploadService for X0AtProvider {
+    fn provider_name(&self) -> &str {
+        "x0at"
+    }
+
+    fn supports_upload_type(&self, upload_type: UploadType) -> bool {
+        matches!(
+            upload_type,
+            UploadType::File | UploadType::Image | UploadType::Paste
+        )
+    }
+
+    fn max_file_size(&self) -> u64 {
+        512 * 1024 * 1024 // 512 MiB
+    }
+
+    async fn upload(
+        &self,
+        request: &UploadRequest,
+        progress: Option<&ProgressTracker>,
+    ) -> Result<UploadResponse, UploadError> {
+        let content_size = request.content.len() as u64;
+
+        if content_size > self.max_file_size() {
+            return Err(UploadError::FileTooLarge {
+                max_size: self.max_file_size(),
+                actual_size: content_size,
+            });
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(self.timeout_seconds))
+            .build()
+            .map_err(|e| UploadError::ConnectionFailed(e.to_string()))?;
+
+        let default_filename = if request.is_redirect {
+            "redirect.html".to_string()
+        } else {
+            "file".to_string()
+        };
+
+        let filename = request
+            .filename
+            .clone()
+            .unwrap_or(default_filename);
+
+        let mime_type = crate::detect::mime_for_filename(&filename);
+
+        let form = reqwest::multipart::Form::new().part(
+            Self::FILE_FIELD,
+            counting_part(&request.content, progress)
+                .file_name(filename)
+                .mime_str(mime_type)
+                .map_err(|e| UploadError::UploadFailed(e.to_string()))?,
+        );
+
+        let response = client
+            .post(&self.endpoint)
+            .header("User-Agent", format!("pst/{}", env!("CARGO_PKG_VERSION")))
+            .multipart(form)
+            .send()
+            .await
+            .map_err(|e| UploadError::ConnectionFailed(e.to_string()))?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(UploadError::HttpError {
+                status_code: status.as_u16(),
+                message: error_text,
+            });
+        }
+
+        let url = response
+            .text()
+            .await
+            .map_err(|e| UploadError::InvalidResponse(e.to_string()))?;
+
+        let url = url.trim().to_string();
+
+        if url.is_empty() {
+            return Err(UploadError::InvalidResponse(
+                "Empty response from server".to_string(),
+            ));
+        }
+
+        Ok(UploadResponse::success(
+            url,
+            self.provider_name().to_string(),
+            None,
+        ))
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            supports_expiration: false,
+            supports_custom_names: false,
+            requires_auth: false,
+            supports_direct_text: false,
+            hosts_pages: false,
+            supports_shortening: false,
+            supports_attachment: false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,26 +218,7 @@ mod tests {
         ];
         
         for (filename, expected_mime) in test_cases {
-            let mime_type: &str = std::path::Path::new(filename)
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .map(|ext| match ext.to_lowercase().as_str() {
-                    "txt" | "log" | "md" => "text/plain",
-                    "html" | "htm" => "text/html",
-                    "css" => "text/css",
-                    "js" => "application/javascript",
-                    "json" => "application/json",
-                    "xml" => "application/xml",
-                    "png" => "image/png",
-                    "jpg" | "jpeg" => "image/jpeg",
-                    "gif" => "image/gif",
-                    "webp" => "image/webp",
-                    "svg" => "image/svg+xml",
-                    "pdf" => "application/pdf",
-                    "zip" => "application/zip",
-                    _ => "application/octet-stream",
-                })
-                .unwrap_or("application/octet-stream");
+            let mime_type = crate::detect::mime_for_filename(filename);
                 
             assert_eq!(mime_type, expected_mime, "Failed for filename: {}", filename);
         }
@@ -281,149 +393,3 @@ mod tests {
         assert_eq!(request.file_size(), 12);
     }
 }
-
-pub struct X0AtProvider {
-    endpoint: String,
-    timeout_seconds: u64,
-}
-
-impl X0AtProvider {
-    pub fn new(timeout_seconds: u64) -> Self {
-        Self {
-            endpoint: "https://x0.at/".to_string(),
-            timeout_seconds,
-        }
-    }
-}
-
-#[async_trait]
-impl UploadService for X0AtProvider {
-    fn provider_name(&self) -> &str {
-        "x0at"
-    }
-
-    fn supports_upload_type(&self, upload_type: UploadType) -> bool {
-        matches!(
-            upload_type,
-            UploadType::File | UploadType::Image | UploadType::Paste
-        )
-    }
-
-    fn max_file_size(&self) -> u64 {
-        512 * 1024 * 1024 // 512 MiB
-    }
-
-    async fn upload(
-        &self,
-        request: &UploadRequest,
-        _progress: Option<&ProgressTracker>,
-    ) -> Result<UploadResponse, UploadError> {
-        let content_size = request.content.len() as u64;
-
-        if content_size > self.max_file_size() {
-            return Err(UploadError::FileTooLarge {
-                max_size: self.max_file_size(),
-                actual_size: content_size,
-            });
-        }
-
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(self.timeout_seconds))
-            .build()
-            .map_err(|e| UploadError::ConnectionFailed(e.to_string()))?;
-
-        let default_filename = if request.is_redirect {
-            "redirect.html".to_string()
-        } else {
-            "file".to_string()
-        };
-
-        let filename = request
-            .filename
-            .clone()
-            .unwrap_or_else(|| default_filename);
-
-        let mime_type = request
-            .filename
-            .as_ref()
-            .and_then(|name| {
-                std::path::Path::new(name)
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-            })
-            .map(|ext| match ext.to_lowercase().as_str() {
-                "txt" | "log" | "md" => "text/plain",
-                "html" | "htm" => "text/html",
-                "css" => "text/css",
-                "js" => "application/javascript",
-                "json" => "application/json",
-                "xml" => "application/xml",
-                "png" => "image/png",
-                "jpg" | "jpeg" => "image/jpeg",
-                "gif" => "image/gif",
-                "webp" => "image/webp",
-                "svg" => "image/svg+xml",
-                "pdf" => "application/pdf",
-                "zip" => "application/zip",
-                _ => "application/octet-stream",
-            })
-            .unwrap_or("application/octet-stream");
-
-        let form = reqwest::multipart::Form::new().part(
-            "file",
-            reqwest::multipart::Part::bytes(request.content.clone())
-                .file_name(filename)
-                .mime_str(mime_type)
-                .map_err(|e| UploadError::UploadFailed(e.to_string()))?,
-        );
-
-        let response = client
-            .post(&self.endpoint)
-            .header("User-Agent", format!("pst/{}", env!("CARGO_PKG_VERSION")))
-            .multipart(form)
-            .send()
-            .await
-            .map_err(|e| UploadError::ConnectionFailed(e.to_string()))?;
-
-        let status = response.status();
-
-        if !status.is_success() {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(UploadError::UploadFailed(format!(
-                "HTTP {}: {}",
-                status, error_text
-            )));
-        }
-
-        let url = response
-            .text()
-            .await
-            .map_err(|e| UploadError::InvalidResponse(e.to_string()))?;
-
-        let url = url.trim().to_string();
-
-        if url.is_empty() {
-            return Err(UploadError::InvalidResponse(
-                "Empty response from server".to_string(),
-            ));
-        }
-
-        Ok(UploadResponse::success(
-            url,
-            self.provider_name().to_string(),
-            None,
-        ))
-    }
-
-    fn capabilities(&self) -> ProviderCapabilities {
-        ProviderCapabilities {
-            supports_expiration: false,
-            supports_custom_names: false,
-            requires_auth: false,
-            supports_direct_text: false,
-        }
-    }
-}
@@ -1,5 +1,5 @@
-use crate::models::{ProgressTracker, UploadRequest, UploadResponse, UploadType};
-use crate::providers::{ProviderCapabilities, UploadError, UploadService};
+use crate::models::{ProgressTracker, ResponseMetadata, UploadRequest, UploadResponse, UploadType};
+use crate::providers::{counting_part, ProviderCapabilities, UploadError, UploadService};
 use async_trait::async_trait;
 
 pub struct ZeroX0STProvider {
@@ -8,9 +8,15 @@ pub struct ZeroX0STProvider {
 }
 
 impl ZeroX0STProvider {
+    /// Names the provider can be configured under
+    pub const NAMES: &'static [&'static str] = &["0x0st", "0x0.st"];
+    pub const ENDPOINT: &'static str = "https://0x0.st";
+    /// Multipart field holding the uploaded file
+    pub const FILE_FIELD: &'static str = "file";
+
     pub fn new(timeout_seconds: u64) -> Self {
         Self {
-            endpoint: "https://0x0.st".to_string(),
+            endpoint: Self::ENDPOINT.to_string(),
             timeout_seconds,
         }
     }
@@ -33,7 +39,7 @@ impl UploadService for ZeroX0STProvider {
     async fn upload(
         &self,
         request: &UploadRequest,
-        _progress: Option<&ProgressTracker>,
+        progress: Option<&ProgressTracker>,
     ) -> Result<UploadResponse, UploadError> {
         let content_size = request.content.len() as u64;
 
@@ -58,41 +64,22 @@ impl UploadService for ZeroX0STProvider {
         let filename = request
             .filename
             .clone()
-            .unwrap_or_else(|| default_filename);
+            .unwrap_or(default_filename);
 
-        let mime_type = request
-            .filename
-            .as_ref()
-            .and_then(|name| {
-                std::path::Path::new(name)
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-            })
-            .map(|ext| match ext.to_lowercase().as_str() {
-                "txt" | "log" | "md" => "text/plain",
-                "html" | "htm" => "text/html",
-                "css" => "text/css",
-                "js" => "application/javascript",
-                "json" => "application/json",
-                "xml" => "application/xml",
-                "png" => "image/png",
-                "jpg" | "jpeg" => "image/jpeg",
-                "gif" => "image/gif",
-                "webp" => "image/webp",
-                "svg" => "image/svg+xml",
-                "pdf" => "application/pdf",
-                "zip" => "application/zip",
-                _ => "application/octet-stream",
-            })
-            .unwrap_or("application/octet-stream");
-
-        let form = reqwest::multipart::Form::new().part(
-            "file",
-            reqwest::multipart::Part::bytes(request.content.clone())
-                .file_name(filename)
-                .mime_str(mime_type)
-                .map_err(|e| UploadError::UploadFailed(e.to_string()))?,
-        );
+        let form = if let Some(target) = &request.options.shorten_url {
+            // 0x0.st creates short links itself, so the redirect page isn't needed
+            reqwest::multipart::Form::new().text("shorten", target.clone())
+        } else {
+            let mime_type = crate::detect::mime_for_filename(&filename);
+
+            reqwest::multipart::Form::new().part(
+                Self::FILE_FIELD,
+                counting_part(&request.content, progress)
+                    .file_name(filename)
+                    .mime_str(mime_type)
+                    .map_err(|e| UploadError::UploadFailed(e.to_string()))?,
+            )
+        };
 
         let response = client
             .post(&self.endpoint)
@@ -109,12 +96,26 @@ impl UploadService for ZeroX0STProvider {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(UploadError::UploadFailed(format!(
-                "HTTP {}: {}",
-                status, error_text
-            )));
+            return Err(UploadError::HttpError {
+                status_code: status.as_u16(),
+                message: error_text,
+            });
         }
 
+        // 0x0.st returns a management token for deleting the file, and when it expires
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let metadata = ResponseMetadata {
+            delete_token: header("X-Token"),
+            expiration: header("X-Expires"),
+            ..Default::default()
+        };
+
         let url = response
             .text()
             .await
@@ -131,7 +132,7 @@ impl UploadService for ZeroX0STProvider {
         Ok(UploadResponse::success(
             url,
             self.provider_name().to_string(),
-            None,
+            Some(metadata),
         ))
     }
 
@@ -141,6 +142,9 @@ impl UploadService for ZeroX0STProvider {
             supports_custom_names: true,
             requires_auth: false,
             supports_direct_text: false,
+            hosts_pages: false,
+            supports_shortening: true,
+            supports_attachment: false,
         }
     }
 }
@@ -0,0 +1,91 @@
+use anyhow::{anyhow, Result};
+use image::{GrayImage, Luma};
+use qrcodegen::{QrCode, QrCodeEcc};
+use std::io::Cursor;
+
+/// Light modules around the code, which scanners need to find it.
+const QUIET_ZONE: i32 = 2;
+
+/// Pixels per module in PNG images.
+const PNG_SCALE: u32 = 8;
+
+fn encode(text: &str) -> Result<QrCode> {
+    QrCode::encode_text(text, QrCodeEcc::Medium)
+        .map_err(|_| anyhow!("Text is too long for a QR code ({} bytes)", text.len()))
+}
+
+/// Renders text as a QR code for the terminal.
+///
+/// Each character cell holds two modules using a half block, with explicit black and white
+/// ANSI colours so the code scans on both light and dark terminal themes.
+pub fn render_terminal(text: &str) -> Result<String> {
+    let code = encode(text)?;
+    let range = -QUIET_ZONE..code.size() + QUIET_ZONE;
+    let colour = |dark: bool| if dark { 0 } else { 7 };
+
+    let mut output = String::new();
+    for y in range.clone().step_by(2) {
+        for x in range.clone() {
+            let top = colour(code.get_module(x, y));
+            let bottom = colour(code.get_module(x, y + 1));
+            output.push_str(&format!("\x1b[3{};4{}m▀", top, bottom));
+        }
+        output.push_str("\x1b[0m\n");
+    }
+    Ok(output)
+}
+
+/// Renders text as a black and white PNG QR code.
+pub fn render_png(text: &str) -> Result<Vec<u8>> {
+    let code = encode(text)?;
+    let modules = (code.size() + 2 * QUIET_ZONE) as u32;
+    let image = GrayImage::from_fn(modules * PNG_SCALE, modules * PNG_SCALE, |x, y| {
+        let x = (x / PNG_SCALE) as i32 - QUIET_ZONE;
+        let y = (y / PNG_SCALE) as i32 - QUIET_ZONE;
+        Luma([if code.get_module(x, y) { 0 } else { 255 }])
+    });
+
+    let mut output = Cursor::new(Vec::new());
+    image.write_to(&mut output, image::ImageOutputFormat::Png)?;
+    Ok(output.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_terminal_uses_two_modules_per_cell() {
+        let text = "https://0x0.st/Hx3k.png";
+        let size = encode(text).unwrap().size();
+        let rendered = render_terminal(text).unwrap();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        let width = (size + 2 * QUIET_ZONE) as usize;
+        assert_eq!(lines.len(), width.div_ceil(2));
+        assert!(lines
+            .iter()
+            .all(|line| line.matches('▀').count() == width && line.ends_with("\x1b[0m")));
+    }
+
+    #[test]
+    fn test_render_png_round_trips() {
+        let png = render_png("https://example.com/").unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_luma8();
+        let size = encode("https://example.com/").unwrap().size() as u32;
+        assert_eq!(image.width(), (size + 4) * PNG_SCALE);
+        // Quiet zone is white, the finder pattern's corner is black
+        assert_eq!(image.get_pixel(0, 0).0, [255]);
+        assert_eq!(
+            image
+                .get_pixel(QUIET_ZONE as u32 * PNG_SCALE, QUIET_ZONE as u32 * PNG_SCALE)
+                .0,
+            [0]
+        );
+    }
+
+    #[test]
+    fn test_too_long_text_is_an_error() {
+        assert!(render_terminal(&"x".repeat(5000)).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::config::RedactionRule;
+use anyhow::{Context, Result};
+use regex::bytes::Regex;
+
+/// Number of substitutions a single rule made.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactionSummary {
+    pub rule: String,
+    pub substitutions: usize,
+}
+
+/// Applies the rules to the text in order, returning the redacted text and per-rule counts.
+///
+/// Works on the raw bytes, so anything the rules don't match is kept as is, even when it
+/// isn't valid UTF-8.
+pub fn apply(
+    text: &[u8],
+    rules: &[(String, &RedactionRule)],
+) -> Result<(Vec<u8>, Vec<RedactionSummary>)> {
+    let mut output = text.to_vec();
+    let mut summary = Vec::new();
+
+    for (name, rule) in rules {
+        let regex = Regex::new(&rule.regex)
+            .with_context(|| format!("Invalid regex in redaction rule '{}'", name))?;
+
+        let substitutions = regex.find_iter(&output).count();
+        if substitutions > 0 {
+            output = regex
+                .replace_all(&output, rule.replacement.as_bytes())
+                .into_owned();
+        }
+
+        summary.push(RedactionSummary {
+            rule: name.clone(),
+            substitutions,
+        });
+    }
+
+    Ok((output, summary))
+}
+
+/// Formats the summary as `rule: N` pairs, e.g. `email: 2, internal_ip: 1`.
+pub fn format_summary(summary: &[RedactionSummary]) -> String {
+    summary
+        .iter()
+        .map(|s| format!("{}: {}", s.rule, s.substitutions))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(regex: &str, replacement: &str) -> RedactionRule {
+        RedactionRule {
+            regex: regex.to_string(),
+            replacement: replacement.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_applies_rules_in_order_and_counts() {
+        let email = rule(r"[\w.+-]+@[\w-]+\.[\w.]+", "[email]");
+        let ip = rule(r"\b10\.\d+\.\d+\.\d+\b", "[internal-ip]");
+        let rules = vec![
+            ("email".to_string(), &email),
+            ("internal_ip".to_string(), &ip),
+        ];
+
+        let text = "alice@example.com connected from 10.0.3.7, bob@example.org from 8.8.8.8";
+        let (redacted, summary) = apply(text.as_bytes(), &rules).unwrap();
+
+        assert_eq!(
+            redacted,
+            b"[email] connected from [internal-ip], [email] from 8.8.8.8"
+        );
+        assert_eq!(format_summary(&summary), "email: 2, internal_ip: 1");
+    }
+
+    #[test]
+    fn test_replacement_supports_capture_groups() {
+        let user = rule(r"/home/(\w+)/", "/home/$${USER}/");
+        let host = rule(r"host=(\w+)\.corp", "host=${1}.example");
+        let rules = vec![("user".to_string(), &user), ("host".to_string(), &host)];
+
+        let (redacted, _) = apply(b"/home/jdoe/app host=db1.corp", &rules).unwrap();
+        assert_eq!(redacted, b"/home/${USER}/app host=db1.example");
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_kept() {
+        let email = rule(r"[\w.+-]+@[\w-]+\.[\w.]+", "[email]");
+        let rules = vec![("email".to_string(), &email)];
+
+        let (redacted, _) = apply(b"\xff alice@example.com \xfe\xc3", &rules).unwrap();
+        assert_eq!(redacted, b"\xff [email] \xfe\xc3");
+    }
+
+    #[test]
+    fn test_invalid_regex_is_an_error() {
+        let broken = rule("(", "x");
+        assert!(apply(b"text", &[("broken".to_string(), &broken)]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use super::{escape_html, page, theme_css, PageOptions};
+use anyhow::Result;
+use std::io::Cursor;
+
+/// Largest width or height of generated thumbnails, in pixels.
+pub const THUMBNAIL_SIZE: u32 = 480;
+
+const GALLERY_CSS: &str = "\
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(220px, 1fr)); \
+gap: 1em; padding: 1em; }
+figure { margin: 0; }
+figure img { width: 100%; aspect-ratio: 4 / 3; object-fit: cover; border-radius: 4px; display: block; }
+figcaption { font-size: 13px; opacity: 0.75; margin-top: 0.3em; overflow-wrap: anywhere; }
+.lightbox { display: none; position: fixed; inset: 0; background: rgba(0, 0, 0, 0.9); \
+z-index: 10; align-items: center; justify-content: center; flex-direction: column; color: #eee; }
+.lightbox:target { display: flex; }
+.lightbox img { max-width: 92vw; max-height: 82vh; object-fit: contain; }
+.lightbox nav { display: flex; gap: 1.5em; margin-top: 0.75em; }
+.lightbox a { color: #eee; }
+";
+
+/// An uploaded image shown in a gallery.
+#[derive(Debug, Clone)]
+pub struct GalleryImage {
+    pub url: String,
+    pub thumbnail_url: String,
+    pub caption: String,
+}
+
+/// Renders an album page with a thumbnail grid and a CSS-only lightbox.
+pub fn render_gallery(images: &[GalleryImage], options: &PageOptions) -> Result<String> {
+    let mut grid = String::from("<main class=\"grid\">\n");
+    let mut lightboxes = String::new();
+
+    for (index, image) in images.iter().enumerate() {
+        let number = index + 1;
+        let url = escape_html(&image.url);
+        let caption = escape_html(&image.caption);
+
+        grid.push_str(&format!(
+            "<figure><a href=\"#img{n}\"><img src=\"{thumb}\" alt=\"{caption}\" loading=\"lazy\"></a>\
+             <figcaption>{caption}</figcaption></figure>\n",
+            n = number,
+            thumb = escape_html(&image.thumbnail_url),
+            caption = caption
+        ));
+
+        let previous = if number > 1 {
+            format!("<a href=\"#img{}\">&larr; previous</a>", number - 1)
+        } else {
+            String::new()
+        };
+        let next = if number < images.len() {
+            format!("<a href=\"#img{}\">next &rarr;</a>", number + 1)
+        } else {
+            String::new()
+        };
+        lightboxes.push_str(&format!(
+            "<div class=\"lightbox\" id=\"img{n}\"><img src=\"{url}\" alt=\"{caption}\" loading=\"lazy\">\
+             <nav>{previous}<span>{caption} ({n}/{total})</span>{next}\
+             <a href=\"{url}\">original</a><a href=\"#\">close</a></nav></div>\n",
+            n = number,
+            url = url,
+            caption = caption,
+            previous = previous,
+            next = next,
+            total = images.len()
+        ));
+    }
+    grid.push_str("</main>\n");
+
+    let info = match images.len() {
+        1 => "1 image".to_string(),
+        count => format!("{} images", count),
+    };
+    let css = format!("{}{}", theme_css(options.theme)?, GALLERY_CSS);
+    Ok(page(options, &css, &info, &(grid + &lightboxes)))
+}
+
+/// Creates a JPEG thumbnail, or `None` when the image is already small or can't be decoded.
+pub fn make_thumbnail(data: &[u8]) -> Option<Vec<u8>> {
+    let image = image::load_from_memory(data).ok()?;
+    if image.width() <= THUMBNAIL_SIZE && image.height() <= THUMBNAIL_SIZE {
+        return None;
+    }
+
+    let thumbnail =
+        image::DynamicImage::ImageRgb8(image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8());
+    let mut output = Cursor::new(Vec::new());
+    thumbnail
+        .write_to(&mut output, image::ImageOutputFormat::Jpeg(80))
+        .ok()?;
+    Some(output.into_inner())
+}
+
+/// Caption for an image, taken from its filename without the extension.
+pub fn caption_for(path: &str) -> String {
+    let path = std::path::Path::new(path);
+    path.file_stem()
+        .or(path.file_name())
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::PageTheme;
+
+    fn image(name: &str) -> GalleryImage {
+        GalleryImage {
+            url: format!("https://cdn.example.com/{}.png", name),
+            thumbnail_url: format!("https://cdn.example.com/{}.thumb.jpg", name),
+            caption: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_gallery_links_thumbnails_to_lightbox() {
+        let options = PageOptions {
+            title: "Screenshots",
+            raw_url: None,
+            theme: PageTheme::Auto,
+        };
+        let images = vec![image("one"), image("<two>")];
+        let page = render_gallery(&images, &options).unwrap();
+
+        assert!(
+            page.contains("<a href=\"#img1\"><img src=\"https://cdn.example.com/one.thumb.jpg\"")
+        );
+        assert!(page.contains("id=\"img2\""));
+        assert!(page.contains("<a href=\"#img2\">next &rarr;</a>"));
+        assert!(page.contains("<a href=\"#img1\">&larr; previous</a>"));
+        assert!(page.contains("<figcaption>&lt;two&gt;</figcaption>"));
+        assert!(page.contains("2 images"));
+        assert!(!page.contains(">raw</a>"));
+    }
+
+    #[test]
+    fn test_gallery_survives_sanitizing() {
+        let options = PageOptions {
+            title: "Screenshots",
+            raw_url: None,
+            theme: PageTheme::Dark,
+        };
+        let page = render_gallery(&[image("one"), image("two")], &options).unwrap();
+        let sanitized = crate::sanitize::sanitize_html(&page);
+        assert_eq!(sanitized.elements + sanitized.attributes, 0);
+    }
+
+    #[test]
+    fn test_make_thumbnail() {
+        let mut encoded = Cursor::new(Vec::new());
+        image::DynamicImage::new_rgba8(1200, 600)
+            .write_to(&mut encoded, image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let thumbnail = make_thumbnail(encoded.get_ref()).unwrap();
+        let decoded = image::load_from_memory(&thumbnail).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (480, 240));
+
+        let mut small = Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(100, 100)
+            .write_to(&mut small, image::ImageOutputFormat::Png)
+            .unwrap();
+        assert!(make_thumbnail(small.get_ref()).is_none());
+        assert!(make_thumbnail(b"not an image").is_none());
+    }
+
+    #[test]
+    fn test_caption_for() {
+        assert_eq!(
+            caption_for("shots/Screenshot 2024-05-01.png"),
+            "Screenshot 2024-05-01"
+        );
+        assert_eq!(caption_for("photo"), "photo");
+    }
+}
@@ -0,0 +1,222 @@
+use super::{escape_html, page, PageOptions, PageTheme};
+use anyhow::{Context, Result};
+use syntect::highlighting::ThemeSet;
+use syntect::html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::LinesWithEndings;
+
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+const LIGHT_THEME: &str = "InspiredGitHub";
+const DARK_THEME: &str = "base16-ocean.dark";
+
+const CODE_CSS: &str = "\
+table.lines { border-collapse: collapse; width: 100%; \
+font: 13px/1.5 ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+table.lines td { padding: 0 0.75em; vertical-align: top; }
+td.ln { text-align: right; user-select: none; opacity: 0.5; width: 1%; }
+td.ln a { color: inherit; text-decoration: none; }
+td.src pre { margin: 0; font: inherit; white-space: pre; }
+tr:target { background: rgba(255, 200, 0, 0.25); }
+";
+
+/// Renders source code as a highlighted page with numbered, linkable lines.
+///
+/// The syntax is taken from `lang` (a name or extension such as `rust` or `py`), then the
+/// filename's extension, then the first line (e.g. a shebang), falling back to plain text.
+pub fn render_code(
+    source: &str,
+    filename: Option<&str>,
+    lang: Option<&str>,
+    options: &PageOptions,
+) -> Result<String> {
+    let syntaxes = SyntaxSet::load_defaults_newlines();
+    let syntax = find_syntax(&syntaxes, source, filename, lang);
+
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &syntaxes, CLASS_STYLE);
+    for line in LinesWithEndings::from(source) {
+        generator
+            .parse_html_for_line_which_includes_newline(line)
+            .context("Failed to highlight paste")?;
+    }
+    if !source.is_empty() && !source.ends_with('\n') {
+        // Terminate the last line so it is split like the others
+        generator
+            .parse_html_for_line_which_includes_newline("\n")
+            .context("Failed to highlight paste")?;
+    }
+    let html = generator.finalize();
+
+    let mut body = String::from("<table class=\"lines\">\n");
+    for (index, line) in split_lines(&html).iter().enumerate() {
+        let number = index + 1;
+        body.push_str(&format!(
+            "<tr id=\"L{n}\"><td class=\"ln\"><a href=\"#L{n}\">{n}</a></td>\
+             <td class=\"src\"><pre>{line}</pre></td></tr>\n",
+            n = number,
+            line = line
+        ));
+    }
+    body.push_str("</table>\n");
+
+    let css = format!("{}{}", theme_css(options.theme)?, CODE_CSS);
+    let subtitle = escape_html(&syntax.name);
+    Ok(page(options, &css, &subtitle, &body))
+}
+
+/// Highlights a code block inside a document, e.g. a Markdown code fence.
+///
+/// Returns the `<pre>` element; unknown or missing languages are rendered as plain text.
+pub fn highlight_block(code: &str, lang: Option<&str>) -> Result<String> {
+    let syntaxes = SyntaxSet::load_defaults_newlines();
+    let syntax = lang
+        .and_then(|token| syntaxes.find_syntax_by_token(token))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, &syntaxes, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        generator
+            .parse_html_for_line_which_includes_newline(line)
+            .context("Failed to highlight code block")?;
+    }
+    Ok(format!(
+        "<pre class=\"block\">{}</pre>\n",
+        generator.finalize()
+    ))
+}
+
+fn find_syntax<'a>(
+    syntaxes: &'a SyntaxSet,
+    source: &str,
+    filename: Option<&str>,
+    lang: Option<&str>,
+) -> &'a SyntaxReference {
+    lang.and_then(|token| syntaxes.find_syntax_by_token(token))
+        .or_else(|| {
+            filename
+                .and_then(|name| std::path::Path::new(name).extension())
+                .and_then(|ext| ext.to_str())
+                .and_then(|ext| syntaxes.find_syntax_by_extension(ext))
+        })
+        .or_else(|| {
+            source
+                .lines()
+                .next()
+                .and_then(|line| syntaxes.find_syntax_by_first_line(line))
+        })
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+}
+
+/// CSS for the highlighting classes; `auto` switches to the dark theme via a media query.
+pub fn theme_css(theme: PageTheme) -> Result<String> {
+    let themes = ThemeSet::load_defaults();
+    let css_for = |name: &str| {
+        css_for_theme_with_class_style(&themes.themes[name], CLASS_STYLE)
+            .context("Failed to generate theme CSS")
+    };
+
+    Ok(match theme {
+        PageTheme::Light => css_for(LIGHT_THEME)?,
+        PageTheme::Dark => css_for(DARK_THEME)?,
+        PageTheme::Auto => format!(
+            "{}\n@media (prefers-color-scheme: dark) {{\n{}\n}}\n",
+            css_for(LIGHT_THEME)?,
+            css_for(DARK_THEME)?
+        ),
+    })
+}
+
+/// Splits highlighted HTML into lines, closing and reopening spans that cross line breaks.
+fn split_lines(html: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut current = String::new();
+    let mut rest = html;
+
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with("<span") {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            open.push(&rest[..end]);
+            current.push_str(&rest[..end]);
+            rest = &rest[end..];
+        } else if rest.starts_with("</span>") {
+            open.pop();
+            current.push_str("</span>");
+            rest = &rest["</span>".len()..];
+        } else if c == '\n' {
+            current.push_str(&"</span>".repeat(open.len()));
+            lines.push(std::mem::replace(&mut current, open.concat()));
+            rest = &rest[1..];
+        } else {
+            current.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(theme: PageTheme) -> PageOptions<'static> {
+        PageOptions {
+            title: "main.rs",
+            raw_url: Some("https://cdn.example.com/main.rs"),
+            theme,
+        }
+    }
+
+    #[test]
+    fn test_split_lines_reopens_spans() {
+        let html = "<span class=\"a\">one\n<span class=\"b\">two</span>\nthree</span>";
+        assert_eq!(
+            split_lines(html),
+            vec![
+                "<span class=\"a\">one</span>",
+                "<span class=\"a\"><span class=\"b\">two</span></span>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_code_numbers_lines_and_links_raw() {
+        let page = render_code(
+            "fn main() {\n    println!(\"<hi>\");\n}",
+            Some("main.rs"),
+            None,
+            &options(PageTheme::Light),
+        )
+        .unwrap();
+
+        assert!(page.contains("<tr id=\"L1\">"));
+        assert!(page.contains("<a href=\"#L3\">3</a>"));
+        assert!(!page.contains("id=\"L4\""));
+        assert!(page.contains("hl-rust"));
+        assert!(page.contains("&lt;hi&gt;"));
+        assert!(page.contains("href=\"https://cdn.example.com/main.rs\""));
+        assert!(!page.contains("prefers-color-scheme"));
+    }
+
+    #[test]
+    fn test_syntax_selection() {
+        let syntaxes = SyntaxSet::load_defaults_newlines();
+        let pick =
+            |source, filename, lang| find_syntax(&syntaxes, source, filename, lang).name.clone();
+
+        assert_eq!(pick("x = 1", Some("a.rs"), Some("python")), "Python");
+        assert_eq!(pick("x = 1", Some("a.py"), None), "Python");
+        assert_eq!(
+            pick("#!/bin/bash\necho", None, None),
+            "Bourne Again Shell (bash)"
+        );
+        assert_eq!(pick("just text", Some("*.txt"), None), "Plain Text");
+    }
+
+    #[test]
+    fn test_auto_theme_uses_media_query() {
+        let css = theme_css(PageTheme::Auto).unwrap();
+        assert!(css.contains("@media (prefers-color-scheme: dark)"));
+        assert!(css.contains(".hl-code"));
+    }
+}
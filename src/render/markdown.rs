@@ -0,0 +1,175 @@
+use super::{highlight_block, page, theme_css, PageOptions};
+use anyhow::Result;
+use pulldown_cmark::{html, CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
+
+/// Styles for rendered documents, shared by Markdown and notebook pages.
+pub(super) const DOCUMENT_CSS: &str = "\
+.doc { max-width: 52em; margin: 0 auto; padding: 1em 1.5em 3em; }
+.doc pre.block { padding: 0.75em 1em; overflow-x: auto; border-radius: 4px; \
+border: 1px solid rgba(128, 128, 128, 0.3); }
+.doc :not(pre) > code { padding: 0.1em 0.3em; border-radius: 3px; background: rgba(128, 128, 128, 0.15); }
+.doc table { border-collapse: collapse; }
+.doc th, .doc td { border: 1px solid rgba(128, 128, 128, 0.4); padding: 0.3em 0.7em; }
+.doc img { max-width: 100%; }
+.doc blockquote { margin-left: 0; padding-left: 1em; border-left: 3px solid rgba(128, 128, 128, 0.4); }
+";
+
+/// Renders a Markdown document (CommonMark with GFM tables, task lists and strikethrough).
+pub fn render_markdown(source: &str, options: &PageOptions) -> Result<String> {
+    let body = format!(
+        "<main class=\"doc\">\n{}</main>\n",
+        markdown_to_html(source)?
+    );
+    let css = format!("{}{}", theme_css(options.theme)?, DOCUMENT_CSS);
+    Ok(page(options, &css, "Markdown", &body))
+}
+
+/// Converts Markdown to an HTML fragment, highlighting fenced code blocks.
+///
+/// Raw HTML in the source is shown as text and links and images may only point to http,
+/// https, mailto or relative URLs, so a shared document can't run scripts on the storage
+/// provider's domain.
+pub fn markdown_to_html(source: &str) -> Result<String> {
+    let parser_options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+
+    let mut events = Vec::new();
+    let mut code_block: Option<(Option<String>, String)> = None;
+
+    for event in Parser::new_ext(source, parser_options) {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .map(|token| token.to_string()),
+                    CodeBlockKind::Indented => None,
+                };
+                code_block = Some((lang, String::new()));
+            }
+            Event::Text(text) if code_block.is_some() => {
+                if let Some((_, code)) = code_block.as_mut() {
+                    code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((lang, code)) = code_block.take() {
+                    let highlighted = highlight_block(&code, lang.as_deref())?;
+                    events.push(Event::Html(CowStr::from(highlighted)));
+                }
+            }
+            Event::Html(raw) | Event::InlineHtml(raw) => events.push(Event::Text(raw)),
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => events.push(Event::Start(Tag::Link {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            })),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => events.push(Event::Start(Tag::Image {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            })),
+            event => events.push(event),
+        }
+    }
+
+    let mut output = String::new();
+    html::push_html(&mut output, events.into_iter());
+    Ok(output)
+}
+
+/// Replaces link targets with other schemes, such as `javascript:` or `data:`, with `#`.
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    // Browsers ignore whitespace and control characters inside a scheme, e.g. `java\tscript:`
+    let cleaned: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control())
+        .collect();
+    let scheme = cleaned
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| !scheme.contains(['/', '?', '#']));
+    match scheme {
+        None => url,
+        Some(scheme)
+            if ["http", "https", "mailto"].contains(&scheme.to_ascii_lowercase().as_str()) =>
+        {
+            url
+        }
+        Some(_) => CowStr::from("#"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::PageTheme;
+
+    #[test]
+    fn test_tables_and_task_lists() {
+        let html = markdown_to_html("| a | b |\n|---|---|\n| 1 | 2 |\n\n- [x] done\n").unwrap();
+        assert!(html.contains("<table>"));
+        assert!(html.contains("<td>2</td>"));
+        assert!(html.contains("type=\"checkbox\""));
+    }
+
+    #[test]
+    fn test_code_fences_are_highlighted() {
+        let html = markdown_to_html("```rust\nfn main() {}\n```\n").unwrap();
+        assert!(html.contains("<pre class=\"block\">"));
+        assert!(html.contains("hl-rust"));
+    }
+
+    #[test]
+    fn test_raw_html_is_escaped() {
+        let html = markdown_to_html("Hello <script>alert(1)</script>\n").unwrap();
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn test_unsafe_link_schemes_are_removed() {
+        let html = markdown_to_html(
+            "[a](javascript:alert(1)) [b](JavaScript:alert(1)) ![c](data:text/html,x) \
+             [d](<java\tscript:alert(1)>)\n\n\
+             [ok](https://example.com) [mail](mailto:me@example.com) [rel](docs/a.md#b) [q](?a=b:c)\n",
+        )
+        .unwrap();
+        assert!(!html.to_lowercase().contains("script:"), "{}", html);
+        assert!(!html.contains("data:"), "{}", html);
+        assert_eq!(html.matches("href=\"#\"").count(), 3, "{}", html);
+        assert!(html.contains("src=\"#\""));
+        assert!(html.contains("href=\"https://example.com\""));
+        assert!(html.contains("href=\"mailto:me@example.com\""));
+        assert!(html.contains("href=\"docs/a.md#b\""));
+        assert!(html.contains("href=\"?a=b:c\""));
+    }
+
+    #[test]
+    fn test_render_markdown_page() {
+        let options = PageOptions {
+            title: "notes.md",
+            raw_url: Some("https://cdn.example.com/notes.md"),
+            theme: PageTheme::Auto,
+        };
+        let page = render_markdown("# Design\n\nText", &options).unwrap();
+        assert!(page.contains("<h1>Design</h1>"));
+        assert!(page.contains("<main class=\"doc\">"));
+        assert!(page.contains("href=\"https://cdn.example.com/notes.md\""));
+    }
+}
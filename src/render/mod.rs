@@ -0,0 +1,182 @@
+mod gallery;
+mod highlight;
+mod markdown;
+mod notebook;
+
+pub use gallery::*;
+pub use highlight::*;
+pub use markdown::*;
+pub use notebook::*;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Colour scheme of generated pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PageTheme {
+    /// Follow the viewer's system preference
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+/// Properties shared by every generated page.
+#[derive(Debug, Clone)]
+pub struct PageOptions<'a> {
+    pub title: &'a str,
+    /// URL of the raw upload the page is generated from, linked from the header
+    pub raw_url: Option<&'a str>,
+    pub theme: PageTheme,
+}
+
+const PAGE_CSS: &str = "\
+body { margin: 0; font: 15px/1.6 system-ui, -apple-system, Segoe UI, sans-serif; }
+code, pre { font: 13px/1.5 ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; }
+header { display: flex; gap: 1em; align-items: baseline; padding: 0.5em 0.75em; \
+border-bottom: 1px solid rgba(128, 128, 128, 0.3); }
+header .title { font-weight: bold; }
+header .info { opacity: 0.6; flex: 1; }
+header a { color: inherit; }
+";
+
+/// Wraps a rendered body in a self-contained page with a header linking to the raw file.
+pub fn page(options: &PageOptions, css: &str, info: &str, body: &str) -> String {
+    let raw_link = options
+        .raw_url
+        .map(|url| format!("<a href=\"{}\">raw</a>", escape_html(url)))
+        .unwrap_or_default();
+    format!(
+        "<!DOCTYPE html>\n\
+         <html lang=\"en\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n\
+         <style>\n{page_css}{css}</style>\n\
+         </head>\n\
+         <body class=\"hl-code\">\n\
+         <header><span class=\"title\">{title}</span><span class=\"info\">{info}</span>\
+         {raw_link}</header>\n\
+         {body}\
+         </body>\n\
+         </html>\n",
+        title = escape_html(options.title),
+        page_css = PAGE_CSS,
+        css = css,
+        info = info,
+        raw_link = raw_link,
+        body = body
+    )
+}
+
+/// Escapes text for use in HTML element content and quoted attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Renders an upload as a page, picking the renderer from `lang` or the filename's extension:
+/// Markdown and Jupyter notebooks become documents, anything else highlighted source code.
+pub fn render_document(
+    source: &str,
+    filename: Option<&str>,
+    lang: Option<&str>,
+    options: &PageOptions,
+) -> Result<String> {
+    let extension = filename
+        .and_then(|name| std::path::Path::new(name).extension())
+        .and_then(|ext| ext.to_str());
+
+    match lang.or(extension).map(|s| s.to_lowercase()).as_deref() {
+        Some("md" | "markdown") => render_markdown(source, options),
+        Some("ipynb") => render_notebook(source, options),
+        _ => render_code(source, filename, lang, options),
+    }
+}
+
+/// Name for a page uploaded next to a raw file, e.g. `Ab3dE9fG.rs.html`.
+pub fn page_filename(raw_url: &str) -> String {
+    let name = raw_url
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("paste");
+    format!("{}.html", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & 'Jerry'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; &#39;Jerry&#39;&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_render_document_picks_renderer() {
+        let options = PageOptions {
+            title: "doc",
+            raw_url: Some("https://cdn.example.com/doc"),
+            theme: PageTheme::Light,
+        };
+
+        let page = render_document("# Title", Some("*.md"), None, &options).unwrap();
+        assert!(page.contains("<h1>Title</h1>"));
+
+        let page = render_document("# Title", Some("*.md"), Some("python"), &options).unwrap();
+        assert!(page.contains("hl-python"));
+        assert!(!page.contains("<h1>"));
+
+        let notebook = r#"{"cells": [], "metadata": {}}"#;
+        let page = render_document(notebook, Some("nb.ipynb"), None, &options).unwrap();
+        assert!(page.contains("Jupyter notebook"));
+    }
+
+    #[test]
+    fn test_page_filename() {
+        assert_eq!(
+            page_filename("https://cdn.example.com/uploads/Ab3dE9fG.rs"),
+            "Ab3dE9fG.rs.html"
+        );
+        assert_eq!(
+            page_filename("https://cdn.example.com/"),
+            "cdn.example.com.html"
+        );
+        assert_eq!(page_filename(""), "paste.html");
+    }
+
+    #[test]
+    fn test_rendered_pages_survive_sanitizing() {
+        let options = PageOptions {
+            title: "doc",
+            raw_url: Some("https://cdn.example.com/doc"),
+            theme: PageTheme::Dark,
+        };
+
+        for (source, filename) in [
+            ("# Title\n\n[link](https://example.com)", "doc.md"),
+            ("fn main() {}", "main.rs"),
+        ] {
+            let page = render_document(source, Some(filename), None, &options).unwrap();
+            let sanitized = crate::sanitize::sanitize_html(&page);
+            assert_eq!(sanitized.elements + sanitized.attributes, 0, "{}", filename);
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use super::markdown::DOCUMENT_CSS;
+use super::{escape_html, highlight_block, markdown_to_html, page, theme_css, PageOptions};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const NOTEBOOK_CSS: &str = "\
+.cell { margin: 1em 0; }
+.prompt { font: 12px ui-monospace, monospace; opacity: 0.6; }
+.output pre { margin: 0.25em 0; padding: 0.5em 1em; white-space: pre-wrap; }
+.output .error { color: #c62828; }
+";
+
+#[derive(Debug, Deserialize)]
+struct Notebook {
+    cells: Vec<Cell>,
+    #[serde(default)]
+    metadata: NotebookMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NotebookMetadata {
+    kernelspec: Option<KernelSpec>,
+    language_info: Option<LanguageInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KernelSpec {
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LanguageInfo {
+    name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cell_type", rename_all = "lowercase")]
+enum Cell {
+    Markdown {
+        source: MultilineText,
+    },
+    Code {
+        source: MultilineText,
+        execution_count: Option<u64>,
+        #[serde(default)]
+        outputs: Vec<Output>,
+    },
+    Raw {
+        source: MultilineText,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "output_type", rename_all = "snake_case")]
+enum Output {
+    Stream {
+        text: MultilineText,
+    },
+    ExecuteResult {
+        data: HashMap<String, serde_json::Value>,
+    },
+    DisplayData {
+        data: HashMap<String, serde_json::Value>,
+    },
+    Error {
+        ename: String,
+        evalue: String,
+        #[serde(default)]
+        traceback: Vec<String>,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+/// Notebook text fields are either a string or a list of lines.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MultilineText {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+impl MultilineText {
+    fn to_text(&self) -> String {
+        match self {
+            MultilineText::Text(text) => text.clone(),
+            MultilineText::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+/// Renders a Jupyter notebook (nbformat 4) with its cells and text outputs.
+pub fn render_notebook(source: &str, options: &PageOptions) -> Result<String> {
+    let notebook: Notebook =
+        serde_json::from_str(source).context("Failed to parse Jupyter notebook")?;
+
+    let language = notebook
+        .metadata
+        .language_info
+        .and_then(|info| info.name)
+        .or_else(|| notebook.metadata.kernelspec.and_then(|spec| spec.language))
+        .unwrap_or_else(|| "python".to_string());
+
+    let mut body = String::from("<main class=\"doc\">\n");
+    for cell in &notebook.cells {
+        body.push_str("<section class=\"cell\">\n");
+        match cell {
+            Cell::Markdown { source } => body.push_str(&markdown_to_html(&source.to_text())?),
+            Cell::Raw { source } => body.push_str(&format!(
+                "<pre class=\"block\">{}</pre>\n",
+                escape_html(&source.to_text())
+            )),
+            Cell::Code {
+                source,
+                execution_count,
+                outputs,
+            } => {
+                let count = execution_count.map_or(" ".to_string(), |n| n.to_string());
+                body.push_str(&format!("<div class=\"prompt\">In [{}]:</div>\n", count));
+                body.push_str(&highlight_block(&source.to_text(), Some(&language))?);
+                for output in outputs {
+                    body.push_str(&render_output(output));
+                }
+            }
+        }
+        body.push_str("</section>\n");
+    }
+    body.push_str("</main>\n");
+
+    let css = format!(
+        "{}{}{}",
+        theme_css(options.theme)?,
+        DOCUMENT_CSS,
+        NOTEBOOK_CSS
+    );
+    let info = format!("Jupyter notebook ({})", escape_html(&language));
+    Ok(page(options, &css, &info, &body))
+}
+
+fn render_output(output: &Output) -> String {
+    let text = match output {
+        Output::Stream { text } => text.to_text(),
+        Output::ExecuteResult { data } | Output::DisplayData { data } => {
+            match data
+                .get("text/plain")
+                .cloned()
+                .and_then(|value| serde_json::from_value::<MultilineText>(value).ok())
+            {
+                Some(text) => text.to_text(),
+                None => return String::new(),
+            }
+        }
+        Output::Error {
+            ename,
+            evalue,
+            traceback,
+        } => {
+            let text = if traceback.is_empty() {
+                format!("{}: {}", ename, evalue)
+            } else {
+                traceback.join("\n")
+            };
+            // Tracebacks are coloured with ANSI escapes for the terminal
+            let plain = crate::transcript::strip_ansi(text.as_bytes());
+            return format!(
+                "<div class=\"output\"><pre class=\"error\">{}</pre></div>\n",
+                escape_html(&String::from_utf8_lossy(&plain))
+            );
+        }
+        Output::Unknown => return String::new(),
+    };
+
+    format!(
+        "<div class=\"output\"><pre>{}</pre></div>\n",
+        escape_html(&text)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::PageTheme;
+
+    const NOTEBOOK: &str = r##"{
+        "cells": [
+            {"cell_type": "markdown", "metadata": {}, "source": ["# Analysis\n", "Some *notes*"]},
+            {"cell_type": "code", "execution_count": 3, "metadata": {},
+             "source": "print('hi')\n1 + 1",
+             "outputs": [
+                {"output_type": "stream", "name": "stdout", "text": ["hi\n"]},
+                {"output_type": "execute_result", "execution_count": 3, "metadata": {},
+                 "data": {"text/plain": ["2"], "text/html": ["<b>2</b>"]}},
+                {"output_type": "error", "ename": "ValueError", "evalue": "bad",
+                 "traceback": ["\u001b[0;31mValueError\u001b[0m: bad <x>"]},
+                {"output_type": "display_data", "metadata": {}, "data": {"image/png": "iVBOR"}}
+             ]}
+        ],
+        "metadata": {"kernelspec": {"name": "python3", "language": "python"}},
+        "nbformat": 4,
+        "nbformat_minor": 5
+    }"##;
+
+    fn options() -> PageOptions<'static> {
+        PageOptions {
+            title: "analysis.ipynb",
+            raw_url: Some("https://cdn.example.com/analysis.ipynb"),
+            theme: PageTheme::Light,
+        }
+    }
+
+    #[test]
+    fn test_renders_cells_and_outputs() {
+        let page = render_notebook(NOTEBOOK, &options()).unwrap();
+
+        assert!(page.contains("<h1>Analysis</h1>"));
+        assert!(page.contains("<em>notes</em>"));
+        assert!(page.contains("In [3]:"));
+        assert!(page.contains("hl-python"));
+        assert!(page.contains("<pre>hi\n</pre>"));
+        assert!(page.contains("<pre>2</pre>"));
+        assert!(!page.contains("<b>2</b>"));
+        assert!(page.contains("ValueError: bad &lt;x&gt;"));
+        assert!(page.contains("Jupyter notebook (python)"));
+    }
+
+    #[test]
+    fn test_invalid_notebook_is_an_error() {
+        assert!(render_notebook("{\"not\": \"a notebook\"}", &options()).is_err());
+    }
+}
@@ -0,0 +1,374 @@
+use super::{is_dropped_element, is_unsafe_attribute, Sanitized};
+
+/// Elements whose contents are skipped along with them, since browsers read their contents
+/// as raw text rather than markup.
+const DROPPED_RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "iframe"];
+
+/// Policy added by [`add_csp`]: no scripts, plugins, frames or form submissions.
+const CSP_POLICY: &str =
+    "script-src 'none'; object-src 'none'; frame-src 'none'; base-uri 'none'; form-action 'none'";
+
+/// Removes scripts, event handlers, `javascript:` links and embedded documents from HTML.
+///
+/// Text is copied unchanged and tags are rebuilt from their safe attributes, with quotes
+/// and angle brackets in attribute values escaped. That way every `<` in the output starts
+/// a tag that has been checked, however the browser ends up parsing the document.
+/// Comments, processing instructions and `<meta http-equiv>` refreshes are removed too.
+pub fn sanitize_html(html: &str) -> Sanitized {
+    let mut sanitized = Sanitized {
+        content: String::with_capacity(html.len()),
+        elements: 0,
+        attributes: 0,
+    };
+    let mut position = 0;
+
+    while let Some(offset) = html[position..].find('<') {
+        let start = position + offset;
+        sanitized.content.push_str(&html[position..start]);
+        let rest = &html[start..];
+        let next = rest[1..].chars().next();
+
+        position = if let Some(comment) = rest.strip_prefix("<!--") {
+            // Comments can hide conditional markup for old browsers
+            sanitized.elements += 1;
+            comment
+                .find("-->")
+                .map_or(html.len(), |end| start + 4 + end + 3)
+        } else if is_doctype(rest) {
+            sanitized.content.push_str("<!DOCTYPE html>");
+            skip_past(html, start, '>')
+        } else if matches!(next, Some('!' | '?')) {
+            // Bogus comments and processing instructions
+            sanitized.elements += 1;
+            skip_past(html, start, '>')
+        } else if next == Some('/') {
+            match parse_tag(&html[start + 2..]) {
+                Some(tag) => {
+                    // Removed elements are counted at their start tag
+                    if is_valid_name(&tag.name)
+                        && !is_dropped_element(&tag.name)
+                        && tag.name != "meta"
+                    {
+                        sanitized.content.push_str(&format!("</{}>", tag.name));
+                    }
+                    start + 2 + tag.length
+                }
+                None => skip_past(html, start, '>'),
+            }
+        } else if next.is_some_and(|c| c.is_ascii_alphabetic()) {
+            match parse_tag(&html[start + 1..]) {
+                Some(tag) => {
+                    let end = start + 1 + tag.length;
+                    write_tag(&tag, &mut sanitized);
+                    if DROPPED_RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) {
+                        skip_raw_text(html, end, &tag.name)
+                    } else {
+                        end
+                    }
+                }
+                // A tag cut off by the end of the file is dropped by browsers too
+                None => html.len(),
+            }
+        } else {
+            sanitized.content.push('<');
+            start + 1
+        };
+    }
+    sanitized
+        .content
+        .push_str(&html[position.min(html.len())..]);
+    sanitized
+}
+
+/// Adds a Content Security Policy that blocks scripts to the top of an HTML document.
+///
+/// The policy goes right after the DOCTYPE, before any of the document's own markup, so
+/// browsers place it in the `<head>` and apply it to everything that follows.
+pub fn add_csp(html: &str) -> String {
+    let meta = format!(
+        "<meta http-equiv=\"Content-Security-Policy\" content=\"{}\">",
+        CSP_POLICY
+    );
+    let trimmed = html.trim_start();
+    let insert_at = if is_doctype(trimmed) {
+        trimmed
+            .find('>')
+            .map_or(0, |end| html.len() - trimmed.len() + end + 1)
+    } else {
+        0
+    };
+    format!("{}{}{}", &html[..insert_at], meta, &html[insert_at..])
+}
+
+struct Tag {
+    /// Lowercase tag name
+    name: String,
+    /// `(name, raw value)` pairs in document order
+    attributes: Vec<(String, Option<String>)>,
+    self_closing: bool,
+    /// Length of the tag after `<` or `</`, including the closing `>`
+    length: usize,
+}
+
+/// Parses a tag following the HTML tokenizer rules, starting after `<` or `</`.
+fn parse_tag(input: &str) -> Option<Tag> {
+    let is_space = |c: char| matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C');
+    let name_end = input
+        .find(|c: char| is_space(c) || c == '/' || c == '>')
+        .unwrap_or(input.len());
+    let mut tag = Tag {
+        name: input[..name_end].to_ascii_lowercase(),
+        attributes: Vec::new(),
+        self_closing: false,
+        length: 0,
+    };
+
+    let mut position = name_end;
+    loop {
+        let rest = &input[position..];
+        let c = rest.chars().next()?;
+        if is_space(c) {
+            position += 1;
+        } else if c == '>' {
+            tag.length = position + 1;
+            return Some(tag);
+        } else if c == '/' {
+            tag.self_closing = rest[1..].starts_with('>');
+            position += 1;
+        } else {
+            // The first character of a name may be `=`; later ones end it
+            let first = c.len_utf8();
+            let name_end = rest[first..]
+                .find(|c: char| is_space(c) || matches!(c, '/' | '>' | '='))
+                .map_or(rest.len(), |end| end + first);
+            let name = rest[..name_end].to_ascii_lowercase();
+            position += name_end;
+
+            let after_name = &input[position..];
+            let skipped = after_name.len() - after_name.trim_start_matches(is_space).len();
+            let value = if after_name[skipped..].starts_with('=') {
+                let value_start = position + skipped + 1;
+                let rest = &input[value_start..];
+                let spaces = rest.len() - rest.trim_start_matches(is_space).len();
+                let rest = &rest[spaces..];
+                let (value, length) = match rest.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = rest[1..].find(quote)?;
+                        (&rest[1..end + 1], end + 2)
+                    }
+                    _ => {
+                        let end = rest
+                            .find(|c: char| is_space(c) || c == '>')
+                            .unwrap_or(rest.len());
+                        (&rest[..end], end)
+                    }
+                };
+                position = value_start + spaces + length;
+                Some(value.to_string())
+            } else {
+                None
+            };
+            tag.attributes.push((name, value));
+        }
+    }
+}
+
+/// Writes a start tag with only its safe attributes, or counts it as removed.
+fn write_tag(tag: &Tag, sanitized: &mut Sanitized) {
+    let is_refresh =
+        tag.name == "meta" && tag.attributes.iter().any(|(name, _)| name == "http-equiv");
+    if !is_valid_name(&tag.name) || is_dropped_element(&tag.name) || is_refresh {
+        sanitized.elements += 1;
+        return;
+    }
+
+    let mut output = format!("<{}", tag.name);
+    for (name, value) in &tag.attributes {
+        let decoded = value.as_deref().map(decode_entities).unwrap_or_default();
+        if !is_valid_name(name) || is_unsafe_attribute(local_name(name), &decoded) {
+            sanitized.attributes += 1;
+            continue;
+        }
+        match value {
+            Some(value) => output.push_str(&format!(
+                " {}=\"{}\"",
+                name,
+                value
+                    .replace('"', "&quot;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;")
+            )),
+            None => output.push_str(&format!(" {}", name)),
+        }
+    }
+    output.push_str(if tag.self_closing { " />" } else { ">" });
+    sanitized.content.push_str(&output);
+}
+
+/// Returns the position after the closing tag of a raw text element.
+fn skip_raw_text(html: &str, start: usize, name: &str) -> usize {
+    let closing = format!("</{}", name);
+    let mut position = start;
+    while let Some(offset) = html[position..].find("</") {
+        let candidate = position + offset;
+        let rest = &html[candidate..];
+        let after = rest.get(closing.len()..).and_then(|r| r.chars().next());
+        if rest.len() >= closing.len()
+            && rest.is_char_boundary(closing.len())
+            && rest[..closing.len()].eq_ignore_ascii_case(&closing)
+            && matches!(
+                after,
+                None | Some(' ' | '\t' | '\n' | '\r' | '\x0C' | '/' | '>')
+            )
+        {
+            return skip_past(html, candidate, '>');
+        }
+        position = candidate + 2;
+    }
+    html.len()
+}
+
+/// Returns the position after the next `c` at or after `start`, or the end of the input.
+fn skip_past(html: &str, start: usize, c: char) -> usize {
+    html[start..]
+        .find(c)
+        .map_or(html.len(), |end| start + end + c.len_utf8())
+}
+
+fn is_doctype(html: &str) -> bool {
+    html.get(..9)
+        .is_some_and(|start| start.eq_ignore_ascii_case("<!doctype"))
+}
+
+/// Whether a tag or attribute name can be written back without changing how it parses.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Decodes the character references browsers accept in URLs, such as `&#106;` and
+/// `&colon;`, so `javascript:` can't be hidden behind them.
+fn decode_entities(value: &str) -> String {
+    const NAMED: [(&str, char); 8] = [
+        ("colon", ':'),
+        ("tab", '\t'),
+        ("newline", '\n'),
+        ("amp", '&'),
+        ("lpar", '('),
+        ("rpar", ')'),
+        ("sol", '/'),
+        ("quot", '"'),
+    ];
+
+    let mut output = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        let (decoded, length) = if let Some(number) = rest.strip_prefix('#') {
+            let (digits, radix, prefix) = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => (hex, 16, 2),
+                None => (number, 10, 1),
+            };
+            let count = digits
+                .find(|c: char| !c.is_digit(radix))
+                .unwrap_or(digits.len());
+            let code = u32::from_str_radix(&digits[..count], radix).ok();
+            let semicolon = digits[count..].starts_with(';') as usize;
+            match code.and_then(char::from_u32) {
+                Some(c) => (Some(c), prefix + count + semicolon),
+                None => (None, 0),
+            }
+        } else {
+            NAMED
+                .iter()
+                .find(|(name, _)| {
+                    rest.len() > name.len()
+                        && rest.is_char_boundary(name.len())
+                        && rest[..name.len()].eq_ignore_ascii_case(name)
+                        && rest[name.len()..].starts_with(';')
+                })
+                .map_or((None, 0), |(name, c)| (Some(*c), name.len() + 1))
+        };
+        match decoded {
+            Some(c) => output.push(c),
+            None => output.push('&'),
+        }
+        rest = &rest[length..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_html_removes_scripts_and_handlers() {
+        let html = r#"<!doctype html>
+<html><head><title>Report</title>
+<script type="module">alert("</p>")</script >
+<meta http-equiv="refresh" content="0;url=javascript:alert(2)">
+</head>
+<body onload=alert(3)>
+<!-- <img src=x onerror=alert(4)> -->
+<p class=intro title='a "quote" <b>'>1 < 2 &amp; 3</p>
+<a href="&#106;ava&#x73;cript&colon;alert(5)">link</a>
+<a href="https://example.com/?a=1&amp;b=2">ok</a>
+<iframe srcdoc="<script>alert(6)</script>"></iframe>
+<img src=data:image/png;base64,AAAA alt="">
+<IMG SRC=x OnError="alert(7)"/>
+</body></html>"#;
+        let sanitized = sanitize_html(html);
+        let content = &sanitized.content;
+
+        assert!(!content.contains("alert"), "{}", content);
+        assert!(content.starts_with("<!DOCTYPE html>\n<html><head><title>Report</title>"));
+        assert!(content.contains(
+            r#"<p class="intro" title="a &quot;quote&quot; &lt;b&gt;">1 < 2 &amp; 3</p>"#
+        ));
+        assert!(content.contains("<a>link</a>"));
+        assert!(content.contains(r#"<a href="https://example.com/?a=1&amp;b=2">ok</a>"#));
+        assert!(content.contains(r#"<img src="data:image/png;base64,AAAA" alt="">"#));
+        assert!(content.contains("<img src=\"x\" />"));
+        assert!(content.contains("<body>"));
+        // script, meta refresh, comment and iframe
+        assert_eq!(sanitized.elements, 4);
+        // onload, href, onerror
+        assert_eq!(sanitized.attributes, 3);
+
+        let again = sanitize_html(content);
+        assert_eq!(&again.content, content);
+        assert_eq!(again.summary(), None);
+    }
+
+    #[test]
+    fn test_sanitize_html_escapes_attribute_breakouts() {
+        // Browsers read <style> as raw text; the quoted value must not end it early
+        let html = r#"<style><b title="</style><img src=x onerror=alert(1)>"></style>"#;
+        let content = sanitize_html(html).content;
+        assert!(!content.contains("<img"));
+
+        let content = sanitize_html("<p>unterminated <img src=x onerror=alert(1)").content;
+        assert_eq!(content, "<p>unterminated ");
+        assert_eq!(sanitize_html("</ p>a<3").content, "a<3");
+    }
+
+    #[test]
+    fn test_add_csp() {
+        let with_doctype = add_csp("<!DOCTYPE html>\n<p>hi</p>");
+        assert!(with_doctype.starts_with(
+            "<!DOCTYPE html><meta http-equiv=\"Content-Security-Policy\" content=\"script-src 'none';"
+        ));
+        assert!(with_doctype.ends_with("\n<p>hi</p>"));
+        assert!(add_csp("<p>hi</p>").starts_with("<meta http-equiv"));
+    }
+}
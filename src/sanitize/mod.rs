@@ -0,0 +1,144 @@
+mod html;
+mod svg;
+
+pub use html::*;
+pub use svg::*;
+
+/// Elements that run code or embed other documents, removed from SVG and HTML.
+const DROPPED_ELEMENTS: [&str; 11] = [
+    "script",
+    "foreignobject",
+    "iframe",
+    "frame",
+    "frameset",
+    "object",
+    "embed",
+    "applet",
+    "handler",
+    "listener",
+    "base",
+];
+
+/// Attributes whose value is loaded or navigated to as a URL.
+const URL_ATTRIBUTES: [&str; 10] = [
+    "href",
+    "src",
+    "action",
+    "formaction",
+    "data",
+    "background",
+    "poster",
+    "codebase",
+    "lowsrc",
+    "dynsrc",
+];
+
+/// SVG animation attributes that can set another attribute, such as `href`, to a URL.
+const ANIMATION_ATTRIBUTES: [&str; 4] = ["values", "from", "to", "by"];
+
+/// Result of sanitizing an SVG or HTML document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sanitized {
+    pub content: String,
+    /// Number of elements removed, such as `<script>`
+    pub elements: usize,
+    /// Number of attributes removed, such as `onload` or `javascript:` links
+    pub attributes: usize,
+}
+
+impl Sanitized {
+    /// Describes what was removed, or `None` when the document was already safe.
+    pub fn summary(&self) -> Option<String> {
+        let plural = |count: usize, noun: &str| {
+            format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+        };
+        match (self.elements, self.attributes) {
+            (0, 0) => None,
+            (elements, 0) => Some(format!("removed {}", plural(elements, "element"))),
+            (0, attributes) => Some(format!("removed {}", plural(attributes, "attribute"))),
+            (elements, attributes) => Some(format!(
+                "removed {} and {}",
+                plural(elements, "element"),
+                plural(attributes, "attribute")
+            )),
+        }
+    }
+}
+
+/// Whether an upload with this filename is served as an SVG image.
+pub fn is_svg(filename: Option<&str>) -> bool {
+    filename.is_some_and(|name| crate::detect::mime_for_filename(name) == "image/svg+xml")
+}
+
+/// Whether an upload with this filename is served as an HTML page.
+pub fn is_html(filename: Option<&str>) -> bool {
+    filename.is_some_and(|name| crate::detect::mime_for_filename(name) == "text/html")
+}
+
+fn is_dropped_element(name: &str) -> bool {
+    DROPPED_ELEMENTS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Whether an attribute can run script, given its name without a namespace prefix and its
+/// unescaped value.
+fn is_unsafe_attribute(name: &str, value: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    if name.starts_with("on") || name == "srcdoc" {
+        return true;
+    }
+    let value: String = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if name == "style" {
+        return value.contains("javascript:") || value.contains("expression(");
+    }
+    if URL_ATTRIBUTES.contains(&name.as_str()) || ANIMATION_ATTRIBUTES.contains(&name.as_str()) {
+        return is_script_url(&value);
+    }
+    false
+}
+
+/// Whether a normalized URL (or `;`-separated list of them) runs script when opened.
+fn is_script_url(value: &str) -> bool {
+    value.contains("javascript:")
+        || value.contains("vbscript:")
+        || value.split(';').any(|part| {
+            // Inline images are fine, other data URLs can hold whole documents
+            part.starts_with("data:") && !part.starts_with("data:image/")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_unsafe_attribute() {
+        assert!(is_unsafe_attribute("onload", "alert(1)"));
+        assert!(is_unsafe_attribute("OnClick", ""));
+        assert!(is_unsafe_attribute("href", " JaVa\tScRiPt:alert(1)"));
+        assert!(is_unsafe_attribute("src", "data:text/html,<script>"));
+        assert!(is_unsafe_attribute("values", "#a;javascript:alert(1)"));
+        assert!(is_unsafe_attribute("style", "width: expression(alert(1))"));
+        assert!(!is_unsafe_attribute("href", "https://example.com/"));
+        assert!(!is_unsafe_attribute("src", "data:image/png;base64,AAAA"));
+        assert!(!is_unsafe_attribute("fill", "red"));
+    }
+
+    #[test]
+    fn test_summary() {
+        let sanitized = |elements, attributes| Sanitized {
+            content: String::new(),
+            elements,
+            attributes,
+        };
+        assert_eq!(sanitized(0, 0).summary(), None);
+        assert_eq!(
+            sanitized(1, 2).summary().unwrap(),
+            "removed 1 element and 2 attributes"
+        );
+        assert_eq!(sanitized(0, 1).summary().unwrap(), "removed 1 attribute");
+    }
+}
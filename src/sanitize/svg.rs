@@ -0,0 +1,142 @@
+use super::{is_dropped_element, is_unsafe_attribute, Sanitized};
+use anyhow::{anyhow, Context, Result};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, XmlVersion};
+
+/// Removes scripts, event handlers, `javascript:` links and embedded documents from an SVG.
+///
+/// Everything else is copied byte for byte. Processing instructions and the DOCTYPE are
+/// dropped too, since they can pull in stylesheets or define entities that expand to
+/// markup. SVGs that aren't well-formed XML are rejected; browsers won't display them.
+pub fn sanitize_svg(data: &[u8]) -> Result<Sanitized> {
+    let text = std::str::from_utf8(data).context("SVG is not valid UTF-8")?;
+    let mut reader = Reader::from_str(text);
+    let mut sanitized = Sanitized {
+        content: String::with_capacity(text.len()),
+        elements: 0,
+        attributes: 0,
+    };
+    // Nesting depth inside a removed element
+    let mut dropped_depth = 0;
+
+    loop {
+        let start = reader.buffer_position() as usize;
+        let event = reader
+            .read_event()
+            .map_err(|e| anyhow!("Invalid SVG at byte {}: {}", reader.error_position(), e))?;
+        let raw = &text[start..reader.buffer_position() as usize];
+
+        match event {
+            Event::Eof => break,
+            Event::Start(_) if dropped_depth > 0 => dropped_depth += 1,
+            Event::End(_) if dropped_depth > 0 => dropped_depth -= 1,
+            _ if dropped_depth > 0 => {}
+            Event::Start(ref tag) | Event::Empty(ref tag)
+                if is_dropped_element(tag.local_name().as_ref()) =>
+            {
+                sanitized.elements += 1;
+                if matches!(event, Event::Start(_)) {
+                    dropped_depth = 1;
+                }
+            }
+            Event::Start(ref tag) | Event::Empty(ref tag) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                match filter_attributes(tag, is_empty)? {
+                    Some((rebuilt, removed)) => {
+                        sanitized.attributes += removed;
+                        sanitized.content.push_str(&rebuilt);
+                    }
+                    None => sanitized.content.push_str(raw),
+                }
+            }
+            Event::PI(_) | Event::DocType(_) => sanitized.elements += 1,
+            _ => sanitized.content.push_str(raw),
+        }
+    }
+
+    Ok(sanitized)
+}
+
+/// Rebuilds a tag without its unsafe attributes, returning it with the number of attributes
+/// removed, or `None` when every attribute is safe.
+fn filter_attributes(tag: &BytesStart, is_empty: bool) -> Result<Option<(String, usize)>> {
+    let mut kept = String::new();
+    let mut removed = 0;
+    for attribute in tag.attributes() {
+        let attribute = attribute.map_err(|e| anyhow!("Invalid SVG attribute: {}", e))?;
+        let unsafe_value = match attribute.normalized_value(XmlVersion::Implicit1_0) {
+            Ok(value) => is_unsafe_attribute(attribute.key.local_name().as_ref(), &value),
+            // Values that can't be read are not worth the risk
+            Err(_) => true,
+        };
+        if unsafe_value {
+            removed += 1;
+        } else {
+            kept.push_str(&format!(
+                " {}=\"{}\"",
+                attribute.key.as_ref(),
+                attribute.value.replace('"', "&quot;")
+            ));
+        }
+    }
+
+    if removed == 0 {
+        return Ok(None);
+    }
+    let close = if is_empty { "/>" } else { ">" };
+    Ok(Some((
+        format!("<{}{}{}", tag.name().as_ref(), kept, close),
+        removed,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_svg_removes_scripts_and_handlers() {
+        let svg = r#"<?xml version="1.0"?>
+<?xml-stylesheet href="evil.xsl" type="text/xsl"?>
+<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" onload="alert(1)">
+  <script>alert(2)</script>
+  <svg:script xmlns:svg="http://www.w3.org/2000/svg"><![CDATA[alert(3)]]></svg:script>
+  <a xlink:href="javascript:alert(4)"><rect width='10' title='say "hi"' fill="red"/></a>
+  <foreignObject><div xmlns="http://www.w3.org/1999/xhtml"><iframe/></div></foreignObject>
+  <set attributeName="href" to="&#106;avascript:alert(5)"/>
+  <circle r="5" fill="blue"/>
+</svg>"#;
+        let sanitized = sanitize_svg(svg.as_bytes()).unwrap();
+        let content = &sanitized.content;
+
+        assert!(!content.contains("alert"));
+        assert!(!content.contains("evil.xsl"));
+        assert!(!content.contains("foreignObject"));
+        assert!(content.starts_with(r#"<?xml version="1.0"?>"#));
+        assert!(content.contains(r#"<rect width='10' title='say "hi"' fill="red"/>"#));
+        assert!(content.contains(r#"<circle r="5" fill="blue"/>"#));
+        assert!(content.contains(r#"<a>"#));
+        assert!(content.contains(r#"<set attributeName="href"/>"#));
+        assert_eq!((sanitized.elements, sanitized.attributes), (4, 3));
+
+        // The result is well-formed and already safe
+        let again = sanitize_svg(content.as_bytes()).unwrap();
+        assert_eq!(&again.content, content);
+        assert_eq!(again.summary(), None);
+    }
+
+    #[test]
+    fn test_sanitize_svg_drops_doctype_entities() {
+        let svg = r#"<!DOCTYPE svg [<!ENTITY x "<script>alert(1)</script>">]><svg>&x;</svg>"#;
+        let sanitized = sanitize_svg(svg.as_bytes()).unwrap();
+        assert_eq!(sanitized.content, "<svg>&x;</svg>");
+        assert_eq!(sanitized.elements, 1);
+    }
+
+    #[test]
+    fn test_sanitize_svg_rejects_malformed_documents() {
+        assert!(sanitize_svg(b"<svg><g></svg>").is_err());
+        assert!(sanitize_svg(b"<svg a='1' a='2'/>").is_err());
+        assert!(sanitize_svg(&[0xFF, 0xFE]).is_err());
+    }
+}
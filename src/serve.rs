@@ -0,0 +1,815 @@
+use crate::config::{Config, InputSource, RoutingContext};
+use crate::models::{UploadOptions, UploadRequest, UploadResponse};
+use crate::orchestrator::UploadOrchestrator;
+use crate::prepare::{self, PrepareError, PrepareOptions};
+use anyhow::{Context, Result};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::body::{Body, Bytes};
+use hyper::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Where `pst serve` listens and who it accepts uploads from.
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// Address to listen on, e.g. `127.0.0.1:8080`
+    pub listen: String,
+    /// Listen on this unix socket instead of a TCP address
+    pub socket: Option<PathBuf>,
+    /// Require `Authorization: Bearer <token>` on every request
+    pub token: Option<String>,
+    /// Largest request body accepted, in bytes
+    pub max_size: u64,
+}
+
+/// Settings a client can pass in the query string, e.g. `POST /?group=images&format=json`.
+#[derive(Debug, Default)]
+struct Query {
+    group: Option<String>,
+    provider: Option<String>,
+    expires: Option<String>,
+    filename: Option<String>,
+    json: bool,
+}
+
+impl Query {
+    fn parse(query: Option<&str>) -> Self {
+        let mut parsed = Query::default();
+        let Some(query) = query else {
+            return parsed;
+        };
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(&value.replace('+', " ")).filter(|v| !v.is_empty());
+            match key {
+                "group" => parsed.group = value,
+                "provider" => parsed.provider = value,
+                "expires" => parsed.expires = value,
+                "filename" => parsed.filename = value,
+                "format" => parsed.json = value.as_deref() == Some("json"),
+                _ => {}
+            }
+        }
+        parsed
+    }
+}
+
+/// Content received in a request, before any processing.
+#[derive(Debug, PartialEq)]
+struct Upload {
+    content: Vec<u8>,
+    /// Name the client gave the file, e.g. from `curl -F file=@shot.png`
+    name: Option<String>,
+    expires: Option<String>,
+}
+
+/// Body of a JSON upload, e.g. from a ShareX text uploader with `{"content": "{input}"}`.
+#[derive(Debug, Deserialize)]
+struct JsonUpload {
+    content: String,
+    #[serde(default)]
+    filename: Option<String>,
+    #[serde(default)]
+    expires: Option<String>,
+}
+
+/// A request that is answered with an error instead of an upload.
+#[derive(Debug)]
+struct Rejection {
+    status: StatusCode,
+    message: String,
+}
+
+impl Rejection {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+
+    fn internal(error: impl Into<anyhow::Error>) -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("{:#}", error.into()),
+        )
+    }
+}
+
+struct Server {
+    config: Arc<Config>,
+    orchestrator: UploadOrchestrator,
+    token_digest: Option<Vec<u8>>,
+    max_size: u64,
+}
+
+/// Accepts uploads over HTTP and sends them through the configured providers until
+/// interrupted.
+pub async fn run(config: Arc<Config>, options: ServeOptions) -> Result<()> {
+    let server = Arc::new(Server::new(
+        config,
+        options.token.as_deref(),
+        options.max_size,
+    ));
+    match &options.socket {
+        Some(path) => serve_unix(server, path).await,
+        None => serve_tcp(server, &options.listen, options.token.is_some()).await,
+    }
+}
+
+async fn serve_tcp(server: Arc<Server>, listen: &str, has_token: bool) -> Result<()> {
+    let listener = tokio::net::TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", listen))?;
+    let address = listener.local_addr()?;
+    if !address.ip().is_loopback() && !has_token {
+        eprintln!(
+            "Warning: Listening on {} without --token, anyone who can reach it can upload",
+            address.ip()
+        );
+    }
+    eprintln!("Listening on http://{}", address);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => spawn_connection(server.clone(), stream),
+                Err(e) => eprintln!("Warning: Failed to accept connection: {}", e),
+            },
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn serve_unix(server: Arc<Server>, path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            anyhow::bail!("{} is in use by another server", path.display());
+        }
+        // Left behind by a server that didn't shut down cleanly
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    // Only the current user may upload through the socket
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    eprintln!("Listening on {}", path.display());
+
+    let result = loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => spawn_connection(server.clone(), stream),
+                Err(e) => eprintln!("Warning: Failed to accept connection: {}", e),
+            },
+            _ = tokio::signal::ctrl_c() => break Ok(()),
+        }
+    };
+    let _ = std::fs::remove_file(path);
+    result
+}
+
+#[cfg(not(unix))]
+async fn serve_unix(_server: Arc<Server>, _path: &Path) -> Result<()> {
+    anyhow::bail!("Unix sockets are not supported on this platform, use --listen")
+}
+
+fn spawn_connection<S>(server: Arc<Server>, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let service = service_fn(move |request| {
+            let server = server.clone();
+            async move { Ok::<_, Infallible>(server.handle(request).await) }
+        });
+        if let Err(e) = http1::Builder::new()
+            .serve_connection(TokioIo::new(stream), service)
+            .await
+        {
+            log::debug!("Connection closed with an error: {}", e);
+        }
+    });
+}
+
+impl Server {
+    fn new(config: Arc<Config>, token: Option<&str>, max_size: u64) -> Self {
+        Self {
+            orchestrator: UploadOrchestrator::new(config.clone()),
+            config,
+            token_digest: token.map(digest),
+            max_size,
+        }
+    }
+
+    async fn handle<B>(&self, request: Request<B>) -> Response<Full<Bytes>>
+    where
+        B: Body,
+        B::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let query = Query::parse(request.uri().query());
+        let json = query.json || accepts_json(request.headers());
+
+        if !self.is_authorized(request.headers()) {
+            let mut response = reject(
+                Rejection::new(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token"),
+                json,
+            );
+            response
+                .headers_mut()
+                .insert("www-authenticate", HeaderValue::from_static("Bearer"));
+            return response;
+        }
+
+        let result = match (request.method(), request.uri().path()) {
+            (&Method::GET, "/") => return text(StatusCode::OK, usage(request.headers())),
+            (&Method::POST, "/") | (&Method::PUT, _) => {
+                match read_upload(request, self.max_size).await {
+                    Ok(upload) => self.upload(upload, &query).await,
+                    Err(rejection) => Err(rejection),
+                }
+            }
+            (_, "/") => Err(Rejection::new(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "Use POST or PUT to upload",
+            )),
+            _ => Err(Rejection::new(StatusCode::NOT_FOUND, "Not found")),
+        };
+
+        match result {
+            Ok(response) => uploaded(&response, json),
+            Err(rejection) => {
+                eprintln!("Rejected upload: {}", rejection.message);
+                reject(rejection, json)
+            }
+        }
+    }
+
+    fn is_authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(expected) = &self.token_digest else {
+            return true;
+        };
+        // Compare digests so the time taken doesn't reveal how much of the token matched
+        headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| digest(token.trim()) == *expected)
+    }
+
+    async fn upload(&self, upload: Upload, query: &Query) -> Result<UploadResponse, Rejection> {
+        if let Some(name) = &query.filename {
+            checked_filename(name)?;
+        }
+        if let Some(name) = &query.provider {
+            if !self
+                .config
+                .get_provider_config(name)
+                .is_some_and(|provider| provider.is_enabled())
+            {
+                return Err(Rejection::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Unknown or disabled provider '{}'", name),
+                ));
+            }
+        }
+
+        let (request, group) = self.prepare(upload, query)?;
+        let response = match &query.provider {
+            Some(name) => {
+                self.orchestrator
+                    .upload_to_specific_provider(&request, name, None)
+                    .await
+            }
+            None => self.orchestrator.upload(&request, &group, None).await,
+        };
+
+        match &response.url {
+            Some(url) if response.success => {
+                eprintln!("{} bytes -> {}", request.file_size(), url)
+            }
+            _ => eprintln!(
+                "{} bytes failed: {}",
+                request.file_size(),
+                response.error.as_deref().unwrap_or("unknown error")
+            ),
+        }
+        Ok(response)
+    }
+
+    /// Applies the same routing and clean-up as an upload from the command line.
+    fn prepare(&self, upload: Upload, query: &Query) -> Result<(UploadRequest, String), Rejection> {
+        let config = &self.config;
+
+        // Named files get a random name with the same extension, like `pst --file`
+        let filename = query.filename.clone().or_else(|| {
+            upload
+                .name
+                .as_deref()
+                .and_then(|name| Path::new(name).extension())
+                .and_then(|ext| ext.to_str())
+                .map(|ext| format!("*.{}", ext))
+        });
+        let (detected_group, detected_filename, upload_type, mime) =
+            crate::determine_upload_type(&upload.content, filename.as_deref(), false);
+        let content = crate::decode_paste(upload.content, &upload_type);
+
+        let route_name = query
+            .filename
+            .clone()
+            .or(upload.name)
+            .or_else(|| detected_filename.clone());
+        // A group from the client replaces routing, including the rule's expiry and EXIF
+        // setting
+        let route = match query.group {
+            Some(_) => None,
+            None => config.find_route(&RoutingContext {
+                filename: route_name.as_deref(),
+                mime: Some(&mime),
+                size: content.len() as u64,
+                source: InputSource::Serve,
+            }),
+        };
+        let group = query
+            .group
+            .clone()
+            .or_else(|| route.map(|r| r.group.clone()))
+            .unwrap_or(detected_group);
+        let strip_exif = route
+            .and_then(|r| r.strip_exif)
+            .unwrap_or(config.general.strip_exif);
+        let options = PrepareOptions {
+            transform: config.image_transform(&group),
+            group: group.clone(),
+            provider: query.provider.clone(),
+            strip_exif,
+            sanitize_svg: true,
+            ..Default::default()
+        };
+        let prepared = prepare::prepare(config, content, detected_filename, &upload_type, &options)
+            .map_err(|e| match e {
+                PrepareError::Other(e) => Rejection::internal(e),
+                e => Rejection::new(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()),
+            })?;
+
+        let request = UploadRequest::new(
+            prepared.content,
+            prepared.filename,
+            upload_type,
+            Some(UploadOptions {
+                expiration: query
+                    .expires
+                    .clone()
+                    .or(upload.expires)
+                    .or_else(|| route.and_then(|r| r.expires.clone())),
+                trusted_only: prepared.trusted_only,
+                ..Default::default()
+            }),
+            false,
+        );
+        Ok((request, group))
+    }
+}
+
+/// Reads the content of a request: a multipart `file` field as sent by 0x0.st clients and
+/// ShareX, a JSON body, or the raw body of any other request.
+async fn read_upload<B>(request: Request<B>, max_size: u64) -> Result<Upload, Rejection>
+where
+    B: Body,
+    B::Error: std::error::Error + Send + Sync + 'static,
+{
+    let too_large = || {
+        Rejection::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("Upload is larger than {} bytes", max_size),
+        )
+    };
+    let declared = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok());
+    if declared.is_some_and(|length| length > max_size) {
+        return Err(too_large());
+    }
+
+    let content_type = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let path_name = match request.method() {
+        &Method::PUT => path_filename(request.uri().path())?,
+        _ => None,
+    };
+
+    let body = Limited::new(request.into_body(), max_size as usize)
+        .collect()
+        .await
+        .map_err(|e| {
+            if e.is::<LengthLimitError>() {
+                too_large()
+            } else {
+                Rejection::new(
+                    StatusCode::BAD_REQUEST,
+                    format!("Failed to read body: {}", e),
+                )
+            }
+        })?
+        .to_bytes();
+
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let upload = match mime.as_str() {
+        "multipart/form-data" => read_multipart(body, &content_type).await?,
+        "application/json" => {
+            let json: JsonUpload = serde_json::from_slice(&body).map_err(|e| {
+                Rejection::new(StatusCode::BAD_REQUEST, format!("Invalid JSON body: {}", e))
+            })?;
+            Upload {
+                content: json.content.into_bytes(),
+                name: match json.filename {
+                    Some(name) => checked_filename(&name)?,
+                    None => path_name,
+                },
+                expires: json.expires,
+            }
+        }
+        _ => Upload {
+            content: body.to_vec(),
+            name: path_name,
+            expires: None,
+        },
+    };
+
+    if upload.content.is_empty() {
+        return Err(Rejection::new(StatusCode::BAD_REQUEST, "Upload is empty"));
+    }
+    Ok(upload)
+}
+
+async fn read_multipart(body: Bytes, content_type: &str) -> Result<Upload, Rejection> {
+    let bad_request =
+        |e: multer::Error| Rejection::new(StatusCode::BAD_REQUEST, format!("Invalid form: {}", e));
+    let boundary = multer::parse_boundary(content_type).map_err(bad_request)?;
+    let stream = futures_util::stream::once(async move { Ok::<_, Infallible>(body) });
+    let mut multipart = multer::Multipart::new(stream, boundary);
+
+    let mut file = None;
+    let mut expires = None;
+    while let Some(field) = multipart.next_field().await.map_err(bad_request)? {
+        match field.name() {
+            Some("expires") => expires = Some(field.text().await.map_err(bad_request)?),
+            // Fall back to the first file when a client names its field differently
+            name if name == Some("file") || (file.is_none() && field.file_name().is_some()) => {
+                let name = field.file_name().map(str::to_string);
+                file = Some((name, field.bytes().await.map_err(bad_request)?));
+            }
+            _ => {}
+        }
+    }
+
+    let (name, content) = file.ok_or_else(|| {
+        Rejection::new(
+            StatusCode::BAD_REQUEST,
+            "No file in the form, send it as `file=@<path>`",
+        )
+    })?;
+    Ok(Upload {
+        content: content.to_vec(),
+        name: name.as_deref().map(checked_filename).transpose()?.flatten(),
+        expires: expires.filter(|e| !e.is_empty()),
+    })
+}
+
+/// File name from the path of a `PUT /<name>` request.
+fn path_filename(path: &str) -> Result<Option<String>, Rejection> {
+    let name = path.strip_prefix('/').unwrap_or(path);
+    let decoded = percent_decode(name)
+        .ok_or_else(|| Rejection::new(StatusCode::BAD_REQUEST, "Invalid file name in path"))?;
+    checked_filename(&decoded)
+}
+
+/// Checks a file name sent by a client. Names with directories in them are refused, since
+/// providers like SFTP join the name to their upload directory.
+fn checked_filename(name: &str) -> Result<Option<String>, Rejection> {
+    let name = name.trim();
+    let bytes = name.as_bytes();
+    let has_drive = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    if name.contains(['/', '\\'])
+        || name.contains("..")
+        || name == "."
+        || has_drive
+        || name.chars().any(char::is_control)
+    {
+        return Err(Rejection::new(
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid file name {:?}, send a name without directories",
+                name
+            ),
+        ));
+    }
+    Ok(Some(name.to_string()).filter(|name| !name.is_empty()))
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn digest(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
+}
+
+fn accepts_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"))
+}
+
+fn text(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body)));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    response
+}
+
+fn json(status: StatusCode, body: &serde_json::Value) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+fn reject(rejection: Rejection, as_json: bool) -> Response<Full<Bytes>> {
+    if as_json {
+        json(
+            rejection.status,
+            &serde_json::json!({ "success": false, "error": rejection.message }),
+        )
+    } else {
+        text(rejection.status, format!("{}\n", rejection.message))
+    }
+}
+
+/// Answers with the URL, or with the full response as JSON for clients like ShareX that
+/// read `{json:url}`.
+fn uploaded(response: &UploadResponse, as_json: bool) -> Response<Full<Bytes>> {
+    let status = if response.success {
+        StatusCode::OK
+    } else {
+        StatusCode::BAD_GATEWAY
+    };
+    match (&response.url, as_json) {
+        (_, true) => json(status, &response.to_json()),
+        (Some(url), false) if response.success => text(status, format!("{}\n", url)),
+        _ => text(
+            status,
+            format!("{}\n", response.error.as_deref().unwrap_or("Upload failed")),
+        ),
+    }
+}
+
+fn usage(headers: &HeaderMap) -> String {
+    let host = headers
+        .get("host")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("localhost");
+    let auth = match headers.contains_key(AUTHORIZATION) {
+        true => " -H \"Authorization: Bearer $PST_SERVE_TOKEN\"",
+        false => "",
+    };
+    format!(
+        "pst serve uploads to the providers configured on this machine.
+
+    curl{auth} -F file=@photo.png http://{host}/
+    curl{auth} -T notes.txt http://{host}/
+    command | curl{auth} --data-binary @- http://{host}/
+
+Query parameters:
+    group=NAME      upload to this provider group
+    provider=NAME   upload to this provider only
+    expires=TIME    expiration, for providers that support it
+    filename=NAME   file name to upload as
+    format=json     answer with JSON instead of the URL
+"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, uri: &str, content_type: &str, body: &str) -> Request<Full<Bytes>> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(CONTENT_TYPE, content_type)
+            .body(Full::new(Bytes::from(body.to_string())))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_query_and_filenames() {
+        let query = Query::parse(Some("group=images&filename=my%20shot.png&format=json&x"));
+        assert_eq!(query.group.as_deref(), Some("images"));
+        assert_eq!(query.filename.as_deref(), Some("my shot.png"));
+        assert!(query.json);
+        assert!(query.provider.is_none());
+
+        assert_eq!(
+            path_filename("/notes%20v2.txt").unwrap().as_deref(),
+            Some("notes v2.txt")
+        );
+        assert_eq!(path_filename("/").unwrap(), None);
+        assert!(path_filename("/%zz").is_err());
+
+        for name in [
+            "../../etc/passwd",
+            "a/b.txt",
+            "C:\\shot.png",
+            "C:shot.png",
+            "..",
+            ".",
+        ] {
+            assert!(checked_filename(name).is_err(), "{}", name);
+        }
+        assert_eq!(
+            checked_filename(" my shot.png ").unwrap().as_deref(),
+            Some("my shot.png")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_traversal_names_are_refused() {
+        let form = "--xyz\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"../../.ssh/authorized_keys\"\r\n\r\n\
+            ssh-ed25519 AAAA\r\n\
+            --xyz--\r\n";
+        let requests = [
+            request(Method::PUT, "/..%2F..%2Fetc%2Fpasswd", "text/plain", "x"),
+            request(Method::PUT, "/uploads/../x.txt", "text/plain", "x"),
+            request(
+                Method::POST,
+                "/",
+                "application/json",
+                r#"{"content": "x", "filename": "/etc/passwd"}"#,
+            ),
+            request(Method::POST, "/", "multipart/form-data; boundary=xyz", form),
+        ];
+        for request in requests {
+            let error = read_upload(request, 1024).await.unwrap_err();
+            assert_eq!(error.status, StatusCode::BAD_REQUEST, "{}", error.message);
+        }
+
+        let server = Server::new(Arc::new(Config::default_with_ftp()), None, 1024);
+        let response = server
+            .handle(request(
+                Method::POST,
+                "/?filename=..%2F..%2Fetc%2Fcron.d%2Fjob",
+                "text/plain",
+                "x",
+            ))
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_group_overrides_the_whole_route() {
+        let mut config = Config::default_with_ftp();
+        config.routing = vec![crate::config::RoutingRule {
+            extensions: vec!["log".to_string()],
+            group: "files".to_string(),
+            expires: Some("7d".to_string()),
+            ..Default::default()
+        }];
+        let server = Server::new(Arc::new(config), None, 1024);
+        let upload = || Upload {
+            content: b"build ok\n".to_vec(),
+            name: Some("build.log".to_string()),
+            expires: None,
+        };
+
+        let (request, group) = server.prepare(upload(), &Query::default()).unwrap();
+        assert_eq!(group, "files");
+        assert_eq!(request.options.expiration.as_deref(), Some("7d"));
+
+        let query = Query::parse(Some("group=pastes"));
+        let (request, group) = server.prepare(upload(), &query).unwrap();
+        assert_eq!(group, "pastes");
+        assert_eq!(request.options.expiration, None);
+    }
+
+    #[tokio::test]
+    async fn test_reads_form_json_and_raw_bodies() {
+        let form = "--xyz\r\n\
+            Content-Disposition: form-data; name=\"expires\"\r\n\r\n\
+            24\r\n\
+            --xyz\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"shot.png\"\r\n\
+            Content-Type: image/png\r\n\r\n\
+            PNGDATA\r\n\
+            --xyz--\r\n";
+        let upload = read_upload(
+            request(Method::POST, "/", "multipart/form-data; boundary=xyz", form),
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            upload,
+            Upload {
+                content: b"PNGDATA".to_vec(),
+                name: Some("shot.png".to_string()),
+                expires: Some("24".to_string()),
+            }
+        );
+
+        let upload = read_upload(
+            request(
+                Method::POST,
+                "/",
+                "application/json",
+                r#"{"content": "hello", "filename": "a.md"}"#,
+            ),
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(upload.content, b"hello");
+        assert_eq!(upload.name.as_deref(), Some("a.md"));
+
+        let upload = read_upload(
+            request(Method::PUT, "/build.log", "application/octet-stream", "ok"),
+            1024,
+        )
+        .await
+        .unwrap();
+        assert_eq!(upload.content, b"ok");
+        assert_eq!(upload.name.as_deref(), Some("build.log"));
+
+        let error = read_upload(request(Method::POST, "/", "text/plain", ""), 1024)
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_size_limit_and_token() {
+        let error = read_upload(request(Method::PUT, "/a.txt", "text/plain", "too long"), 4)
+            .await
+            .unwrap_err();
+        assert_eq!(error.status, StatusCode::PAYLOAD_TOO_LARGE);
+
+        let server = Server::new(Arc::new(Config::default_with_ftp()), Some("s3cret"), 1024);
+        let get = |authorization: Option<&str>| {
+            let mut builder = Request::builder().uri("/");
+            if let Some(value) = authorization {
+                builder = builder.header(AUTHORIZATION, value);
+            }
+            builder.body(Full::new(Bytes::new())).unwrap()
+        };
+
+        let response = server.handle(get(None)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = server.handle(get(Some("Bearer wrong"))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = server.handle(get(Some("Bearer s3cret"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,204 @@
+mod preview;
+
+pub use preview::*;
+
+use crate::render::escape_html;
+use anyhow::{bail, Context, Result};
+use reqwest::Url;
+
+/// A redirect page for a shortened link.
+#[derive(Debug, Clone, Default)]
+pub struct RedirectPage {
+    pub target: String,
+    pub preview: LinkPreview,
+    /// Show a "you are leaving" page instead of redirecting straight away
+    pub interstitial: bool,
+    /// Seconds to wait before redirecting; interstitial pages only redirect when set
+    pub delay: Option<u32>,
+}
+
+impl RedirectPage {
+    /// Creates a page for the target, which must be an absolute http(s) URL.
+    pub fn new(target: &str) -> Result<Self> {
+        let url =
+            Url::parse(target).with_context(|| format!("Invalid redirect URL: {}", target))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            bail!("Redirect URL must start with http:// or https://");
+        }
+
+        Ok(Self {
+            target: url.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Whether the page does nothing beyond an immediate redirect, so a provider's native
+    /// link shortener can be used instead of hosting it.
+    pub fn is_plain(&self) -> bool {
+        self.preview.is_empty() && !self.interstitial && self.delay.unwrap_or(0) == 0
+    }
+
+    pub fn to_html(&self) -> Vec<u8> {
+        let target = escape_html(&self.target);
+        let host = Url::parse(&self.target)
+            .ok()
+            .and_then(|url| url.host_str().map(|h| h.to_string()))
+            .unwrap_or_default();
+        let title = self.preview.title.as_deref().unwrap_or("Redirect");
+
+        let mut head = Vec::new();
+        let redirects = !self.interstitial || self.delay.is_some();
+        if redirects {
+            let delay = self.delay.unwrap_or(0);
+            head.push(format!(
+                "<meta http-equiv=\"refresh\" content=\"{}; url={}\">",
+                delay, target
+            ));
+            head.push(format!(
+                "<script>setTimeout(function () {{ window.location.replace({}); }}, {});</script>",
+                script_string(&self.target),
+                delay * 1000
+            ));
+        }
+        head.extend(self.preview.meta_tags(&self.target));
+
+        let body = if self.interstitial {
+            let countdown = match self.delay {
+                Some(delay) => format!(" You will be redirected in {} seconds.", delay),
+                None => String::new(),
+            };
+            format!(
+                "<h1>You are leaving this site</h1>\n    \
+                 <p>This link goes to <strong>{}</strong>:</p>\n    \
+                 <p><a href=\"{}\" rel=\"noopener noreferrer\">{}</a></p>\n    \
+                 <p>Only continue if you trust this site.{}</p>",
+                escape_html(&host),
+                target,
+                target,
+                countdown
+            )
+        } else {
+            format!(
+                "<p>Redirecting you to <a href=\"{}\">{}</a></p>",
+                target, target
+            )
+        };
+
+        format!(
+            "<!DOCTYPE html>\n\
+             <html>\n\
+             <head>\n    \
+             <meta charset=\"utf-8\">\n    \
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n    \
+             <meta name=\"referrer\" content=\"no-referrer\">\n    \
+             <title>{}</title>\n    \
+             {}\n\
+             </head>\n\
+             <body>\n    \
+             {}\n\
+             </body>\n\
+             </html>",
+            escape_html(title),
+            head.join("\n    "),
+            body
+        )
+        .into_bytes()
+    }
+}
+
+/// Checks a custom slug, which becomes the redirect page's filename.
+pub fn validate_slug(slug: &str) -> Result<()> {
+    let valid = !slug.is_empty()
+        && slug.len() <= 64
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!(
+            "Invalid slug '{}': use up to 64 letters, digits, '-' or '_'",
+            slug
+        );
+    }
+    Ok(())
+}
+
+/// Encodes a value as a JavaScript string literal that is safe inside a `<script>` element.
+fn script_string(value: &str) -> String {
+    serde_json::Value::from(value)
+        .to_string()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn html(page: &RedirectPage) -> String {
+        String::from_utf8(page.to_html()).unwrap()
+    }
+
+    #[test]
+    fn test_rejects_non_http_urls() {
+        assert!(RedirectPage::new("javascript:alert(1)").is_err());
+        assert!(RedirectPage::new("not a url").is_err());
+        assert!(RedirectPage::new("https://example.com/a?b=c").is_ok());
+    }
+
+    #[test]
+    fn test_crafted_url_cannot_inject_markup() {
+        let page =
+            RedirectPage::new("https://example.com/\"><script>alert(1)</script>?q=\"+alert(2)+\"")
+                .unwrap();
+        let html = html(&page);
+
+        assert!(!html.contains("<script>alert"));
+        assert!(!html.contains("\"+alert(2)+\""));
+        assert_eq!(html.matches("<script>").count(), 1);
+    }
+
+    #[test]
+    fn test_script_string_escapes_closing_tags() {
+        assert_eq!(
+            script_string("a\"</script>&"),
+            "\"a\\\"\\u003c/script\\u003e\\u0026\""
+        );
+    }
+
+    #[test]
+    fn test_plain_redirect_is_immediate() {
+        let page = RedirectPage::new("https://example.com/").unwrap();
+        assert!(page.is_plain());
+
+        let html = html(&page);
+        assert!(html.contains("content=\"0; url=https://example.com/\""));
+        assert!(html.contains("Redirecting you to"));
+    }
+
+    #[test]
+    fn test_interstitial_waits_for_the_user() {
+        let mut page = RedirectPage::new("https://example.com/path").unwrap();
+        page.interstitial = true;
+        assert!(!page.is_plain());
+
+        let html = html(&page);
+        assert!(html.contains("You are leaving this site"));
+        assert!(html.contains("<strong>example.com</strong>"));
+        assert!(!html.contains("http-equiv=\"refresh\""));
+
+        page.delay = Some(5);
+        let html = self::html(&page);
+        assert!(html.contains("content=\"5; url=https://example.com/path\""));
+        assert!(html.contains("5000"));
+        assert!(html.contains("redirected in 5 seconds"));
+    }
+
+    #[test]
+    fn test_slug_validation() {
+        assert!(validate_slug("release-notes_2").is_ok());
+        assert!(validate_slug("").is_err());
+        assert!(validate_slug("../etc").is_err());
+        assert!(validate_slug(&"a".repeat(65)).is_err());
+    }
+}
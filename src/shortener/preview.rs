@@ -0,0 +1,210 @@
+use crate::render::escape_html;
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Largest amount of the target page read when fetching preview metadata.
+const MAX_PREVIEW_BYTES: usize = 512 * 1024;
+
+/// OpenGraph and Twitter card metadata shown when a short link is shared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkPreview {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+}
+
+impl LinkPreview {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none() && self.image.is_none()
+    }
+
+    /// Fills fields that are still unset from another preview.
+    pub fn or(self, other: LinkPreview) -> LinkPreview {
+        LinkPreview {
+            title: self.title.or(other.title),
+            description: self.description.or(other.description),
+            image: self.image.or(other.image),
+        }
+    }
+
+    /// `<meta>` tags for the preview; empty when there is nothing to show.
+    pub fn meta_tags(&self, url: &str) -> Vec<String> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let card = if self.image.is_some() {
+            "summary_large_image"
+        } else {
+            "summary"
+        };
+        let mut tags = vec![
+            meta("property", "og:type", "website"),
+            meta("property", "og:url", url),
+            meta("name", "twitter:card", card),
+        ];
+        if let Some(title) = &self.title {
+            tags.push(meta("property", "og:title", title));
+            tags.push(meta("name", "twitter:title", title));
+        }
+        if let Some(description) = &self.description {
+            tags.push(meta("name", "description", description));
+            tags.push(meta("property", "og:description", description));
+            tags.push(meta("name", "twitter:description", description));
+        }
+        if let Some(image) = &self.image {
+            tags.push(meta("property", "og:image", image));
+            tags.push(meta("name", "twitter:image", image));
+        }
+        tags
+    }
+}
+
+fn meta(attribute: &str, name: &str, content: &str) -> String {
+    format!(
+        "<meta {}=\"{}\" content=\"{}\">",
+        attribute,
+        name,
+        escape_html(content)
+    )
+}
+
+/// Fetches the target page and reads its title, description and image.
+pub async fn fetch_preview(url: &str, timeout_seconds: u64) -> Result<LinkPreview> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(timeout_seconds))
+        .build()?;
+
+    let mut response = client
+        .get(url)
+        .header("User-Agent", format!("pst/{}", env!("CARGO_PKG_VERSION")))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Failed to fetch {}", url))?;
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        if body.len() >= MAX_PREVIEW_BYTES {
+            break;
+        }
+    }
+
+    Ok(parse_preview(&String::from_utf8_lossy(&body)))
+}
+
+/// Reads OpenGraph/Twitter tags from a page, falling back to `<title>` and the description.
+pub fn parse_preview(html: &str) -> LinkPreview {
+    let meta_tag = Regex::new(r"(?is)<meta\s[^>]*>").expect("valid meta regex");
+    let attribute = Regex::new(r#"(?is)([a-z:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#)
+        .expect("valid attribute regex");
+
+    let mut tags = Vec::new();
+    for tag in meta_tag.find_iter(html) {
+        let mut key = None;
+        let mut content = None;
+        for captures in attribute.captures_iter(tag.as_str()) {
+            let value = captures.get(2).or(captures.get(3)).map(|m| m.as_str());
+            match captures[1].to_lowercase().as_str() {
+                "property" | "name" => key = value.map(|v| v.to_lowercase()),
+                "content" => content = value.map(decode_entities),
+                _ => {}
+            }
+        }
+        if let (Some(key), Some(content)) = (key, content) {
+            if !content.trim().is_empty() {
+                tags.push((key, content.trim().to_string()));
+            }
+        }
+    }
+
+    let find = |names: &[&str]| {
+        names.iter().find_map(|name| {
+            tags.iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, content)| content.clone())
+        })
+    };
+
+    let title_tag = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").expect("valid title regex");
+    let title = find(&["og:title", "twitter:title"]).or_else(|| {
+        title_tag
+            .captures(html)
+            .map(|c| decode_entities(c[1].trim()))
+            .filter(|t| !t.is_empty())
+    });
+
+    LinkPreview {
+        title,
+        description: find(&["og:description", "twitter:description", "description"]),
+        image: find(&["og:image", "og:image:url", "twitter:image"]),
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_preview_prefers_opengraph() {
+        let html = r#"<html><head>
+            <title>Fallback</title>
+            <meta property="og:title" content="Release 1.0 &amp; more">
+            <meta name='description' content='Plain description'>
+            <meta content="https://example.com/card.png" property="og:image" />
+        </head></html>"#;
+
+        let preview = parse_preview(html);
+        assert_eq!(preview.title.as_deref(), Some("Release 1.0 & more"));
+        assert_eq!(preview.description.as_deref(), Some("Plain description"));
+        assert_eq!(
+            preview.image.as_deref(),
+            Some("https://example.com/card.png")
+        );
+    }
+
+    #[test]
+    fn test_parse_preview_falls_back_to_title_tag() {
+        let preview = parse_preview("<title>\n  Just a page\n</title>");
+        assert_eq!(preview.title.as_deref(), Some("Just a page"));
+        assert!(preview.image.is_none());
+    }
+
+    #[test]
+    fn test_meta_tags_are_escaped() {
+        let preview = LinkPreview {
+            title: Some("\"><script>x</script>".to_string()),
+            ..Default::default()
+        };
+        let tags = preview.meta_tags("https://example.com/").join("\n");
+        assert!(tags.contains("content=\"&quot;&gt;&lt;script&gt;x&lt;/script&gt;\""));
+        assert!(tags.contains("content=\"summary\""));
+        assert!(LinkPreview::default().meta_tags("x").is_empty());
+    }
+
+    #[test]
+    fn test_explicit_values_win_over_fetched() {
+        let explicit = LinkPreview {
+            title: Some("Mine".to_string()),
+            ..Default::default()
+        };
+        let fetched = LinkPreview {
+            title: Some("Theirs".to_string()),
+            description: Some("Fetched".to_string()),
+            image: None,
+        };
+        let merged = explicit.or(fetched);
+        assert_eq!(merged.title.as_deref(), Some("Mine"));
+        assert_eq!(merged.description.as_deref(), Some("Fetched"));
+    }
+}
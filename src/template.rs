@@ -0,0 +1,277 @@
+use crate::models::{format_bytes, UploadResponse};
+use crate::render::escape_html;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+/// Placeholders available in output templates.
+const FIELDS: [&str; 13] = [
+    "url",
+    "name",
+    "filename",
+    "size",
+    "size_human",
+    "mime_type",
+    "type",
+    "provider",
+    "sha256",
+    "expires",
+    "delete_token",
+    "raw_url",
+    "qr_url",
+];
+
+/// Markup for pasting a link into chat, issues and wikis. Images are embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Markup {
+    Markdown,
+    Html,
+    Bbcode,
+    Org,
+}
+
+/// Part of a custom template.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    Text(String),
+    Field(&'static str),
+}
+
+/// How the link is printed and copied, chosen with `--format` or `--template`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputTemplate {
+    Markup(Markup),
+    Custom(Vec<Segment>),
+}
+
+impl OutputTemplate {
+    /// Resolves a `--format` name: a built-in markup, or a template from `[templates]`.
+    pub fn named(name: &str, templates: &HashMap<String, String>) -> Result<Self> {
+        let markup = match name.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Markup::Markdown,
+            "html" => Markup::Html,
+            "bbcode" => Markup::Bbcode,
+            "org" => Markup::Org,
+            _ => match templates.get(name) {
+                Some(template) => return Self::parse(template),
+                None => bail!(
+                    "Unknown format '{}'. Use markdown, html, bbcode, org or a template from [templates] in the config",
+                    name
+                ),
+            },
+        };
+        Ok(OutputTemplate::Markup(markup))
+    }
+
+    /// Parses a template such as `![{filename}]({url})`. Use `{{` and `}}` for literal braces.
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("Unclosed '{{{}' in template", name),
+                        }
+                    }
+                    let Some(field) = FIELDS.iter().find(|&&field| field == name) else {
+                        bail!(
+                            "Unknown placeholder {{{}}} in template. Available: {}",
+                            name,
+                            FIELDS.map(|f| format!("{{{}}}", f)).join(", ")
+                        );
+                    };
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Field(field));
+                }
+                '}' => bail!("Unmatched '}}' in template, use '}}}}' for a literal brace"),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(OutputTemplate::Custom(segments))
+    }
+
+    /// Formats a successful upload, or returns `None` when there is no URL.
+    pub fn render(&self, response: &UploadResponse) -> Option<String> {
+        let url = response.url.as_deref()?;
+        let field = |name: &str| field_value(response, name);
+
+        Some(match self {
+            OutputTemplate::Custom(segments) => segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Text(text) => text.clone(),
+                    Segment::Field(name) => field(name),
+                })
+                .collect(),
+            OutputTemplate::Markup(markup) => {
+                let label = Some(field("name"))
+                    .filter(|name| !name.is_empty())
+                    .unwrap_or_else(|| url.to_string());
+                render_markup(*markup, url, &label, is_image(response))
+            }
+        })
+    }
+}
+
+fn render_markup(markup: Markup, url: &str, label: &str, image: bool) -> String {
+    match (markup, image) {
+        (Markup::Markdown, true) => format!("![{}]({})", escape_markdown(label), url),
+        (Markup::Markdown, false) => format!("[{}]({})", escape_markdown(label), url),
+        (Markup::Html, true) => format!(
+            "<img src=\"{}\" alt=\"{}\">",
+            escape_html(url),
+            escape_html(label)
+        ),
+        (Markup::Html, false) => {
+            format!(
+                "<a href=\"{}\">{}</a>",
+                escape_html(url),
+                escape_html(label)
+            )
+        }
+        (Markup::Bbcode, true) => format!("[img]{}[/img]", url),
+        (Markup::Bbcode, false) => format!("[url={}]{}[/url]", url, label),
+        // Org shows links without a description as inline images
+        (Markup::Org, true) => format!("[[{}]]", url),
+        (Markup::Org, false) => format!("[[{}][{}]]", url, label.replace(']', "}")),
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| match c {
+            '[' | ']' | '\\' => vec!['\\', c],
+            c => vec![c],
+        })
+        .collect()
+}
+
+fn is_image(response: &UploadResponse) -> bool {
+    response.metadata.as_ref().is_some_and(|metadata| {
+        metadata.upload_type.as_deref() == Some("image")
+            || metadata
+                .mime_type
+                .as_deref()
+                .is_some_and(|mime| mime.starts_with("image/"))
+    })
+}
+
+fn field_value(response: &UploadResponse, name: &str) -> String {
+    let metadata = response.metadata.clone().unwrap_or_default();
+    let value = match name {
+        "url" => response.url.clone(),
+        "name" => metadata.original_name.or(metadata.filename),
+        "filename" => metadata.filename,
+        "size" => metadata.file_size.map(|size| size.to_string()),
+        "size_human" => metadata.file_size.map(format_bytes),
+        "mime_type" => metadata.mime_type,
+        "type" => metadata.upload_type,
+        "provider" => Some(response.provider.clone()),
+        "sha256" => metadata.sha256,
+        "expires" => metadata.expiration,
+        "delete_token" => metadata.delete_token,
+        "raw_url" | "qr_url" => metadata.provider_specific.get(name).cloned(),
+        _ => None,
+    };
+    value.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ResponseMetadata;
+
+    fn response(name: &str, upload_type: &str) -> UploadResponse {
+        UploadResponse::success(
+            format!("https://0x0.st/Hx3k.{}", name.rsplit('.').next().unwrap()),
+            "0x0.st".to_string(),
+            Some(ResponseMetadata {
+                original_name: Some(name.to_string()),
+                filename: Some("Hx3k".to_string()),
+                file_size: Some(2048),
+                upload_type: Some(upload_type.to_string()),
+                ..Default::default()
+            }),
+        )
+    }
+
+    #[test]
+    fn test_markup_embeds_images_and_links_files() {
+        let image = response("cat.png", "image");
+        let file = response("notes [draft].txt", "paste");
+        let render = |format: &str, response: &UploadResponse| {
+            OutputTemplate::named(format, &HashMap::new())
+                .unwrap()
+                .render(response)
+                .unwrap()
+        };
+
+        assert_eq!(
+            render("markdown", &image),
+            "![cat.png](https://0x0.st/Hx3k.png)"
+        );
+        assert_eq!(
+            render("markdown", &file),
+            r"[notes \[draft\].txt](https://0x0.st/Hx3k.txt)"
+        );
+        assert_eq!(
+            render("html", &image),
+            r#"<img src="https://0x0.st/Hx3k.png" alt="cat.png">"#
+        );
+        assert_eq!(
+            render("bbcode", &image),
+            "[img]https://0x0.st/Hx3k.png[/img]"
+        );
+        assert_eq!(
+            render("org", &file),
+            "[[https://0x0.st/Hx3k.txt][notes [draft}.txt]]"
+        );
+    }
+
+    #[test]
+    fn test_custom_templates() {
+        let mut templates = HashMap::new();
+        templates.insert("slack".to_string(), "<{url}|{name}>".to_string());
+        let image = response("cat.png", "image");
+
+        let slack = OutputTemplate::named("slack", &templates).unwrap();
+        assert_eq!(
+            slack.render(&image).unwrap(),
+            "<https://0x0.st/Hx3k.png|cat.png>"
+        );
+
+        let custom = OutputTemplate::parse("![{filename}]({url}) ({size_human}) {{x}}").unwrap();
+        assert_eq!(
+            custom.render(&image).unwrap(),
+            "![Hx3k](https://0x0.st/Hx3k.png) (2.00 KB) {x}"
+        );
+
+        assert!(OutputTemplate::parse("{nope}").is_err());
+        assert!(OutputTemplate::parse("a } b").is_err());
+        assert!(OutputTemplate::parse("{url").is_err());
+        assert!(OutputTemplate::named("wiki", &templates).is_err());
+        assert_eq!(
+            custom.render(&UploadResponse::failed("x".into(), "e".into())),
+            None
+        );
+    }
+}
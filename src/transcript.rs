@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use regex::bytes::Regex;
+use std::io::Write;
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+/// Options controlling how a command's output is captured.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureOptions {
+    /// Mirror the output to stderr while the command runs
+    pub tee: bool,
+    /// Remove ANSI escape sequences from the captured output
+    pub strip_ansi: bool,
+}
+
+/// Captured output and outcome of a command.
+#[derive(Debug)]
+pub struct Transcript {
+    pub command_line: String,
+    pub output: Vec<u8>,
+    pub status: ExitStatus,
+    pub duration: Duration,
+}
+
+impl Transcript {
+    pub fn success(&self) -> bool {
+        self.status.success()
+    }
+
+    /// Renders the transcript as a paste with a short header.
+    pub fn render(&self) -> Vec<u8> {
+        let mut rendered = format!(
+            "$ {}\n# {}, duration: {:.2}s\n\n",
+            self.command_line,
+            self.status,
+            self.duration.as_secs_f64()
+        )
+        .into_bytes();
+        rendered.extend_from_slice(&self.output);
+        rendered
+    }
+}
+
+/// Runs a command, capturing stdout and stderr interleaved in arrival order.
+pub async fn run(command: &[String], options: &CaptureOptions) -> Result<Transcript> {
+    let (program, arguments) = command.split_first().context("No command given")?;
+    let command_line = command
+        .iter()
+        .map(|arg| quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let started = Instant::now();
+    let mut child = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run command: {}", program))?;
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward_lines(stdout, sender.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_lines(stderr, sender.clone()));
+    }
+    drop(sender);
+
+    let mut output = Vec::new();
+    while let Some(line) = receiver.recv().await {
+        if options.tee {
+            let mut terminal = std::io::stderr();
+            terminal.write_all(&line)?;
+            terminal.flush()?;
+        }
+        output.extend_from_slice(&line);
+    }
+
+    let status = child
+        .wait()
+        .await
+        .with_context(|| format!("Failed to wait for command: {}", program))?;
+
+    if options.strip_ansi {
+        output = strip_ansi(&output);
+    }
+
+    Ok(Transcript {
+        command_line,
+        output,
+        status,
+        duration: started.elapsed(),
+    })
+}
+
+async fn forward_lines<R: AsyncRead + Unpin>(reader: R, sender: mpsc::UnboundedSender<Vec<u8>>) {
+    let mut reader = BufReader::new(reader);
+    loop {
+        let mut line = Vec::new();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Removes ANSI CSI and OSC escape sequences, such as colours and hyperlinks.
+pub fn strip_ansi(data: &[u8]) -> Vec<u8> {
+    let pattern =
+        Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]")
+            .expect("valid ANSI regex");
+    pattern.replace_all(data, &b""[..]).into_owned()
+}
+
+fn quote(arg: &str) -> String {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if is_plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        let colored = b"\x1b[1;31merror\x1b[0m: \x1b]8;;http://x\x07link\x1b]8;;\x07 done";
+        assert_eq!(strip_ansi(colored), b"error: link done".to_vec());
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("cargo"), "cargo");
+        assert_eq!(quote("--features=a,b"), "--features=a,b");
+        assert_eq!(quote("hello world"), "'hello world'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+        assert_eq!(quote(""), "''");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_captures_both_streams_and_status() {
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo out; echo err >&2; exit 3".to_string(),
+        ];
+
+        let transcript = run(&command, &CaptureOptions::default()).await.unwrap();
+
+        assert!(!transcript.success());
+        assert_eq!(transcript.status.code(), Some(3));
+        let output = String::from_utf8(transcript.output.clone()).unwrap();
+        assert!(output.contains("out\n"));
+        assert!(output.contains("err\n"));
+
+        let rendered = String::from_utf8(transcript.render()).unwrap();
+        assert!(rendered.starts_with("$ sh -c 'echo out; echo err >&2; exit 3'\n# exit status: 3"));
+    }
+}
@@ -0,0 +1,343 @@
+use crate::config::ImageTransformConfig;
+use crate::models::format_bytes;
+use anyhow::{anyhow, Context, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+/// Quality used for lossy formats when none is configured.
+pub const DEFAULT_QUALITY: u8 = 80;
+
+/// Format images can be converted to before upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConvertFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Avif,
+}
+
+impl ConvertFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ConvertFormat::Png => "png",
+            ConvertFormat::Jpeg => "jpg",
+            ConvertFormat::Webp => "webp",
+            ConvertFormat::Avif => "avif",
+        }
+    }
+
+    fn from_image_format(format: ImageFormat) -> Option<Self> {
+        match format {
+            ImageFormat::Png => Some(ConvertFormat::Png),
+            ImageFormat::Jpeg => Some(ConvertFormat::Jpeg),
+            ImageFormat::WebP => Some(ConvertFormat::Webp),
+            ImageFormat::Avif => Some(ConvertFormat::Avif),
+            _ => None,
+        }
+    }
+}
+
+/// Result of a transform that made the image smaller.
+#[derive(Debug)]
+pub struct Transformed {
+    pub data: Vec<u8>,
+    pub format: ConvertFormat,
+    /// Human-readable list of what was done, e.g. `resized to 1920x1080`
+    pub steps: Vec<String>,
+    /// The pixels were decoded and encoded again, which leaves no metadata behind
+    pub reencoded: bool,
+}
+
+/// Outcome of running the pipeline, for reporting.
+#[derive(Debug)]
+pub enum TransformOutcome {
+    /// The transformed image is smaller and should be uploaded instead
+    Applied(Transformed),
+    /// The transformed image was not smaller, so the original is kept
+    NotSmaller { original: usize, transformed: usize },
+}
+
+impl TransformOutcome {
+    pub fn report(&self, original: usize) -> String {
+        match self {
+            TransformOutcome::Applied(result) => format!(
+                "Transformed image ({}): {} -> {}",
+                result.steps.join(", "),
+                format_bytes(original as u64),
+                format_bytes(result.data.len() as u64)
+            ),
+            TransformOutcome::NotSmaller {
+                original,
+                transformed,
+            } => format!(
+                "Kept original image: transformed size {} is not smaller than {}",
+                format_bytes(*transformed as u64),
+                format_bytes(*original as u64)
+            ),
+        }
+    }
+}
+
+/// Resizes, auto-rotates and re-encodes an image according to the configuration.
+///
+/// Re-encoding drops all metadata, so EXIF orientation is applied to the pixels first.
+pub fn transform(data: &[u8], config: &ImageTransformConfig) -> Result<TransformOutcome> {
+    let source_format = image::guess_format(data).context("Failed to detect image format")?;
+    if source_format == ImageFormat::Gif {
+        // Decoding would only keep the first frame of an animation
+        return Err(anyhow!("GIF images are not transformed"));
+    }
+
+    let target = match config.format {
+        Some(format) => format,
+        None => ConvertFormat::from_image_format(source_format).ok_or_else(|| {
+            anyhow!(
+                "Cannot re-encode {:?} images; choose a format to convert to",
+                source_format
+            )
+        })?,
+    };
+
+    let mut steps = Vec::new();
+    let needs_decode = config.max_width.is_some()
+        || config.max_height.is_some()
+        || config.quality.is_some()
+        || config
+            .format
+            .is_some_and(|f| Some(f) != ConvertFormat::from_image_format(source_format));
+
+    let encoded = if needs_decode {
+        let mut image = image::load_from_memory(data).context("Failed to decode image")?;
+
+        if config.auto_orient {
+            if let Some(orientation) = crate::exif::read_orientation(data).filter(|&o| o != 1) {
+                image = crate::exif::apply_orientation(image, orientation);
+                steps.push("rotated upright".to_string());
+            }
+        }
+
+        let (width, height) = image.dimensions();
+        let max_width = config.max_width.unwrap_or(u32::MAX);
+        let max_height = config.max_height.unwrap_or(u32::MAX);
+        if width > max_width || height > max_height {
+            image = image.resize(max_width, max_height, FilterType::Lanczos3);
+            steps.push(format!("resized to {}x{}", image.width(), image.height()));
+        }
+
+        if Some(target) != ConvertFormat::from_image_format(source_format) {
+            steps.push(format!("converted to {}", target.extension()));
+        }
+        let quality = config.quality.unwrap_or(DEFAULT_QUALITY);
+        if target != ConvertFormat::Png {
+            steps.push(format!("quality {}", quality));
+        }
+        encode(&image, target, quality)?
+    } else {
+        data.to_vec()
+    };
+
+    let encoded = if target == ConvertFormat::Png && config.optimize_png {
+        steps.push("optimized png".to_string());
+        optimize_png(&encoded)?
+    } else {
+        encoded
+    };
+
+    if encoded.len() >= data.len() {
+        return Ok(TransformOutcome::NotSmaller {
+            original: data.len(),
+            transformed: encoded.len(),
+        });
+    }
+
+    Ok(TransformOutcome::Applied(Transformed {
+        data: encoded,
+        format: target,
+        steps,
+        reencoded: needs_decode,
+    }))
+}
+
+fn encode(image: &DynamicImage, format: ConvertFormat, quality: u8) -> Result<Vec<u8>> {
+    let quality = quality.clamp(1, 100);
+    match format {
+        ConvertFormat::Png => {
+            let mut output = Cursor::new(Vec::new());
+            image.write_to(&mut output, image::ImageOutputFormat::Png)?;
+            Ok(output.into_inner())
+        }
+        ConvertFormat::Jpeg => {
+            // JPEG has no alpha channel
+            let rgb = DynamicImage::ImageRgb8(image.to_rgb8());
+            let mut output = Cursor::new(Vec::new());
+            rgb.write_to(&mut output, image::ImageOutputFormat::Jpeg(quality))?;
+            Ok(output.into_inner())
+        }
+        ConvertFormat::Webp => {
+            let rgba = image.to_rgba8();
+            let encoded = webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                .encode_simple(quality == 100, quality as f32)
+                .map_err(|e| anyhow!("WebP encoding failed: {:?}", e))?;
+            Ok(encoded.to_vec())
+        }
+        ConvertFormat::Avif => {
+            let rgba = image.to_rgba8();
+            let pixels: Vec<ravif::RGBA8> = rgba
+                .pixels()
+                .map(|p| ravif::RGBA8::new(p[0], p[1], p[2], p[3]))
+                .collect();
+            let encoded = ravif::Encoder::new()
+                .with_quality(quality as f32)
+                .with_speed(6)
+                .encode_rgba(ravif::Img::new(
+                    pixels.as_slice(),
+                    rgba.width() as usize,
+                    rgba.height() as usize,
+                ))
+                .map_err(|e| anyhow!("AVIF encoding failed: {}", e))?;
+            Ok(encoded.avif_file)
+        }
+    }
+}
+
+/// Recompresses a PNG losslessly, dropping chunks that don't affect how it looks.
+fn optimize_png(data: &[u8]) -> Result<Vec<u8>> {
+    let mut options = oxipng::Options::from_preset(2);
+    // Text, time and eXIf chunks can hold the author, timestamps and GPS location
+    options.strip = oxipng::StripChunks::Safe;
+    oxipng::optimize_from_memory(data, &options)
+        .map_err(|e| anyhow!("PNG optimization failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A noisy gradient that compresses poorly as PNG, like a screenshot or photo.
+    fn photo_png(width: u32, height: u32) -> Vec<u8> {
+        let image = image::RgbImage::from_fn(width, height, |x, y| {
+            let noise = ((x * 7919 + y * 104729) % 251) as u8;
+            image::Rgb([(x % 256) as u8 ^ noise, (y % 256) as u8, noise])
+        });
+        let mut output = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut output, image::ImageOutputFormat::Png)
+            .unwrap();
+        output.into_inner()
+    }
+
+    fn applied(outcome: TransformOutcome) -> Transformed {
+        match outcome {
+            TransformOutcome::Applied(result) => result,
+            other => panic!("transform was not applied: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resize_and_convert_to_jpeg() {
+        let png = photo_png(400, 200);
+        let result = applied(
+            transform(
+                &png,
+                &ImageTransformConfig {
+                    max_width: Some(100),
+                    format: Some(ConvertFormat::Jpeg),
+                    ..Default::default()
+                },
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(result.format, ConvertFormat::Jpeg);
+        let decoded = image::load_from_memory(&result.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (100, 50));
+        assert_eq!(
+            result.steps,
+            vec!["resized to 100x50", "converted to jpg", "quality 80"]
+        );
+    }
+
+    #[test]
+    fn test_convert_to_webp_and_avif() {
+        let png = photo_png(64, 64);
+        for format in [ConvertFormat::Webp, ConvertFormat::Avif] {
+            let result = applied(
+                transform(
+                    &png,
+                    &ImageTransformConfig {
+                        format: Some(format),
+                        quality: Some(50),
+                        ..Default::default()
+                    },
+                )
+                .unwrap(),
+            );
+            assert_eq!(result.format, format);
+            assert!(result.data.len() < png.len());
+        }
+    }
+
+    #[test]
+    fn test_larger_result_keeps_original() {
+        let mut tiny = Cursor::new(Vec::new());
+        DynamicImage::new_rgb8(4, 4)
+            .write_to(&mut tiny, image::ImageOutputFormat::Png)
+            .unwrap();
+        let tiny = tiny.into_inner();
+
+        let outcome = transform(
+            &tiny,
+            &ImageTransformConfig {
+                format: Some(ConvertFormat::Jpeg),
+                quality: Some(100),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(matches!(outcome, TransformOutcome::NotSmaller { .. }));
+        assert!(outcome
+            .report(tiny.len())
+            .starts_with("Kept original image"));
+    }
+
+    #[test]
+    fn test_auto_orient_rotates_pixels() {
+        let jpeg = crate::exif::jpeg_with_orientation(64, 32, 6);
+        let result = applied(
+            transform(
+                &jpeg,
+                &ImageTransformConfig {
+                    quality: Some(30),
+                    ..Default::default()
+                },
+            )
+            .unwrap(),
+        );
+
+        let decoded = image::load_from_memory(&result.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (32, 64));
+        assert_eq!(result.steps[0], "rotated upright");
+    }
+
+    #[test]
+    fn test_optimize_png_is_lossless() {
+        let png = photo_png(64, 64);
+        let outcome = transform(
+            &png,
+            &ImageTransformConfig {
+                optimize_png: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        if let TransformOutcome::Applied(result) = outcome {
+            let before = image::load_from_memory(&png).unwrap().to_rgb8();
+            let after = image::load_from_memory(&result.data).unwrap().to_rgb8();
+            assert_eq!(before, after);
+        }
+    }
+}